@@ -0,0 +1,53 @@
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::TastyTrade;
+
+/// Income building up in an account that hasn't been paid out yet. Fields
+/// an account's balance doesn't report are left unset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Accruals {
+    /// Interest on the cash sweep so far this month.
+    #[serde(default)]
+    pub accrued_interest: Option<Decimal>,
+    /// Dividends declared on held shares and not yet paid.
+    #[serde(default)]
+    pub pending_dividends: Option<Decimal>,
+}
+
+impl Accruals {
+    pub fn total(&self) -> Decimal {
+        self.accrued_interest.unwrap_or_default() + self.pending_dividends.unwrap_or_default()
+    }
+}
+
+/// An account's balance as downloaded, accruals and all.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Balance {
+    pub cash_balance: Decimal,
+    pub net_liquidating_value: Decimal,
+    #[serde(default)]
+    pub derivative_buying_power: Decimal,
+    #[serde(default)]
+    pub maintenance_excess: Decimal,
+    #[serde(flatten)]
+    pub accruals: Accruals,
+}
+
+/// Downloads the balance of `account`, its figures and accruals in the one
+/// request.
+pub async fn balance(tasty: &TastyTrade, account: &str) -> Result<Balance> {
+    let url = format!("/accounts/{}/balances", account);
+    tasty
+        .get(url)
+        .await
+        .with_context(|| format!("Downloading balances of {}", account))
+}
+
+/// Reads the accruals from the balance of `account`.
+pub async fn fetch(tasty: &TastyTrade, account: String) -> Result<(String, Accruals)> {
+    let found = balance(tasty, &account).await?;
+    Ok((account, found.accruals))
+}
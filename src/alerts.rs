@@ -0,0 +1,151 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Portfolio-wide values a rule can watch.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortfolioMeasure {
+    Profit,
+    NetLiq,
+}
+
+/// An `[[alerts]]` entry of the config. It watches either a symbol's price
+/// or a portfolio total and fires when it crosses `above` or `below`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub symbol: Option<String>,
+    pub portfolio: Option<PortfolioMeasure>,
+    pub above: Option<Decimal>,
+    pub below: Option<Decimal>,
+}
+
+impl Rule {
+    fn describe(&self) -> String {
+        match (&self.symbol, self.portfolio) {
+            (Some(symbol), _) => symbol.clone(),
+            (None, Some(PortfolioMeasure::Profit)) => "portfolio P/L".to_owned(),
+            (None, Some(PortfolioMeasure::NetLiq)) => "portfolio net liq".to_owned(),
+            (None, None) => "nothing".to_owned(),
+        }
+    }
+
+    fn crossed(&self, value: Decimal) -> Option<String> {
+        if let Some(above) = self.above.filter(|above| value > *above) {
+            return Some(format!(
+                "{} is {} (above {})",
+                self.describe(),
+                value,
+                above
+            ));
+        }
+        if let Some(below) = self.below.filter(|below| value < *below) {
+            return Some(format!(
+                "{} is {} (below {})",
+                self.describe(),
+                value,
+                below
+            ));
+        }
+        None
+    }
+}
+
+pub struct Price {
+    pub value: Decimal,
+    /// Whether the price came from a quote rather than the last close.
+    pub live: bool,
+}
+
+/// What the rules are evaluated against.
+pub struct Inputs {
+    /// By tastytrade symbol.
+    pub prices: BTreeMap<String, Price>,
+    pub profit: Decimal,
+    pub net_liq: Decimal,
+    /// Share of positions that have had a live quote.
+    pub live_fraction: f64,
+}
+
+struct RuleState {
+    armed: bool,
+    firing: bool,
+}
+
+/// Evaluates the alert rules, holding each one back until the values it
+/// watches are live so that stale closing prices don't set it off.
+pub struct Alerts {
+    rules: Vec<(Rule, RuleState)>,
+    started: Instant,
+    /// Rules arm after this long even without live data.
+    grace: Duration,
+    /// Portfolio rules arm once this share of positions is live.
+    quote_fraction: f64,
+}
+
+impl Alerts {
+    pub fn new(rules: Vec<Rule>, grace: Duration, quote_fraction: f64) -> Self {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let state = RuleState {
+                    armed: false,
+                    firing: false,
+                };
+                (rule, state)
+            })
+            .collect();
+        Self {
+            rules,
+            started: Instant::now(),
+            grace,
+            quote_fraction,
+        }
+    }
+
+    /// Whether any rule is still waiting for live data.
+    pub fn arming(&self) -> bool {
+        self.rules.iter().any(|(_, state)| !state.armed)
+    }
+
+    /// Evaluates every rule, returning a message for each one that started
+    /// firing. A rule fires again only after its condition clears.
+    pub fn evaluate(&mut self, inputs: &Inputs) -> Vec<String> {
+        let grace_over = self.started.elapsed() >= self.grace;
+        let mut fired = vec![];
+        for (rule, state) in &mut self.rules {
+            let (value, live) = match (&rule.symbol, rule.portfolio) {
+                (Some(symbol), _) => match inputs.prices.get(symbol) {
+                    Some(price) => (Some(price.value), price.live),
+                    None => (None, false),
+                },
+                (None, Some(measure)) => {
+                    let value = match measure {
+                        PortfolioMeasure::Profit => inputs.profit,
+                        PortfolioMeasure::NetLiq => inputs.net_liq,
+                    };
+                    (Some(value), inputs.live_fraction >= self.quote_fraction)
+                }
+                (None, None) => (None, false),
+            };
+            state.armed |= live || grace_over;
+            let value = match value {
+                Some(value) if state.armed => value,
+                _ => continue,
+            };
+            match rule.crossed(value) {
+                Some(message) if !state.firing => {
+                    state.firing = true;
+                    fired.push(message);
+                }
+                Some(_) => {}
+                None => state.firing = false,
+            }
+        }
+        fired
+    }
+}
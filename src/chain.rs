@@ -0,0 +1,253 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tastytrade_rs::{
+    api::{order::Symbol, quote_streaming::DxFeedSymbol},
+    TastyTrade,
+};
+
+use crate::option_symbol::OptionKind;
+
+/// How many of the nearest expirations are shown.
+const EXPIRATIONS: usize = 4;
+
+/// How many strikes are shown, and so streamed, at a time. Both sides of
+/// each, in the selected expiration only.
+pub const VISIBLE_STRIKES: usize = 21;
+
+#[derive(Debug, Deserialize)]
+struct Nested {
+    items: Vec<NestedChain>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NestedChain {
+    expirations: Vec<NestedExpiration>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct NestedExpiration {
+    expiration_date: NaiveDate,
+    strikes: Vec<NestedStrike>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct NestedStrike {
+    strike_price: Decimal,
+    call: String,
+    call_streamer_symbol: String,
+    put: String,
+    put_streamer_symbol: String,
+}
+
+pub struct Contract {
+    pub symbol: Symbol,
+    pub stream_symbol: DxFeedSymbol,
+}
+
+/// What is streamed of a contract in view.
+#[derive(Debug, Clone, Default)]
+pub struct Quote {
+    pub bid: Option<Decimal>,
+    pub ask: Option<Decimal>,
+    pub delta: Option<f64>,
+}
+
+impl Quote {
+    pub fn mid(&self) -> Option<Decimal> {
+        Some((self.bid? + self.ask?) / Decimal::TWO)
+    }
+}
+
+/// The option chain of an underlying as browsed in the chain view, with a
+/// cursor on one contract: a strike and side in the selected expiration.
+pub struct Chain {
+    pub underlying: Symbol,
+    pub expirations: Vec<NaiveDate>,
+    pub strikes: Vec<Decimal>,
+    contracts: BTreeMap<(NaiveDate, Decimal, OptionKind), Contract>,
+    /// Quotes of the contracts currently streamed.
+    pub quotes: BTreeMap<DxFeedSymbol, Quote>,
+    /// Which side of the chain the cursor is on.
+    pub kind: OptionKind,
+    pub strike: usize,
+    pub expiration: usize,
+    /// The contracts subscribed for the view, which are the ones shown.
+    pub streamed: BTreeSet<DxFeedSymbol>,
+}
+
+/// Downloads the chain of `underlying`, keeping the expirations nearest to
+/// `today`.
+pub async fn fetch(tasty: &TastyTrade, underlying: Symbol, today: NaiveDate) -> Result<Chain> {
+    let url = format!("/option-chains/{}/nested", underlying.0);
+    let nested: Nested = tasty
+        .get(url)
+        .await
+        .with_context(|| format!("Downloading the option chain of {}", underlying.0))?;
+    Ok(Chain::new(underlying, nested, today))
+}
+
+/// A chain from the body of a nested chain response, for tests outside
+/// this module.
+#[cfg(test)]
+pub fn from_body(underlying: Symbol, body: &str, today: NaiveDate) -> Chain {
+    Chain::new(underlying, serde_json::from_str(body).unwrap(), today)
+}
+
+impl Chain {
+    /// The chain of `underlying` as listed in the nested response, keeping
+    /// the expirations nearest to `today`.
+    fn new(underlying: Symbol, nested: Nested, today: NaiveDate) -> Self {
+        // Underlyings with several roots list some expirations more than once;
+        // the first root listed is the standard one.
+        let mut by_date: BTreeMap<NaiveDate, Vec<NestedStrike>> = BTreeMap::new();
+        for expiration in nested.items.into_iter().flat_map(|chain| chain.expirations) {
+            if expiration.expiration_date >= today {
+                by_date
+                    .entry(expiration.expiration_date)
+                    .or_insert(expiration.strikes);
+            }
+        }
+
+        let mut contracts = BTreeMap::new();
+        let mut strikes = BTreeSet::new();
+        let expirations: Vec<NaiveDate> = by_date.keys().take(EXPIRATIONS).copied().collect();
+        for (date, listed) in by_date.into_iter().take(EXPIRATIONS) {
+            for strike in listed {
+                strikes.insert(strike.strike_price);
+                let sides = [
+                    (OptionKind::Call, strike.call, strike.call_streamer_symbol),
+                    (OptionKind::Put, strike.put, strike.put_streamer_symbol),
+                ];
+                for (kind, symbol, stream_symbol) in sides {
+                    contracts.insert(
+                        (date, strike.strike_price, kind),
+                        Contract {
+                            symbol: Symbol(symbol),
+                            stream_symbol: DxFeedSymbol(stream_symbol),
+                        },
+                    );
+                }
+            }
+        }
+
+        Chain {
+            underlying,
+            expirations,
+            strikes: strikes.into_iter().collect(),
+            contracts,
+            quotes: BTreeMap::new(),
+            kind: OptionKind::Call,
+            strike: 0,
+            expiration: 0,
+            streamed: BTreeSet::new(),
+        }
+    }
+
+    /// Puts the cursor on the strike nearest to `price`.
+    pub fn center_on(&mut self, price: Decimal) {
+        self.strike = self
+            .strikes
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, strike)| (**strike - price).abs())
+            .map_or(0, |(i, _)| i);
+    }
+
+    /// The strikes shown, around the cursor.
+    pub fn visible_strikes(&self) -> Range<usize> {
+        let len = self.strikes.len();
+        let start = self
+            .strike
+            .saturating_sub(VISIBLE_STRIKES / 2)
+            .min(len.saturating_sub(VISIBLE_STRIKES));
+        start..(start + VISIBLE_STRIKES).min(len)
+    }
+
+    /// The contract at `strike` on the `kind` side of the selected
+    /// expiration.
+    pub fn contract(&self, strike: usize, kind: OptionKind) -> Option<&Contract> {
+        let date = *self.expirations.get(self.expiration)?;
+        let strike = *self.strikes.get(strike)?;
+        self.contracts.get(&(date, strike, kind))
+    }
+
+    pub fn selected(&self) -> Option<&Contract> {
+        self.contract(self.strike, self.kind)
+    }
+
+    pub fn quote(&self, contract: &Contract) -> Option<&Quote> {
+        self.quotes.get(&contract.stream_symbol)
+    }
+
+    /// The streamer symbols of every contract shown.
+    pub fn visible_symbols(&self) -> BTreeSet<DxFeedSymbol> {
+        self.visible_strikes()
+            .flat_map(|strike| {
+                [OptionKind::Call, OptionKind::Put]
+                    .into_iter()
+                    .filter_map(move |kind| {
+                        Some(self.contract(strike, kind)?.stream_symbol.clone())
+                    })
+            })
+            .collect()
+    }
+
+    pub fn up(&mut self) {
+        self.strike = self.strike.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.strike = (self.strike + 1).min(self.strikes.len().saturating_sub(1));
+    }
+
+    pub fn left(&mut self) {
+        self.expiration = self.expiration.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.expiration = (self.expiration + 1).min(self.expirations.len().saturating_sub(1));
+    }
+
+    pub fn toggle_kind(&mut self) {
+        self.kind = match self.kind {
+            OptionKind::Call => OptionKind::Put,
+            OptionKind::Put => OptionKind::Call,
+        };
+    }
+
+    /// Returns whether `symbol` is in view.
+    pub fn apply_quote(&mut self, symbol: &DxFeedSymbol, bid: Decimal, ask: Decimal) -> bool {
+        let quote = match self.quote_mut(symbol) {
+            Some(quote) => quote,
+            None => return false,
+        };
+        quote.bid = Some(bid);
+        quote.ask = Some(ask);
+        true
+    }
+
+    /// Returns whether `symbol` is in view.
+    pub fn apply_delta(&mut self, symbol: &DxFeedSymbol, delta: f64) -> bool {
+        let quote = match self.quote_mut(symbol) {
+            Some(quote) => quote,
+            None => return false,
+        };
+        quote.delta = Some(delta).filter(|delta| delta.is_finite());
+        true
+    }
+
+    fn quote_mut(&mut self, symbol: &DxFeedSymbol) -> Option<&mut Quote> {
+        self.streamed
+            .contains(symbol)
+            .then(|| self.quotes.entry(symbol.clone()).or_default())
+    }
+}
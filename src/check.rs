@@ -0,0 +1,141 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tastytrade_rs::{api::quote_streaming::DxFeedSymbol, dxfeed, TastyTrade};
+
+/// Longest any one step may take, so a hung connection can't keep a cron
+/// job waiting.
+const STEP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Quoted in every session, and the feed sends its last quote on
+/// subscribing even when the market is closed.
+const QUOTE_SYMBOL: &str = "SPY";
+
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+}
+
+impl Report {
+    /// Runs one step against the timeout and prints how it went.
+    async fn step<T, E: Into<anyhow::Error>>(
+        &mut self,
+        name: &str,
+        step: impl Future<Output = Result<T, E>>,
+        describe: impl FnOnce(&T) -> String,
+    ) -> Option<T> {
+        let started = Instant::now();
+        let result = match tokio::time::timeout(STEP_TIMEOUT, step).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(_) => Err(anyhow!("timed out after {}s", STEP_TIMEOUT.as_secs())),
+        };
+        let took = started.elapsed().as_millis();
+        match result {
+            Ok(value) => {
+                self.passed += 1;
+                println!("PASS  {:<16}{:>7}ms  {}", name, took, describe(&value));
+                Some(value)
+            }
+            Err(e) => {
+                self.failed += 1;
+                println!("FAIL  {:<16}{:>7}ms  {:#}", name, took, e);
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, name: &str) {
+        self.failed += 1;
+        println!("SKIP  {}", name);
+    }
+}
+
+/// Logs in and goes through what the app needs at startup, printing a line
+/// per step. Fails when any step did.
+pub async fn run(login: &str, password: &str, sandbox: bool) -> Result<()> {
+    println!(
+        "tastytrade-cli {} self-check{}",
+        env!("CARGO_PKG_VERSION"),
+        if sandbox { " (sandbox)" } else { "" }
+    );
+    let mut report = Report::default();
+    let tasty = report
+        .step("login", TastyTrade::login(login, password, sandbox), |_| {
+            login.to_owned()
+        })
+        .await;
+    match tasty {
+        Some(tasty) => steps(&tasty, &mut report).await,
+        None => {
+            for name in [
+                "accounts",
+                "positions",
+                "account stream",
+                "quote stream",
+                "quote event",
+            ] {
+                report.skip(name);
+            }
+        }
+    }
+    if report.failed > 0 {
+        bail!(
+            "{} of {} checks failed",
+            report.failed,
+            report.failed + report.passed
+        );
+    }
+    println!("All {} checks passed", report.passed);
+    Ok(())
+}
+
+async fn steps(tasty: &TastyTrade, report: &mut Report) {
+    let accounts = report
+        .step("accounts", tasty.accounts(), |accounts| {
+            let numbers: Vec<_> = accounts.iter().map(|account| account.number().0).collect();
+            format!("{} ({})", numbers.len(), numbers.join(", "))
+        })
+        .await;
+    match &accounts {
+        Some(accounts) => {
+            let count = async {
+                let mut count = 0;
+                for account in accounts {
+                    let positions = account.positions().await.with_context(|| {
+                        format!("Downloading positions of {}", account.number().0)
+                    })?;
+                    count += positions.len();
+                }
+                Ok::<_, anyhow::Error>(count)
+            };
+            report
+                .step("positions", count, |count| format!("{} open", count))
+                .await;
+        }
+        None => report.skip("positions"),
+    }
+    report
+        .step("account stream", tasty.create_account_streamer(), |_| {
+            "connected".to_owned()
+        })
+        .await;
+    let streamer = report
+        .step("quote stream", tasty.create_quote_streamer(), |_| {
+            "connected".to_owned()
+        })
+        .await;
+    match streamer {
+        Some(mut streamer) => {
+            let sub = streamer.create_sub(dxfeed::DXF_ET_QUOTE);
+            sub.add_symbols(&[DxFeedSymbol(QUOTE_SYMBOL.to_owned())]);
+            report
+                .step("quote event", sub.get_event(), |event| event.sym.clone())
+                .await;
+        }
+        None => report.skip("quote event"),
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, fs, io, os::unix::fs::PermissionsExt, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tui::style::Color;
+
+use crate::alerts;
+
+/// Settings read from `config.toml` in the user's config directory.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Upper bound on the number of symbols subscribed to on the quote feed.
+    pub max_subscriptions: usize,
+    /// How far the computed net liq may drift from the reported one, in
+    /// dollars, before the difference is highlighted.
+    pub net_liq_tolerance: Decimal,
+    /// Groups whose net liq is smaller than this many dollars either way are
+    /// treated as dust: dimmed, and hidden on request.
+    pub dust_threshold: Option<Decimal>,
+    /// Groups whose net delta, in shares, and net liq, in dollars, are both
+    /// smaller than these either way count as hedged, and can be hidden.
+    pub hedged_delta: f64,
+    pub hedged_net_liq: Decimal,
+    /// Decimals shown in the PORT % column.
+    pub port_percent_decimals: u32,
+    /// Shares of the portfolio smaller than this many percent either way
+    /// show as `<x%` in the PORT % column.
+    pub port_percent_min: Option<Decimal>,
+    /// Markets wider than this many percent of their mid have their
+    /// CURRENT price shown in yellow. Zero turns it off.
+    pub wide_spread_percent: Decimal,
+    /// Seconds a position may go without a streamed quote or greeks during
+    /// regular hours before its price is marked stale. Zero turns it off.
+    pub stale_quote_secs: u64,
+    /// Start with every group expanded, as with `--expanded`.
+    pub expanded: bool,
+    /// Redraws a second at most, as with `--max-fps`.
+    pub max_fps: Option<f64>,
+    /// Seconds between the snapshots sent to viewers, as with
+    /// `--snapshot-interval`.
+    pub snapshot_interval: f64,
+    /// Beta of each underlying, used to beta weight delta in the risk
+    /// summary. Underlyings not listed count as 1.
+    pub betas: BTreeMap<String, f64>,
+    /// Currency of products not priced in dollars, by symbol root, like
+    /// `"/FDAX" = "EUR"`.
+    pub currencies: BTreeMap<String, String>,
+    /// Dollars per unit of each currency in `currencies`. Positions in a
+    /// currency without a rate are left out of the totals.
+    pub fx_rates: BTreeMap<String, Decimal>,
+    pub alerts: Vec<alerts::Rule>,
+    /// Longest an alert rule waits for live quotes before it is armed
+    /// anyway, in seconds.
+    pub alert_grace_secs: u64,
+    /// Share of positions that must have a live quote before rules on
+    /// portfolio totals are armed.
+    pub alert_quote_fraction: f64,
+    /// Where order actions taken from the TUI are logged. Defaults to
+    /// `trade-log.jsonl` in the data directory.
+    pub trade_log: Option<PathBuf>,
+    /// Account numbers to show. All of the login's accounts when empty.
+    pub accounts: Vec<String>,
+    /// Login used when no profile gives one.
+    pub login: Option<String>,
+    pub password: Option<String>,
+    /// Named logins, picked with `--profile`.
+    #[serde(alias = "profiles")]
+    pub profile: BTreeMap<String, Profile>,
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_subscriptions: 500,
+            net_liq_tolerance: Decimal::from(10),
+            dust_threshold: None,
+            hedged_delta: 1.0,
+            hedged_net_liq: Decimal::from(100),
+            port_percent_decimals: 2,
+            port_percent_min: None,
+            wide_spread_percent: Decimal::from(10),
+            stale_quote_secs: 30,
+            expanded: false,
+            max_fps: None,
+            snapshot_interval: 0.5,
+            betas: BTreeMap::new(),
+            currencies: BTreeMap::new(),
+            fx_rates: BTreeMap::new(),
+            alerts: vec![],
+            alert_grace_secs: 60,
+            alert_quote_fraction: 0.9,
+            trade_log: None,
+            accounts: vec![],
+            login: None,
+            password: None,
+            profile: BTreeMap::new(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// A `[profile.NAME]` table. Each profile keeps its own state file and
+/// trade log.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub login: Option<String>,
+    pub password: Option<String>,
+    /// Account numbers to show instead of the top-level `accounts`.
+    pub accounts: Option<Vec<String>>,
+    /// Log into the certification environment, as with `--sandbox`.
+    pub sandbox: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Underlying day moves within this many percent leave the group header
+    /// untinted.
+    pub flat_band: f64,
+    /// Underlying day moves beyond this many percent use the strong tint.
+    pub strong_band: f64,
+    pub up: Color,
+    pub strong_up: Color,
+    pub down: Color,
+    pub strong_down: Color,
+    /// Colors of long and short quantities in the AMOUNT column, and of the
+    /// net direction marker on group headers.
+    pub long: Color,
+    pub short: Color,
+    /// Signs put in front of long and short quantities.
+    pub long_sign: String,
+    pub short_sign: String,
+    /// Markers after a group's symbol when its net delta is long or short.
+    pub net_long: String,
+    pub net_short: String,
+    /// Colors of gains and losses in the profit columns and of day changes
+    /// on the watchlist.
+    pub profit: Color,
+    pub loss: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            flat_band: 0.25,
+            strong_band: 2.0,
+            up: Color::Rgb(0, 60, 0),
+            strong_up: Color::Rgb(0, 110, 0),
+            down: Color::Rgb(70, 0, 0),
+            strong_down: Color::Rgb(130, 0, 0),
+            long: Color::LightGreen,
+            short: Color::LightRed,
+            long_sign: "+".to_owned(),
+            short_sign: "\u{2212}".to_owned(),
+            net_long: "\u{25b2}".to_owned(),
+            net_short: "\u{25bc}".to_owned(),
+            profit: Color::Green,
+            loss: Color::Red,
+        }
+    }
+}
+
+impl Theme {
+    /// Tint for a group whose underlying moved `percent` today, if any.
+    pub fn day_change_color(&self, percent: f64) -> Option<Color> {
+        let magnitude = percent.abs();
+        if magnitude <= self.flat_band {
+            None
+        } else if percent > 0.0 {
+            Some(if magnitude >= self.strong_band {
+                self.strong_up
+            } else {
+                self.up
+            })
+        } else {
+            Some(if magnitude >= self.strong_band {
+                self.strong_down
+            } else {
+                self.down
+            })
+        }
+    }
+
+    /// The sign shown in front of a signed quantity.
+    pub fn sign(&self, quantity: Decimal) -> &str {
+        if quantity.is_zero() {
+            ""
+        } else if quantity.is_sign_negative() {
+            &self.short_sign
+        } else {
+            &self.long_sign
+        }
+    }
+
+    /// The color of a gain or loss, unless it is zero.
+    pub fn pnl_color(&self, value: Decimal) -> Option<Color> {
+        if value.is_zero() {
+            None
+        } else if value.is_sign_negative() {
+            Some(self.loss)
+        } else {
+            Some(self.profit)
+        }
+    }
+
+    /// The color of a signed quantity, unless it is zero.
+    pub fn direction_color(&self, quantity: Decimal) -> Option<Color> {
+        if quantity.is_zero() {
+            None
+        } else if quantity.is_sign_negative() {
+            Some(self.short)
+        } else {
+            Some(self.long)
+        }
+    }
+
+    /// The marker and color for a group with net delta `delta`, if it has
+    /// any.
+    pub fn net_direction(&self, delta: f64) -> Option<(&str, Color)> {
+        if delta > 0.0 {
+            Some((&self.net_long, self.long))
+        } else if delta < 0.0 {
+            Some((&self.net_short, self.short))
+        } else {
+            None
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tastytrade-cli").join("config.toml"))
+    }
+
+    /// Loads the config file, falling back to defaults when there is none.
+    pub fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+        toml::from_str(&contents).with_context(|| format!("Parsing {}", path.display()))
+    }
+
+    /// A warning when the config file holds a password but others can read
+    /// it.
+    pub fn permissions_warning(&self) -> Option<String> {
+        let has_password =
+            self.password.is_some() || self.profile.values().any(|p| p.password.is_some());
+        if !has_password {
+            return None;
+        }
+        let path = Self::path()?;
+        let mode = fs::metadata(&path).ok()?.permissions().mode();
+        if mode & 0o077 == 0 {
+            return None;
+        }
+        Some(format!(
+            "Warning: {} holds a password but has mode {:o}; chmod 600 it",
+            path.display(),
+            mode & 0o777
+        ))
+    }
+
+    /// The profile named `name`, or the only one when no name is given.
+    /// Several profiles and no name means none is used.
+    pub fn select_profile(&self, name: Option<&str>) -> Result<Option<(&str, &Profile)>> {
+        match name {
+            Some(name) => match self.profile.get_key_value(name) {
+                Some((name, profile)) => Ok(Some((name.as_str(), profile))),
+                None => bail!("No profile named {} in the config", name),
+            },
+            None if self.profile.len() == 1 => Ok(self
+                .profile
+                .iter()
+                .next()
+                .map(|(name, profile)| (name.as_str(), profile))),
+            None => Ok(None),
+        }
+    }
+}
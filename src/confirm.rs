@@ -0,0 +1,74 @@
+use crossterm::event::KeyCode;
+use rust_decimal::Decimal;
+
+/// The key that sends an order whose dry run couldn't be made.
+pub const OVERRIDE_KEY: char = '!';
+
+/// What tastytrade's dry run said of an order about to be sent.
+#[derive(Debug, Clone)]
+pub enum DryRun {
+    Pending,
+    /// The order would be accepted. Warnings don't stop it being sent.
+    Passed {
+        /// Negative when buying power goes down.
+        buying_power_effect: Decimal,
+        /// Negative when fees are paid.
+        fees: Option<Decimal>,
+        warnings: Vec<String>,
+    },
+    /// tastytrade refused the order, so it isn't sent.
+    Rejected(String),
+    /// The dry run itself didn't go through. The order may still be sent,
+    /// unchecked, with `OVERRIDE_KEY`.
+    Failed(String),
+}
+
+/// What a key pressed in a confirmation asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Send,
+    /// Back to whatever the order was written in.
+    Back,
+    /// Nothing yet; the dry run is still out.
+    Wait,
+}
+
+/// The last step before an order is sent: its dry run, and the user's
+/// explicit yes. Anything that sends orders goes through one.
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    /// The order in a few words.
+    pub summary: String,
+    pub dry_run: DryRun,
+}
+
+impl Confirmation {
+    pub fn new(summary: String) -> Self {
+        Self {
+            summary,
+            dry_run: DryRun::Pending,
+        }
+    }
+
+    pub fn key(&self, code: KeyCode) -> Answer {
+        match (&self.dry_run, code) {
+            (DryRun::Passed { .. }, KeyCode::Char('y')) => Answer::Send,
+            (DryRun::Failed(_), KeyCode::Char(OVERRIDE_KEY)) => Answer::Send,
+            (DryRun::Pending, KeyCode::Esc) => Answer::Back,
+            (DryRun::Pending, _) => Answer::Wait,
+            _ => Answer::Back,
+        }
+    }
+
+    /// The buying power effect to log with the order, when the dry run
+    /// gave one.
+    pub fn buying_power_effect(&self) -> Option<Decimal> {
+        match self.dry_run {
+            DryRun::Passed {
+                buying_power_effect,
+                ..
+            } => Some(buying_power_effect),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+
+/// Whether a failed login was turned down for want of a one-time code.
+/// The client only passes on the response body, so this goes by its text.
+pub fn needs_otp(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        message.contains("otp") || message.contains("one-time") || message.contains("two-factor")
+    })
+}
+
+/// Passwords are stored in the OS keyring under this service, with the
+/// login as the user. Sandbox logins get their own service, since the
+/// certification environment has separate passwords, and so does each
+/// profile, so two profiles with the same login keep their own passwords.
+fn service(profile: Option<&str>, sandbox: bool) -> String {
+    let service = if sandbox {
+        "tastytrade-cli-sandbox"
+    } else {
+        "tastytrade-cli"
+    };
+    match profile {
+        Some(profile) => format!("{}:{}", service, profile),
+        None => service.to_owned(),
+    }
+}
+
+fn entry(login: &str, profile: Option<&str>, sandbox: bool) -> Result<keyring::Entry> {
+    keyring::Entry::new(&service(profile, sandbox), login).context("Opening the keyring")
+}
+
+/// The password saved for `login` under `profile`. A missing entry and an
+/// unavailable keyring both leave the password to be asked for.
+pub fn saved_password(login: &str, profile: Option<&str>, sandbox: bool) -> Option<String> {
+    entry(login, profile, sandbox).ok()?.get_password().ok()
+}
+
+pub fn save_password(
+    login: &str,
+    profile: Option<&str>,
+    sandbox: bool,
+    password: &str,
+) -> Result<()> {
+    entry(login, profile, sandbox)?
+        .set_password(password)
+        .context("Saving the password to the keyring")
+}
+
+/// Deletes the password saved for `login` under `profile`, returning
+/// whether there was one.
+pub fn forget_password(login: &str, profile: Option<&str>, sandbox: bool) -> Result<bool> {
+    match entry(login, profile, sandbox)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context("Removing the password from the keyring"),
+    }
+}
+
+/// Asks for the password on the terminal without echoing it, until
+/// something is typed. Runs before the terminal is put in raw mode.
+pub fn prompt_password(login: &str) -> Result<String> {
+    loop {
+        let password = rpassword::prompt_password(format!("Password for {}: ", login))
+            .context("Reading the password")?;
+        if !password.is_empty() {
+            return Ok(password);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_are_kept_apart_by_profile() {
+        assert_eq!(service(None, false), "tastytrade-cli");
+        assert_eq!(service(None, true), "tastytrade-cli-sandbox");
+        assert_eq!(service(Some("work"), false), "tastytrade-cli:work");
+        assert_eq!(service(Some("work"), true), "tastytrade-cli-sandbox:work");
+        assert_ne!(service(Some("work"), false), service(Some("home"), false));
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use rust_decimal::Decimal;
+
+/// Columns summed into the totals at the end of a diff.
+const TOTAL_FIELDS: [&str; 2] = ["net_liq", "profit"];
+
+type Key = (String, String);
+
+/// A CSV export, with rows keyed by account and symbol.
+struct Export {
+    headers: BTreeSet<String>,
+    rows: BTreeMap<Key, BTreeMap<String, String>>,
+}
+
+impl Export {
+    fn read(path: &Path) -> Result<Self> {
+        let mut reader =
+            csv::Reader::from_path(path).with_context(|| format!("Opening {}", path.display()))?;
+        let header_row = reader.headers()?.clone();
+        let headers: BTreeSet<String> = header_row.iter().map(str::to_owned).collect();
+        for required in ["account", "symbol"] {
+            if !headers.contains(required) {
+                bail!("{} has no {} column", path.display(), required);
+            }
+        }
+        let mut rows = BTreeMap::new();
+        for record in reader.records() {
+            let record = record.with_context(|| format!("Reading {}", path.display()))?;
+            let fields: BTreeMap<String, String> = header_row
+                .iter()
+                .zip(record.iter())
+                .map(|(header, value)| (header.to_owned(), value.to_owned()))
+                .collect();
+            let key = (fields["account"].clone(), fields["symbol"].clone());
+            rows.insert(key, fields);
+        }
+        Ok(Self { headers, rows })
+    }
+}
+
+fn number(fields: &BTreeMap<String, String>, field: &str) -> Option<Decimal> {
+    fields
+        .get(field)
+        .and_then(|value| Decimal::from_str(value).ok())
+}
+
+fn describe(fields: &BTreeMap<String, String>) -> String {
+    let get = |field: &str| fields.get(field).map_or("?", String::as_str);
+    format!(
+        "{} {}  qty {}  P/L {}",
+        get("account"),
+        get("symbol"),
+        get("quantity"),
+        get("profit")
+    )
+}
+
+/// Compares two exports and prints the positions added, removed and changed
+/// between them. A position counts as changed when its quantity differs or
+/// its P/L moved by more than `threshold`.
+pub fn run(old_path: &Path, new_path: &Path, threshold: Decimal) -> Result<()> {
+    let old = Export::read(old_path)?;
+    let new = Export::read(new_path)?;
+
+    // Files written by different versions may not share every column; the
+    // missing ones are reported and left out of the comparison.
+    let only_old: Vec<_> = old.headers.difference(&new.headers).cloned().collect();
+    let only_new: Vec<_> = new.headers.difference(&old.headers).cloned().collect();
+    if !only_old.is_empty() {
+        println!(
+            "Fields missing from {}: {}",
+            new_path.display(),
+            only_old.join(", ")
+        );
+    }
+    if !only_new.is_empty() {
+        println!(
+            "Fields missing from {}: {}",
+            old_path.display(),
+            only_new.join(", ")
+        );
+    }
+
+    let added: Vec<_> = new
+        .rows
+        .iter()
+        .filter(|(key, _)| !old.rows.contains_key(*key))
+        .collect();
+    let removed: Vec<_> = old
+        .rows
+        .iter()
+        .filter(|(key, _)| !new.rows.contains_key(*key))
+        .collect();
+
+    println!("Added: {}", added.len());
+    for (_, fields) in added {
+        println!("  + {}", describe(fields));
+    }
+    println!("Removed: {}", removed.len());
+    for (_, fields) in removed {
+        println!("  - {}", describe(fields));
+    }
+
+    let mut changed = vec![];
+    for (key, old_fields) in &old.rows {
+        let new_fields = match new.rows.get(key) {
+            Some(fields) => fields,
+            None => continue,
+        };
+        let mut changes = vec![];
+        if let (Some(before), Some(after)) = (
+            number(old_fields, "quantity"),
+            number(new_fields, "quantity"),
+        ) {
+            if before != after {
+                changes.push(format!("qty {} -> {}", before, after));
+            }
+        }
+        if let (Some(before), Some(after)) =
+            (number(old_fields, "profit"), number(new_fields, "profit"))
+        {
+            if (after - before).abs() > threshold {
+                changes.push(format!(
+                    "P/L {} -> {} ({:+})",
+                    before,
+                    after,
+                    after - before
+                ));
+            }
+        }
+        if !changes.is_empty() {
+            changed.push(format!("{} {}  {}", key.0, key.1, changes.join(", ")));
+        }
+    }
+    println!("Changed: {}", changed.len());
+    for line in changed {
+        println!("  ~ {}", line);
+    }
+
+    println!("Totals:");
+    for field in TOTAL_FIELDS {
+        let sum = |export: &Export| {
+            export.headers.contains(field).then(|| {
+                export
+                    .rows
+                    .values()
+                    .filter_map(|fields| number(fields, field))
+                    .sum::<Decimal>()
+            })
+        };
+        match (sum(&old), sum(&new)) {
+            (Some(before), Some(after)) => {
+                println!("  {} {} -> {} ({:+})", field, before, after, after - before)
+            }
+            _ => println!("  {} not in both files", field),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Version of the shapes below, which are what every export is written
+/// from. Bump it whenever a field is added, renamed or removed, or its
+/// meaning changes.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// What a position's quantity counts.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Shares,
+    Contracts,
+    Units,
+}
+
+/// One position. CSV and NDJSON exports are made of these alone, so each
+/// row carries the schema version itself.
+#[derive(Debug, Serialize)]
+pub struct PositionRow<'a> {
+    pub schema_version: u32,
+    pub account: &'a str,
+    pub underlying: &'a str,
+    pub symbol: &'a str,
+    pub quantity: Decimal,
+    pub unit: Unit,
+    pub trade_price: Decimal,
+    pub current: Decimal,
+    pub bid: Option<Decimal>,
+    pub bid_size: Option<f64>,
+    pub ask: Option<Decimal>,
+    pub ask_size: Option<f64>,
+    pub profit: Decimal,
+    /// Premium received at open, negative when paid. Only set for options.
+    pub credit: Option<Decimal>,
+    pub net_liq: Decimal,
+    pub theta: Decimal,
+    pub delta: Decimal,
+    pub note: &'a str,
+}
+
+/// The positions on one underlying with their subtotals.
+#[derive(Debug, Serialize)]
+pub struct GroupRow<'a> {
+    pub underlying: &'a str,
+    pub profit: Decimal,
+    pub credit: Decimal,
+    pub net_liq: Decimal,
+    pub theta: Decimal,
+    pub delta: Decimal,
+    pub positions: Vec<PositionRow<'a>>,
+}
+
+impl<'a> GroupRow<'a> {
+    pub fn new(underlying: &'a str, positions: Vec<PositionRow<'a>>) -> Self {
+        let sum = |field: fn(&PositionRow) -> Decimal| positions.iter().map(field).sum::<Decimal>();
+        Self {
+            underlying,
+            profit: sum(|pos| pos.profit),
+            credit: sum(|pos| pos.credit.unwrap_or_default()),
+            net_liq: sum(|pos| pos.net_liq),
+            theta: sum(|pos| pos.theta),
+            delta: sum(|pos| pos.delta),
+            positions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Totals {
+    pub profit: Decimal,
+    /// Positions plus cash.
+    pub net_liq: Decimal,
+    pub cash: Decimal,
+    pub theta: Decimal,
+    pub delta: Decimal,
+}
+
+/// A whole export, as written by the JSON format.
+#[derive(Debug, Serialize)]
+pub struct Document<'a> {
+    pub schema_version: u32,
+    pub groups: Vec<GroupRow<'a>>,
+    pub totals: Totals,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(groups: Vec<GroupRow<'a>>, cash: Decimal) -> Self {
+        let sum = |field: fn(&GroupRow) -> Decimal| groups.iter().map(field).sum::<Decimal>();
+        let totals = Totals {
+            profit: sum(|group| group.profit),
+            net_liq: sum(|group| group.net_liq) + cash,
+            cash,
+            theta: sum(|group| group.theta),
+            delta: sum(|group| group.delta),
+        };
+        Self {
+            schema_version: SCHEMA_VERSION,
+            groups,
+            totals,
+        }
+    }
+
+    fn positions(&self) -> impl Iterator<Item = &PositionRow<'a>> {
+        self.groups.iter().flat_map(|group| &group.positions)
+    }
+}
+
+pub fn write(path: &Path, format: Format, document: &Document) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Creating {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in document.positions() {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, document)?;
+            out.flush()?;
+        }
+        Format::Ndjson => {
+            for row in document.positions() {
+                serde_json::to_writer(&mut out, row)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keys of `value` serialized as JSON, in order.
+    fn keys(value: &impl Serialize) -> Vec<String> {
+        match serde_json::to_value(value).unwrap() {
+            serde_json::Value::Object(map) => map.keys().cloned().collect(),
+            other => panic!("not an object: {}", other),
+        }
+    }
+
+    fn position() -> PositionRow<'static> {
+        PositionRow {
+            schema_version: SCHEMA_VERSION,
+            account: "5WT00001",
+            underlying: "SPY",
+            symbol: "SPY   240119P00470000",
+            quantity: Decimal::ONE,
+            unit: Unit::Contracts,
+            trade_price: Decimal::ONE,
+            current: Decimal::ONE,
+            bid: None,
+            bid_size: None,
+            ask: None,
+            ask_size: None,
+            profit: Decimal::ZERO,
+            credit: None,
+            net_liq: Decimal::ONE,
+            theta: Decimal::ZERO,
+            delta: Decimal::ZERO,
+            note: "",
+        }
+    }
+
+    /// Fails when a field changes without SCHEMA_VERSION being bumped: the
+    /// key sets below are those of the version they are listed under.
+    /// Bump the version and the expected keys together.
+    #[test]
+    fn key_sets_match_the_schema_version() {
+        assert_eq!(
+            SCHEMA_VERSION, 2,
+            "update the key sets below to the new version"
+        );
+        let document = Document::new(vec![GroupRow::new("SPY", vec![position()])], Decimal::ZERO);
+        let mut position_keys = keys(&position());
+        position_keys.sort();
+        let mut expected = vec![
+            "schema_version",
+            "account",
+            "underlying",
+            "symbol",
+            "quantity",
+            "unit",
+            "trade_price",
+            "current",
+            "bid",
+            "bid_size",
+            "ask",
+            "ask_size",
+            "profit",
+            "credit",
+            "net_liq",
+            "theta",
+            "delta",
+            "note",
+        ];
+        expected.sort_unstable();
+        assert_eq!(position_keys, expected);
+
+        let mut group_keys = keys(&document.groups[0]);
+        group_keys.sort();
+        assert_eq!(
+            group_keys,
+            [
+                "credit",
+                "delta",
+                "net_liq",
+                "positions",
+                "profit",
+                "theta",
+                "underlying"
+            ]
+        );
+
+        let mut total_keys = keys(&document.totals);
+        total_keys.sort();
+        assert_eq!(total_keys, ["cash", "delta", "net_liq", "profit", "theta"]);
+
+        let mut document_keys = keys(&document);
+        document_keys.sort();
+        assert_eq!(document_keys, ["groups", "schema_version", "totals"]);
+    }
+}
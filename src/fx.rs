@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use rust_decimal::Decimal;
+
+use crate::config::Config;
+
+/// The currency of the accounts, which every total is shown in.
+pub const HOME: &str = "USD";
+
+/// Which products are priced in another currency, and the rates to turn
+/// them into dollars. Both come from the config, since tastytrade-rs
+/// passes on no currency with a position.
+#[derive(Debug, Clone)]
+pub struct Fx {
+    currencies: BTreeMap<String, String>,
+    pub rates: BTreeMap<String, Decimal>,
+    /// When the rates were read, which is as fresh as they get.
+    pub loaded_at: DateTime<Local>,
+}
+
+impl Fx {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            currencies: config.currencies.clone(),
+            rates: config.fx_rates.clone(),
+            loaded_at: Local::now(),
+        }
+    }
+
+    /// The currency `symbol` is priced in: the one configured for the
+    /// longest root it starts with, or dollars. Futures options match with
+    /// the leading dot left off.
+    pub fn currency(&self, symbol: &str) -> &str {
+        let symbol = symbol.trim_start_matches('.');
+        self.currencies
+            .iter()
+            .filter(|(root, _)| symbol.starts_with(root.as_str()))
+            .max_by_key(|(root, _)| root.len())
+            .map_or(HOME, |(_, currency)| currency.as_str())
+    }
+
+    /// Dollars per unit of `currency`, if a rate is known.
+    pub fn rate(&self, currency: &str) -> Option<Decimal> {
+        if currency == HOME {
+            Some(Decimal::ONE)
+        } else {
+            self.rates.get(currency).copied()
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::{api::order::Symbol, TastyTrade};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Deliverable {
+    /// "Shares" or "Cash".
+    pub deliverable_type: String,
+    pub amount: Decimal,
+    #[serde(default)]
+    pub symbol: Option<String>,
+}
+
+impl Deliverable {
+    pub fn describe(&self) -> String {
+        match &self.symbol {
+            Some(symbol) if self.deliverable_type != "Cash" => {
+                format!("{} {}", self.amount.normalize(), symbol)
+            }
+            _ => format!("${} cash", self.amount.round_dp(2)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EquityOptionInfo {
+    #[serde(default)]
+    pub deliverables: Vec<Deliverable>,
+}
+
+/// Options adjusted after a corporate action trade under a new root with a
+/// digit appended, e.g. `AAPL1`.
+pub fn is_adjusted_root(root: &str) -> bool {
+    root.ends_with(|c: char| c.is_ascii_digit())
+}
+
+pub async fn equity_option(tasty: &TastyTrade, symbol: &Symbol) -> Result<EquityOptionInfo> {
+    let url = format!(
+        "/instruments/equity-options/{}",
+        symbol.0.replace(' ', "%20")
+    );
+    tasty
+        .get(url)
+        .await
+        .with_context(|| format!("Looking up deliverables for {}", symbol.0))
+}
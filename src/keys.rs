@@ -0,0 +1,331 @@
+use crossterm::event::KeyCode;
+
+/// What a key does in the table. Popups with keys of their own, like the
+/// ladder and the chain, see keys before these do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Help,
+    Notifications,
+    Orders,
+    ClosePosition,
+    Roll,
+    AcknowledgeAlerts,
+    Risk,
+    WhatIf,
+    Chain,
+    Detail,
+    Close,
+    Ladder,
+    Stream,
+    Next,
+    Previous,
+    First,
+    Last,
+    PageUp,
+    PageDown,
+    NextTab,
+    PreviousTab,
+    /// The tab numbered by the digit pressed.
+    PickTab,
+    ToggleGroup,
+    OpenAll,
+    CloseAll,
+    Strategy,
+    Sort,
+    ReverseSort,
+    Dust,
+    Hedged,
+    PerLotGreeks,
+    GreekColumns,
+    Nested,
+    Pause,
+    Note,
+    Filter,
+    Export,
+    RetrySubscriptions,
+    Resubscribe,
+    Refresh,
+    ReloadWatchlist,
+    Watch,
+    Unwatch,
+}
+
+pub struct Binding {
+    pub keys: &'static [KeyCode],
+    /// How the keys are written in the help.
+    pub label: &'static str,
+    pub description: &'static str,
+    pub command: Command,
+}
+
+const fn bind(
+    keys: &'static [KeyCode],
+    label: &'static str,
+    description: &'static str,
+    command: Command,
+) -> Binding {
+    Binding {
+        keys,
+        label,
+        description,
+        command,
+    }
+}
+
+/// Every key of the table, in the order the help lists them. Keys are
+/// dispatched from here too, so the help can't leave one out.
+pub const BINDINGS: &[Binding] = &[
+    bind(
+        &[KeyCode::Up, KeyCode::Char('k')],
+        "Up k",
+        "previous line",
+        Command::Previous,
+    ),
+    bind(
+        &[KeyCode::Down, KeyCode::Char('j')],
+        "Down j",
+        "next line",
+        Command::Next,
+    ),
+    bind(
+        &[KeyCode::PageUp],
+        "PageUp",
+        "a screenful up",
+        Command::PageUp,
+    ),
+    bind(
+        &[KeyCode::PageDown],
+        "PageDown",
+        "a screenful down",
+        Command::PageDown,
+    ),
+    bind(
+        &[KeyCode::Home, KeyCode::Char('g')],
+        "Home g",
+        "first line",
+        Command::First,
+    ),
+    bind(
+        &[KeyCode::End, KeyCode::Char('G')],
+        "End G",
+        "last line",
+        Command::Last,
+    ),
+    bind(
+        &[KeyCode::Char(' ')],
+        "Space",
+        "open or close the group",
+        Command::ToggleGroup,
+    ),
+    bind(
+        &[KeyCode::Char('o')],
+        "o",
+        "open every group",
+        Command::OpenAll,
+    ),
+    bind(
+        &[KeyCode::Char('c')],
+        "c",
+        "close every group",
+        Command::CloseAll,
+    ),
+    bind(
+        &[KeyCode::Char('s')],
+        "s",
+        "cycle the sort column",
+        Command::Sort,
+    ),
+    bind(
+        &[KeyCode::Char('S')],
+        "S",
+        "reverse the sort",
+        Command::ReverseSort,
+    ),
+    bind(&[KeyCode::Tab], "Tab", "next account tab", Command::NextTab),
+    bind(
+        &[KeyCode::BackTab],
+        "Shift-Tab",
+        "previous account tab",
+        Command::PreviousTab,
+    ),
+    bind(
+        &[
+            KeyCode::Char('0'),
+            KeyCode::Char('1'),
+            KeyCode::Char('2'),
+            KeyCode::Char('3'),
+            KeyCode::Char('4'),
+            KeyCode::Char('5'),
+            KeyCode::Char('6'),
+            KeyCode::Char('7'),
+            KeyCode::Char('8'),
+            KeyCode::Char('9'),
+        ],
+        "0-9",
+        "all accounts, or the account numbered",
+        Command::PickTab,
+    ),
+    bind(
+        &[KeyCode::Enter],
+        "Enter",
+        "detail view (compact layout)",
+        Command::Detail,
+    ),
+    bind(
+        &[KeyCode::Esc],
+        "Esc",
+        "close popups, else clear the filter",
+        Command::Close,
+    ),
+    bind(
+        &[KeyCode::Char('t')],
+        "t",
+        "price the group as a strategy",
+        Command::Strategy,
+    ),
+    bind(
+        &[KeyCode::Char('h')],
+        "h",
+        "hide or show dust",
+        Command::Dust,
+    ),
+    bind(
+        &[KeyCode::Char('z')],
+        "z",
+        "hide or show hedged groups",
+        Command::Hedged,
+    ),
+    bind(
+        &[KeyCode::Char('d')],
+        "d",
+        "greeks per contract or in dollars",
+        Command::PerLotGreeks,
+    ),
+    bind(
+        &[KeyCode::Char('m')],
+        "m",
+        "show or hide the GAMMA and VEGA columns",
+        Command::GreekColumns,
+    ),
+    bind(
+        &[KeyCode::Char('X')],
+        "X",
+        "list option legs under their expirations",
+        Command::Nested,
+    ),
+    bind(
+        &[KeyCode::Char('p')],
+        "p",
+        "pause or resume live updates",
+        Command::Pause,
+    ),
+    bind(
+        &[KeyCode::Char('/')],
+        "/",
+        "filter by symbol",
+        Command::Filter,
+    ),
+    bind(
+        &[KeyCode::Char('n')],
+        "n",
+        "edit the position's note",
+        Command::Note,
+    ),
+    bind(&[KeyCode::Char('v')], "v", "risk summary", Command::Risk),
+    bind(
+        &[KeyCode::Char('w')],
+        "w",
+        "what-if on the underlying",
+        Command::WhatIf,
+    ),
+    bind(
+        &[KeyCode::Char('x')],
+        "x",
+        "expiration ladder",
+        Command::Ladder,
+    ),
+    bind(&[KeyCode::Char('O')], "O", "option chain", Command::Chain),
+    bind(
+        &[KeyCode::Char('D')],
+        "D",
+        "stream and request stats",
+        Command::Stream,
+    ),
+    bind(
+        &[KeyCode::Char('e')],
+        "e",
+        "export positions",
+        Command::Export,
+    ),
+    bind(
+        &[KeyCode::Char('r')],
+        "r",
+        "download positions again",
+        Command::Refresh,
+    ),
+    bind(
+        &[KeyCode::Char('W')],
+        "W",
+        "reload the watchlist",
+        Command::ReloadWatchlist,
+    ),
+    bind(&[KeyCode::Char('+')], "+", "watch a symbol", Command::Watch),
+    bind(
+        &[KeyCode::Char('-')],
+        "-",
+        "stop watching the selected symbol, or one typed in",
+        Command::Unwatch,
+    ),
+    bind(
+        &[KeyCode::F(5), KeyCode::Char('u')],
+        "F5 u",
+        "resubscribe the line's quotes",
+        Command::Resubscribe,
+    ),
+    bind(
+        &[KeyCode::Char('L')],
+        "L",
+        "retry dropped subscriptions",
+        Command::RetrySubscriptions,
+    ),
+    bind(
+        &[KeyCode::Char('a')],
+        "a",
+        "mark alerts read",
+        Command::AcknowledgeAlerts,
+    ),
+    bind(
+        &[KeyCode::Char('b')],
+        "b",
+        "working orders",
+        Command::Orders,
+    ),
+    bind(
+        &[KeyCode::Char('C')],
+        "C",
+        "close the selected position or strategy",
+        Command::ClosePosition,
+    ),
+    bind(
+        &[KeyCode::Char('R')],
+        "R",
+        "roll the selected short option",
+        Command::Roll,
+    ),
+    bind(
+        &[KeyCode::Char('N')],
+        "N",
+        "order notifications",
+        Command::Notifications,
+    ),
+    bind(&[KeyCode::Char('?')], "?", "this help", Command::Help),
+    bind(&[KeyCode::Char('q')], "q", "quit", Command::Quit),
+];
+
+pub fn command(code: KeyCode) -> Option<Command> {
+    BINDINGS
+        .iter()
+        .find(|binding| binding.keys.contains(&code))
+        .map(|binding| binding.command)
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_decimal::{prelude::Zero, Decimal};
+
+use crate::option_symbol::OptionKind;
+
+/// One option leg, with its greeks already scaled to the position.
+pub struct Leg {
+    pub underlying: String,
+    pub expiration: NaiveDate,
+    pub kind: OptionKind,
+    pub strike: Decimal,
+    /// Signed number of shares the leg controls: contracts times multiplier.
+    pub quantity: Decimal,
+    /// Price per share the leg was opened at.
+    pub open: Decimal,
+    pub theta: Decimal,
+    pub delta: Decimal,
+}
+
+impl Leg {
+    /// P/L of holding the leg to expiration with the underlying at `price`.
+    fn expiry_profit(&self, price: Decimal) -> Decimal {
+        let intrinsic = match self.kind {
+            OptionKind::Call => (price - self.strike).max(Decimal::zero()),
+            OptionKind::Put => (self.strike - price).max(Decimal::zero()),
+        };
+        self.quantity * (intrinsic - self.open)
+    }
+}
+
+/// What expires on one date.
+pub struct Expiration {
+    pub date: NaiveDate,
+    pub legs: usize,
+    pub theta: Decimal,
+    pub delta: Decimal,
+    /// The most the legs can lose if held to expiration, as a negative
+    /// number, or `None` when the loss isn't bounded.
+    pub worst_loss: Option<Decimal>,
+}
+
+/// Groups `legs` by expiration, earliest first.
+pub fn summarize(legs: &[Leg]) -> Vec<Expiration> {
+    let mut by_date: BTreeMap<NaiveDate, Vec<&Leg>> = BTreeMap::new();
+    for leg in legs {
+        by_date.entry(leg.expiration).or_default().push(leg);
+    }
+    by_date
+        .into_iter()
+        .map(|(date, legs)| {
+            let mut by_underlying: BTreeMap<&str, Vec<&Leg>> = BTreeMap::new();
+            for leg in &legs {
+                by_underlying.entry(&leg.underlying).or_default().push(leg);
+            }
+            let worst_loss = by_underlying
+                .values()
+                .map(|legs| worst_loss(legs))
+                .sum::<Option<Decimal>>();
+            Expiration {
+                date,
+                legs: legs.len(),
+                theta: legs.iter().map(|leg| leg.theta).sum(),
+                delta: legs.iter().map(|leg| leg.delta).sum(),
+                worst_loss,
+            }
+        })
+        .collect()
+}
+
+/// The lowest P/L at expiration of legs on one underlying. The P/L is linear
+/// between strikes, so it is lowest at zero, at a strike, or without bound
+/// when more calls are short than long.
+fn worst_loss(legs: &[&Leg]) -> Option<Decimal> {
+    let calls: Decimal = legs
+        .iter()
+        .filter(|leg| leg.kind == OptionKind::Call)
+        .map(|leg| leg.quantity)
+        .sum();
+    if calls < Decimal::zero() {
+        return None;
+    }
+    let profit_at =
+        |price: Decimal| -> Decimal { legs.iter().map(|leg| leg.expiry_profit(price)).sum() };
+    let worst = legs
+        .iter()
+        .map(|leg| leg.strike)
+        .chain([Decimal::zero()])
+        .map(profit_at)
+        .min()
+        .unwrap_or_default();
+    Some(worst.min(Decimal::zero()))
+}
@@ -1,430 +1,7627 @@
 #![feature(async_closure)]
 
+mod accruals;
+mod alerts;
+mod chain;
+mod check;
+mod config;
+mod confirm;
+mod credentials;
+mod diff;
+mod export;
+mod fx;
+mod instrument;
+mod keys;
+mod ladder;
+mod market;
+mod marketdata;
+mod notify;
+mod option_symbol;
+mod orders;
+mod permissions;
+mod premium;
+mod readonly;
+mod requests;
+mod risk;
+mod script;
+mod settings;
+mod setup;
+mod share;
+mod state;
+mod streamed;
+mod streamstats;
+mod subscription;
+mod symbolcache;
+mod ticket;
+mod tradelog;
+mod transactions;
+mod watchlist;
+
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, EventStream, KeyCode, KeyEventKind},
+    cursor,
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyCode, KeyEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use futures::{future::LocalBoxFuture, stream::FuturesUnordered, FutureExt};
 use futures_util::StreamExt;
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Stdout,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::signal::unix::{signal, SignalKind};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
     Frame, Terminal,
 };
 
+use serde::{Deserialize, Serialize};
+
 use rust_decimal::{
-    prelude::{FromPrimitive, Zero},
+    prelude::{FromPrimitive, ToPrimitive, Zero},
     Decimal,
 };
 use tastytrade_rs::{
     api::{
         account_streaming::{AccountEvent, AccountMessage},
-        order::Symbol,
-        position::QuantityDirection,
-        quote_streaming::DxFeedSymbol,
+        order::{InstrumentType, LiveOrderRecord, Symbol},
+        position::{BriefPosition, QuantityDirection},
+        quote_streaming::{DxFeedSymbol, QuoteSubscription},
     },
     dxfeed::{self, Event, EventData},
     TastyTrade,
 };
 
+use chrono::{DateTime, Datelike, NaiveDate};
+use chrono_tz::America::New_York;
+use config::{Config, Theme};
+use export::{Document, GroupRow, PositionRow, Unit};
+use instrument::Deliverable;
+use keys::Command as KeyCommand;
+use option_symbol::{OptionKind, OptionSymbol};
+use settings::Settings;
+use share::{GroupSnapshot, RecordSnapshot, Snapshot, WatchSnapshot};
+use state::{note_key, LocalState, Note, SavedLine, View};
+use subscription::{Priority, Subscriptions};
+use transactions::OpenReference;
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    /// tastytrade username or email
-    #[arg(short, long)]
-    login: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// tastytrade username or email. Overrides the profile's and the
+    /// config's.
+    #[arg(short, long, env = "TASTY_LOGIN")]
+    login: Option<String>,
+
+    /// tastytrade password. Overrides the profile's and the config's. Asked
+    /// for when none of them gives one, which keeps it out of the shell
+    /// history.
+    #[arg(short, long, env = "TASTY_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+
+    /// Profile from the config to log in with. Defaults to the only one
+    /// when there is just one.
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Only load this account; repeat for several. Replaces the accounts
+    /// from the config and profile.
+    #[arg(long = "account", value_name = "NUMBER")]
+    accounts: Vec<String>,
+
+    /// Serve the live state to viewers started with --attach, e.g.
+    /// unix:/tmp/tasty.sock
+    #[arg(long, value_name = "ADDRESS")]
+    serve_state: Option<share::Address>,
+
+    /// Show the state served by another instance instead of logging in.
+    /// The view is read-only.
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "serve_state")]
+    attach: Option<share::Address>,
+
+    /// Press keys from a file as if typed, for demos and tests. Each line
+    /// is a delay in milliseconds and a key, like `500 Down`
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// File of symbols to watch, one per line with an optional label. It is
+    /// re-read on SIGHUP or when pressing W.
+    #[arg(long, value_name = "FILE")]
+    watch_file: Option<PathBuf>,
+
+    /// Symbol to watch, listed before the watch file's. Can be given more
+    /// than once: --watch SPY --watch /ES
+    #[arg(long = "watch", value_name = "SYMBOL")]
+    watch: Vec<String>,
+
+    /// Show position notes as a column in the table
+    #[arg(long)]
+    note_column: bool,
+
+    /// Show the net option premium collected on each underlying this year as
+    /// a column in the table
+    #[arg(long)]
+    premium_column: bool,
+
+    /// Show the premium received (positive) or paid at open for each option
+    /// leg as a CREDIT column next to PROFIT
+    #[arg(long)]
+    credit_column: bool,
+
+    /// Start with the GAMMA and VEGA columns shown, as with the `m` key
+    #[arg(long)]
+    greek_columns: bool,
+
+    /// Start with option legs listed under their expirations, as with the
+    /// `X` key
+    #[arg(long)]
+    nested: bool,
+
+    /// Start with every group expanded and the first position selected
+    #[arg(long)]
+    expanded: bool,
+
+    /// Log in, check each step of startup, print a line per step and exit;
+    /// fails with a nonzero exit code if any step does
+    #[arg(long, conflicts_with = "attach")]
+    check: bool,
+
+    /// Never run the first-run setup, even without a config file
+    #[arg(long)]
+    no_setup: bool,
+
+    /// Save the password in the OS keyring once logged in, so that --login
+    /// alone is enough next time
+    #[arg(long)]
+    save_credentials: bool,
+
+    /// Remove the login's password from the OS keyring
+    #[arg(long, conflicts_with = "save_credentials")]
+    forget_credentials: bool,
+
+    /// Log into tastytrade's certification environment instead of the live
+    /// one
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Look up every streamer symbol again instead of using the ones saved
+    /// from earlier runs
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Only read from the account: anything that would change it, or the
+    /// watchlist, is refused.
+    #[arg(long, env = "TASTY_READ_ONLY")]
+    read_only: bool,
+
+    /// Use the narrow layout whatever the terminal's width. It is used
+    /// anyway on terminals narrower than 100 columns.
+    #[arg(long)]
+    compact: bool,
+
+    /// Directory exports are written to
+    #[arg(long, default_value = ".")]
+    export_dir: PathBuf,
+
+    /// File format used for exports
+    #[arg(long, value_enum, default_value_t = export::Format::Csv)]
+    export_format: export::Format,
+
+    /// Redraw at most this many times a second, e.g. 0.2 for once every five
+    /// seconds over a slow link. Unlimited by default.
+    #[arg(long, visible_alias = "tick-rate", value_name = "FPS", value_parser = settings::parse_fps)]
+    max_fps: Option<f64>,
+
+    /// Seconds between the snapshots sent to viewers with --serve-state.
+    /// Defaults to 0.5.
+    #[arg(long, value_name = "SECONDS", value_parser = settings::parse_snapshot_interval)]
+    snapshot_interval: Option<f64>,
 
-    /// tastytrade password
-    #[arg(short, long)]
-    password: String,
+    /// Seconds a position may go without a streamed update during regular
+    /// hours before its price is marked stale, or 0 for never. Overrides
+    /// stale_quote_secs in the config.
+    #[arg(long, value_name = "SECONDS", value_parser = settings::parse_staleness_threshold)]
+    staleness_threshold: Option<f64>,
 }
 
-#[derive(Debug)]
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two CSV exports and print what changed between them
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Smallest P/L change, in dollars, reported for a position
+        #[arg(long, default_value_t = Decimal::ONE)]
+        threshold: Decimal,
+    },
+}
+
+#[derive(Debug, Default)]
 struct SimpleGreeks {
     theta: f64,
     delta: f64,
+    gamma: f64,
+    vega: f64,
+    rho: f64,
+    /// Implied volatility, as a fraction. None until a Greeks event gives
+    /// a number.
+    volatility: Option<f64>,
+}
+
+/// Top of the book from the last Quote event.
+#[derive(Debug)]
+struct BidAsk {
+    bid: Decimal,
+    bid_size: f64,
+    ask: Decimal,
+    ask_size: f64,
+    /// Set for quotes seeded from REST market data at load, until the first
+    /// streamed quote replaces them.
+    delayed: bool,
+}
+
+impl BidAsk {
+    fn is_live(quote: &Option<BidAsk>) -> bool {
+        quote.as_ref().map_or(false, |quote| !quote.delayed)
+    }
+
+    /// How wide the market is, as a percentage of the mid. None without a
+    /// two-sided market to measure.
+    fn spread_percent(&self) -> Option<Decimal> {
+        let mid = (self.bid + self.ask) / Decimal::TWO;
+        if self.bid.is_sign_negative() || self.ask < self.bid || mid.is_zero() {
+            return None;
+        }
+        Some((self.ask - self.bid) * Decimal::from(100) / mid)
+    }
 }
 
 #[derive(Debug)]
 struct PriceRecord {
+    account: String,
     symbol: Symbol,
+    instrument_type: InstrumentType,
     open: Decimal,
     current: Decimal,
     amount: Decimal,
     multiplier: Decimal,
     direction: QuantityDirection,
     greeks: SimpleGreeks,
+    quote: Option<BidAsk>,
+    option: Option<OptionSymbol>,
+    /// What one contract of an adjusted option delivers. Only shown: an
+    /// adjusted contract is still priced with the position's `multiplier`.
+    deliverables: Option<Vec<Deliverable>>,
+    currency: String,
+    /// Dollars per unit of `currency`, when known.
+    fx_rate: Option<Decimal>,
+    /// The previous session's close, from the last Summary event.
+    prev_close: Option<Decimal>,
+    /// When the stream last sent a quote or greeks for the position.
+    updated: Option<Instant>,
 }
 
-#[derive(Default)]
-struct UnderlyingGroup {
-    open: bool,
-    pub records: BTreeMap<DxFeedSymbol, PriceRecord>,
-}
+impl PriceRecord {
+    fn direction_sign(&self) -> Decimal {
+        if let QuantityDirection::Short = self.direction {
+            Decimal::from(-1)
+        } else {
+            Decimal::from(1)
+        }
+    }
 
-struct App {
-    state: TableState,
-    groups: BTreeMap<Symbol, UnderlyingGroup>,
-    num_lines: usize,
-    balances: BTreeMap<String, Decimal>,
-}
+    fn signed_amount(&self) -> Decimal {
+        self.amount * self.direction_sign()
+    }
 
-impl App {
-    fn new(
-        records: BTreeMap<Symbol, UnderlyingGroup>,
-        balances: BTreeMap<String, Decimal>,
-    ) -> Self {
-        let mut this = Self {
-            state: TableState::default(),
-            groups: records,
-            num_lines: 0,
-            balances,
+    /// Whether the stream has gone quiet on the position for longer than
+    /// `cutoff`. Positions it never quoted don't count.
+    fn is_stale(&self, cutoff: Option<Duration>) -> bool {
+        match (self.updated, cutoff) {
+            (Some(updated), Some(cutoff)) => updated.elapsed() > cutoff,
+            _ => false,
+        }
+    }
+
+    fn unit(&self) -> Unit {
+        match self.instrument_type {
+            InstrumentType::Equity => Unit::Shares,
+            InstrumentType::EquityOption
+            | InstrumentType::Future
+            | InstrumentType::FutureOption => Unit::Contracts,
+            _ => Unit::Units,
+        }
+    }
+
+    /// The quantity as shown in the AMOUNT column, with the theme's sign for
+    /// its direction. Contracts are suffixed so they can't be mistaken for a
+    /// share count.
+    fn amount_text(&self, theme: &Theme) -> String {
+        let amount = self.signed_amount().round_dp(5).normalize();
+        let suffix = match self.unit() {
+            Unit::Contracts => "c",
+            Unit::Shares | Unit::Units => "",
         };
+        format!("{}{}{}", theme.sign(amount), amount.abs(), suffix)
+    }
 
-        this.update_num_lines();
-        this
+    /// Scales a per-unit value to the whole position, in dollars. Without
+    /// an exchange rate that comes to nothing, keeping the position out of
+    /// totals rather than mixing currencies.
+    fn to_net(&self, value: Decimal) -> Decimal {
+        (self.native_net(value) * self.fx_rate.unwrap_or_default()).round_dp(2)
     }
 
-    pub fn update_num_lines(&mut self) {
-        self.num_lines = self.groups.values().fold(self.groups.len(), |acc, group| {
-            acc + if group.open { group.records.len() } else { 0 }
-        });
+    /// Like `to_net`, in the currency the position is priced in.
+    fn native_net(&self, value: Decimal) -> Decimal {
+        (value * self.signed_amount() * self.multiplier).round_dp(2)
     }
 
-    pub fn toggle_group(&mut self) {
-        let selected = match self.state.selected() {
-            Some(s) => s,
-            None => return,
-        };
-        let mut i = 0;
-        for group in self.groups.values_mut() {
-            if i == selected {
-                group.open = !group.open;
-                break;
-            }
-            i += 1;
-            if group.open {
-                i += group.records.len()
-            }
+    /// Takes in what an adjusted option delivers. The dollar math keeps
+    /// the multiplier the position came with, since the contract's price is
+    /// quoted against it whatever the deliverable became.
+    fn adjust(&mut self, info: &instrument::EquityOptionInfo) {
+        self.deliverables = Some(info.deliverables.clone());
+    }
+
+    /// The shares one contract delivers on assignment or exercise.
+    fn shares_per_contract(&self) -> Decimal {
+        match &self.deliverables {
+            Some(deliverables) => deliverables
+                .iter()
+                .filter(|deliverable| deliverable.deliverable_type == "Shares")
+                .map(|deliverable| deliverable.amount)
+                .sum(),
+            None => self.multiplier,
         }
-        self.update_num_lines();
     }
 
-    pub fn next(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.num_lines - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.state.select(Some(i));
+    fn set_currency(&mut self, fx: &fx::Fx) {
+        self.currency = fx.currency(&self.symbol.0).to_owned();
+        self.fx_rate = fx.rate(&self.currency);
     }
 
-    pub fn previous(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.num_lines - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
+    fn note_key(&self) -> String {
+        note_key(&self.account, &self.symbol)
+    }
+
+    fn is_expired(&self, today: NaiveDate) -> bool {
+        self.option
+            .as_ref()
+            .map_or(false, |option| option.expiration < today)
+    }
+
+    /// Delta and gamma scaled to the whole position. Shares get no greeks
+    /// events; each one is a delta of 1.
+    fn scaled_delta_gamma(&self) -> (f64, f64) {
+        let delta = match self.unit() {
+            Unit::Shares => 1.0,
+            _ => self.greeks.delta,
         };
-        self.state.select(Some(i));
+        (self.scale(delta), self.scale(self.greeks.gamma))
     }
 
-    pub fn get_record(&mut self, symbol: DxFeedSymbol) -> Option<&mut PriceRecord> {
-        for positions in self.groups.values_mut() {
-            for (pos_symbol, pos) in positions.records.iter_mut() {
-                if *pos_symbol == symbol {
-                    return Some(pos);
-                }
-            }
+    /// P/L since the previous close, once a Summary event has given it.
+    /// Positions opened today count from the close too.
+    fn day_profit(&self) -> Option<Decimal> {
+        Some(self.to_net(self.current - self.prev_close?))
+    }
+
+    /// The day's P/L as a share of what the position was worth at the
+    /// previous close, so shorts gain when the price falls.
+    fn day_percent(&self) -> Option<Decimal> {
+        let base = self.to_net(self.prev_close?).abs();
+        if base.is_zero() {
+            return None;
         }
-        None
+        Some(self.day_profit()? * Decimal::from(100) / base)
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+    /// How the position is named under its `underlying`: options by their
+    /// expiration, strike and kind, anything else by its symbol.
+    fn name(&self, underlying: &Symbol) -> String {
+        if self.symbol == *underlying {
+            return "SHARES".to_owned();
+        }
+        self.option
+            .as_ref()
+            .map_or_else(|| self.symbol.0.clone(), ToString::to_string)
+    }
 
-    println!("Logging in...");
+    /// Calendar days from `today` to the option's expiration.
+    fn days_to_expiration(&self, today: NaiveDate) -> Option<i64> {
+        let option = self.option.as_ref()?;
+        Some((option.expiration - today).num_days())
+    }
 
-    let tasty = TastyTrade::login(&args.login, &args.password, false)
-        .await
-        .context("Logging into tastytrade")?;
+    /// What was paid or received for the position, either way.
+    fn cost_basis(&self) -> Decimal {
+        self.to_net(self.open).abs()
+    }
 
-    println!("Downloading account info...");
+    /// P/L as a percentage of the cost basis, so shorts gain as the price
+    /// falls. None when opened at zero, as some adjustments are.
+    fn profit_percent(&self) -> Option<Decimal> {
+        percent_of(self.to_net(self.current - self.open), self.cost_basis())
+    }
 
-    let account_streamer = tasty.create_account_streamer().await?;
-    let mut positions = Vec::new();
-    let mut balances = BTreeMap::new();
-    for account in tasty.accounts().await.unwrap() {
-        account_streamer.subscribe_to_account(&account).await;
-        positions.extend(account.positions().await.unwrap());
-        balances.insert(account.number().0, account.balance().await?.cash_balance);
+    /// Gamma and vega scaled to the whole position, short ones negative.
+    /// Shares have neither.
+    fn scaled_gamma_vega(&self) -> (f64, f64) {
+        (self.scale(self.greeks.gamma), self.scale(self.greeks.vega))
     }
 
-    println!("Downloading symbols...");
-    let sym_futures = positions
-        .iter()
-        .map(|pos| tasty.get_streamer_symbol(&pos.instrument_type, &pos.symbol));
-
-    let stream_syms = futures::future::join_all(sym_futures).await;
-    let stream_syms: Result<Vec<_>, _> = stream_syms.into_iter().collect();
-    let stream_syms = stream_syms?;
-
-    println!("Setting up records...");
-    let mut records: BTreeMap<Symbol, UnderlyingGroup> = BTreeMap::new();
-    for (pos, stream_sym) in positions.iter().zip(stream_syms.iter()) {
-        let record = PriceRecord {
-            symbol: pos.symbol.clone(),
-            open: pos.average_open_price.round_dp(2),
-            current: pos.close_price.round_dp(2),
-            amount: pos.quantity,
-            multiplier: pos.multiplier,
-            direction: pos.quantity_direction,
-            greeks: SimpleGreeks {
-                theta: 0.0,
-                delta: 0.0,
-            },
+    fn scale(&self, value: f64) -> f64 {
+        self.to_net(Decimal::from_f64(value).unwrap_or_default())
+            .to_f64()
+            .unwrap_or_default()
+    }
+
+    /// Dollars received for an option leg when it was opened, negative when
+    /// they were paid. It only depends on the entry, so it doesn't move with
+    /// quotes.
+    fn open_credit(&self) -> Option<Decimal> {
+        match self.instrument_type {
+            InstrumentType::EquityOption | InstrumentType::FutureOption => {
+                Some(-self.to_net(self.open))
+            }
+            _ => None,
+        }
+    }
+
+    fn net_theta(&self) -> Decimal {
+        self.to_net(Decimal::from_f64(self.greeks.theta).unwrap_or_default())
+    }
+
+    /// Takes a REST quote as the price until the stream has one.
+    fn seed(&mut self, quote: &marketdata::MarketData) {
+        let (bid, ask) = match (quote.bid, quote.ask) {
+            (Some(bid), Some(ask)) => (bid, ask),
+            _ => return,
         };
-        records
-            .entry(pos.underlying_symbol.clone())
-            .or_default()
-            .records
-            .insert(stream_sym.clone(), record);
+        self.current = quote.mark.unwrap_or((bid + ask) / Decimal::TWO).round_dp(2);
+        let size = |size: Option<Decimal>| size.and_then(|size| size.to_f64()).unwrap_or_default();
+        self.quote = Some(BidAsk {
+            bid,
+            bid_size: size(quote.bid_size),
+            ask,
+            ask_size: size(quote.ask_size),
+            delayed: true,
+        });
     }
 
-    print!("Setting up quote streaming...");
-    let mut quote_streamer = tasty.create_quote_streamer().await?;
-    let quote_sub = quote_streamer.create_sub(dxfeed::DXF_ET_QUOTE | dxfeed::DXF_ET_GREEKS);
-    quote_sub.add_symbols(&stream_syms);
+    /// Takes the position details from a fresh download, keeping the live
+    /// quote and greeks.
+    fn update_position(&mut self, pos: PriceRecord) {
+        self.open = pos.open;
+        self.amount = pos.amount;
+        self.direction = pos.direction;
+        // Until the stream has quoted it, a fresher seed replaces the old.
+        if !BidAsk::is_live(&self.quote) && pos.quote.is_some() {
+            self.current = pos.current;
+            self.quote = pos.quote;
+        }
+        self.multiplier = pos.multiplier;
+        // A failed deliverables lookup keeps the adjustment already known.
+        if pos.deliverables.is_some() || self.deliverables.is_none() {
+            self.deliverables = pos.deliverables;
+        }
+    }
+}
 
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+/// A position as downloaded, before it is placed in the table.
+struct LoadedPosition {
+    underlying: Symbol,
+    stream_symbol: DxFeedSymbol,
+    record: PriceRecord,
+}
 
-    let mut app = App::new(records, balances);
-    let mut keyboard_event_stream = EventStream::new();
+/// Everything a position download produces.
+struct LoadedPositions {
+    positions: Vec<LoadedPosition>,
+    /// Streamer symbols of the equity underlyings that were looked up.
+    underlyings: BTreeMap<Symbol, DxFeedSymbol>,
+    balances: BTreeMap<String, AccountBalance>,
+    /// Premium collected by underlying, when it was asked for and could be
+    /// worked out.
+    premium: Option<BTreeMap<String, Decimal>>,
+    /// What each underlying traded at when it was first opened, when the
+    /// transactions could be downloaded.
+    opened: Option<BTreeMap<String, OpenReference>>,
+    /// Lookups that failed without failing the whole download.
+    warnings: Vec<String>,
+}
 
-    loop {
-        tokio::select! {
-            ev = quote_sub.get_event() => {
-                if let Ok(Event { sym, data }) = ev {
-                    if let Some(record) = app.get_record(DxFeedSymbol(sym)) {
-                        match data {
-                            EventData::Quote(quote) => {
-                                record.current = Decimal::from_f64((quote.bid_price + quote.ask_price) / 2.0).unwrap_or_default();
-                            }
-                            EventData::Greeks(greeks) => {
-                                record.greeks = SimpleGreeks {
-                                    theta: greeks.theta,
-                                    delta: greeks.delta,
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            ev = account_streamer.get_event() => {
-                if let Ok(AccountEvent::AccountMessage(msg)) = ev {
-                    if let AccountMessage::AccountBalance(bal) = *msg {
-                        app.balances.insert(bal.account_number.0, bal.cash_balance);
-                    }
-                }
-            }
-            maybe_event = keyboard_event_stream.next() => {
-                match maybe_event {
-                    Some(Ok(event)) => {
-                        if let event::Event::Key(key) = event {
-                            if key.kind == KeyEventKind::Press {
-                                match key.code {
-                                    KeyCode::Char('q') => break,
-                                    KeyCode::Down => app.next(),
-                                    KeyCode::Up => app.previous(),
-                                    KeyCode::Char(' ') => app.toggle_group(),
-                                    _ => {}
-                                }
-                            }
+/// Theta split by whether it comes from options expiring by `week_end`.
+struct ThetaSplit {
+    week_end: NaiveDate,
+    this_week: Decimal,
+    later: Decimal,
+}
 
-                        }
-                    }
-                    Some(Err(e)) => println!("Error: {:?}\r", e),
-                    None => break,
-                }
-            }
+impl ThetaSplit {
+    fn new(week_end: NaiveDate) -> Self {
+        Self {
+            week_end,
+            this_week: Decimal::zero(),
+            later: Decimal::zero(),
         }
+    }
 
-        terminal.draw(|f| ui(f, &mut app))?;
+    fn add(&mut self, rec: &PriceRecord) {
+        let expires_this_week = rec
+            .option
+            .as_ref()
+            .map_or(false, |option| option.expiration <= self.week_end);
+        if expires_this_week {
+            self.this_week += rec.net_theta();
+        } else {
+            self.later += rec.net_theta();
+        }
     }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
-    terminal.show_cursor()?;
+    fn total(&self) -> Decimal {
+        self.this_week + self.later
+    }
+}
 
-    Ok(())
+/// Dollar amounts in the table always show two decimals, so a value keeps
+/// the same text, and the terminal isn't sent the cell again, until it
+/// actually changes.
+fn money(value: Decimal) -> String {
+    format!("{:.2}", value)
 }
 
-fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let rects = Layout::default()
-        .constraints([Constraint::Percentage(100)].as_ref())
-        .margin(2)
-        .split(f.size());
+impl std::fmt::Display for ThetaSplit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} / {}", money(self.this_week), money(self.later))
+    }
+}
 
-    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
-    let normal_style = Style::default().bg(Color::Blue);
-    let header_cells = [
-        "PORT %",
-        "SYMBOL",
-        "CURRENT",
-        "AMOUNT",
-        "TRADE PRICE",
-        "PROFIT",
-        "THETA",
-        "DELTA",
-        "NET LIQ",
-    ]
-    .iter()
-    .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red)));
-    let header = Row::new(header_cells).style(normal_style).height(1);
+/// The parts of an account's balance the app shows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccountBalance {
+    cash: Decimal,
+    /// Net liquidating value as tastytrade computes it.
+    reported_net_liq: Decimal,
+    #[serde(default)]
+    derivative_buying_power: Decimal,
+    #[serde(default)]
+    maintenance_excess: Decimal,
+    #[serde(default)]
+    accruals: accruals::Accruals,
+}
 
-    let mut total = app.groups.iter().fold(Decimal::zero(), |acc, (_, group)| {
-        acc + group.records.iter().fold(Decimal::zero(), |acc, (_, rec)| {
-            acc + (rec.current
-                * rec.amount
-                * rec.multiplier
-                * if let QuantityDirection::Short = rec.direction {
-                    Decimal::from(-1)
-                } else {
-                    Decimal::from(1)
-                })
-            .round_dp(2)
-        })
-    });
+/// Live prices for a group's underlying itself.
+#[derive(Debug)]
+struct UnderlyingQuote {
+    symbol: DxFeedSymbol,
+    price: Option<Decimal>,
+    prev_close: Option<Decimal>,
+    /// Trading is halted, from the last Profile event.
+    halted: bool,
+}
 
-    let mut rows: Vec<Row> = app
-        .groups
-        .iter()
-        .flat_map(|(underlying_symbol, records)| {
-            let mut rows = vec![vec![]];
-            let mut profit_sum = Decimal::zero();
-            let mut net_liq_sum = Decimal::zero();
-            for rec in records.records.values() {
-                let to_net = |value: Decimal| -> Decimal {
-                    (value
-                        * rec.amount
-                        * rec.multiplier
-                        * if let QuantityDirection::Short = rec.direction {
-                            Decimal::from(-1)
-                        } else {
-                            Decimal::from(1)
-                        })
-                    .round_dp(2)
-                };
-                let profit = to_net(rec.current - rec.open);
-                profit_sum += profit;
+impl UnderlyingQuote {
+    fn day_change_percent(&self) -> Option<Decimal> {
+        let prev_close = self.prev_close.filter(|close| !close.is_zero())?;
+        Some((self.price? - prev_close) * Decimal::from(100) / prev_close)
+    }
+}
 
-                let net_liq = to_net(rec.current);
-                net_liq_sum += net_liq;
+/// A symbol from `--watch` or the watch file.
+struct WatchItem {
+    symbol: Symbol,
+    label: Option<String>,
+    quote: UnderlyingQuote,
+    bid: Option<Decimal>,
+    ask: Option<Decimal>,
+}
 
-                if !records.open {
-                    continue;
-                }
-                let theta = to_net(Decimal::from_f64(rec.greeks.theta).unwrap());
-                let delta = to_net(Decimal::from_f64(rec.greeks.delta).unwrap());
+#[derive(Default)]
+struct UnderlyingGroup {
+    open: bool,
+    /// Expirations closed under the group's header while nested.
+    closed_expirations: BTreeSet<NaiveDate>,
+    /// Whether the header shows the option legs combined as one strategy.
+    strategy: bool,
+    pub records: BTreeMap<RecordKey, PriceRecord>,
+    underlying: Option<UnderlyingQuote>,
+}
 
-                let name = if rec.symbol == *underlying_symbol {
-                    "SHARES".to_owned()
-                } else {
-                    rec.symbol.0.clone()
-                };
-                let cells = vec![
-                    ((net_liq * Decimal::from_u64(100).unwrap()) / total)
-                        .round_dp(2)
-                        .to_string()
-                        + "%",
-                    format!(" {}", name),
-                    rec.current.round_dp(2).to_string(),
-                    (rec.amount
-                        * if let QuantityDirection::Short = rec.direction {
-                            Decimal::from(-1)
-                        } else {
-                            Decimal::from(1)
-                        })
-                    .round_dp(5)
-                    .to_string(),
-                    rec.open.to_string(),
-                    profit.to_string(),
-                    theta.to_string(),
-                    delta.to_string(),
-                    net_liq.to_string(),
-                ];
-                rows.push(cells)
-            }
+/// What a group's positions are kept by. Two accounts holding the same
+/// contract are two positions, quoted by the one subscription.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct RecordKey {
+    stream_symbol: DxFeedSymbol,
+    account: String,
+}
 
-            let group_header = rows.get_mut(0).unwrap();
-            group_header.extend(vec![
-                ((net_liq_sum * Decimal::from_u64(100).unwrap()) / total)
-                    .round_dp(2)
-                    .to_string()
-                    + "%",
-                underlying_symbol.0.clone(),
-                "".to_owned(),
-                "".to_owned(),
-                "".to_owned(),
-                profit_sum.round_dp(2).to_string(),
-                "".to_owned(),
-                "".to_owned(),
-                net_liq_sum.round_dp(2).to_string(),
-            ]);
+impl RecordKey {
+    fn new(stream_symbol: &DxFeedSymbol, record: &PriceRecord) -> Self {
+        Self {
+            stream_symbol: stream_symbol.clone(),
+            account: record.account.clone(),
+        }
+    }
+}
 
-            rows.into_iter().map(Row::new)
-        })
-        .collect();
+/// A group's option legs priced as one position.
+struct Strategy {
+    /// How many of the strategy are held: the largest number of contracts
+    /// dividing every leg.
+    units: u64,
+    /// Net mark and net opening price of one unit, summed over the signed
+    /// legs, so credits are negative like a short position's price.
+    mark: Decimal,
+    open: Decimal,
+}
 
-    rows.push(Row::new(vec![""]));
-    rows.push(Row::new(vec!["CASH"]));
-    for (account, balance) in &app.balances {
-        rows.push(Row::new(vec![
-            " ".to_owned() + account,
-            balance.to_string(),
-        ]));
-        total += balance;
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
-    rows.push(Row::new(vec![""]));
-    rows.push(Row::new(vec!["TOTAL".to_owned(), total.to_string()]));
+}
 
-    let t = Table::new(rows)
-        .header(header)
-        .block(Block::default().borders(Borders::ALL))
-        .highlight_style(selected_style)
-        .highlight_symbol(">> ")
-        .widths(&[
-            Constraint::Length(8),
-            Constraint::Length(25),
-            Constraint::Length(12),
-            Constraint::Length(12),
-            Constraint::Length(12),
-            Constraint::Length(12),
-            Constraint::Length(12),
+impl UnderlyingGroup {
+    /// Whether the last Profile event said trading is halted.
+    fn is_halted(&self) -> bool {
+        self.underlying.as_ref().map_or(false, |quote| quote.halted)
+    }
+
+    fn net_liq(&self) -> Decimal {
+        self.records
+            .values()
+            .fold(Decimal::zero(), |acc, rec| acc + rec.to_net(rec.current))
+    }
+
+    /// The option legs combined, if there are at least two.
+    fn strategy(&self) -> Option<Strategy> {
+        let legs: Vec<&PriceRecord> = self
+            .records
+            .values()
+            .filter(|rec| rec.option.is_some())
+            .collect();
+        if legs.len() < 2 {
+            return None;
+        }
+        // Fractional quantities can't be split into units.
+        let units = legs
+            .iter()
+            .map(|rec| rec.amount.to_u64().filter(|_| rec.amount.fract().is_zero()))
+            .try_fold(0, |acc, amount| Some(gcd(acc, amount?)))
+            .filter(|units| *units > 0)
+            .unwrap_or(1);
+        let per_unit = |price: fn(&PriceRecord) -> Decimal| -> Decimal {
+            legs.iter()
+                .map(|rec| rec.signed_amount() * price(rec))
+                .sum::<Decimal>()
+                / Decimal::from(units)
+        };
+        Some(Strategy {
+            units,
+            mark: per_unit(|rec| rec.current),
+            open: per_unit(|rec| rec.open),
+        })
+    }
+
+    /// Net delta in shares of the underlying, counting shares as delta 1.
+    fn net_delta(&self) -> f64 {
+        self.records
+            .values()
+            .map(|rec| rec.scaled_delta_gamma().0)
+            .sum()
+    }
+}
+
+/// What the groups, and the legs within them, are ordered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Symbol,
+    Profit,
+    NetLiq,
+    /// The share of the portfolio either way, so big shorts sort with big
+    /// longs.
+    PortPercent,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Symbol => SortKey::Profit,
+            SortKey::Profit => SortKey::NetLiq,
+            SortKey::NetLiq => SortKey::PortPercent,
+            SortKey::PortPercent => SortKey::Symbol,
+        }
+    }
+
+    /// The title of the column sorted by.
+    fn column(self) -> &'static str {
+        match self {
+            SortKey::Symbol => "SYMBOL",
+            SortKey::Profit => "PROFIT",
+            SortKey::NetLiq => "NET LIQ",
+            SortKey::PortPercent => "PORT %",
+        }
+    }
+
+    /// The value a leg sorts by, for the keys other than the symbol.
+    fn value(self, rec: &PriceRecord) -> Decimal {
+        match self {
+            SortKey::Symbol => Decimal::zero(),
+            SortKey::Profit => rec.to_net(rec.current - rec.open),
+            SortKey::NetLiq => rec.to_net(rec.current),
+            SortKey::PortPercent => rec.to_net(rec.current).abs(),
+        }
+    }
+}
+
+/// A line of the table that can be selected.
+#[derive(PartialEq)]
+enum Selection {
+    Group(Symbol),
+    /// The line over a group's legs expiring on a date, while nested.
+    Expiration(Symbol, NaiveDate),
+    Record(Symbol, RecordKey),
+    Expired,
+    ExpiredRecord(RecordKey),
+    /// The WATCHLIST header.
+    Watchlist,
+    Watch(Symbol),
+}
+
+impl From<&Selection> for SavedLine {
+    fn from(selection: &Selection) -> Self {
+        match selection {
+            Selection::Group(underlying) => SavedLine::Group(underlying.0.clone()),
+            Selection::Expiration(underlying, date) => {
+                SavedLine::Expiration(underlying.0.clone(), *date)
+            }
+            Selection::Record(underlying, key) => {
+                SavedLine::Position(underlying.0.clone(), key.stream_symbol.0.clone())
+            }
+            Selection::Expired => SavedLine::Expired,
+            Selection::ExpiredRecord(key) => {
+                SavedLine::ExpiredPosition(key.stream_symbol.0.clone())
+            }
+            Selection::Watchlist => SavedLine::Watchlist,
+            Selection::Watch(symbol) => SavedLine::Watch(symbol.0.clone()),
+        }
+    }
+}
+
+enum InputTarget {
+    Note(String),
+    WhatIf(Symbol),
+    /// The symbol filter, applied as it is typed.
+    Filter,
+    /// A symbol to add to the watchlist.
+    Watch,
+    /// A watched symbol to take off the watchlist.
+    Unwatch,
+}
+
+/// A hypothetical price for a group's underlying, shown in a popup.
+struct WhatIf {
+    underlying: Symbol,
+    from: Decimal,
+    to: Decimal,
+}
+
+/// Updates held back while the screen is paused. Only the latest of each
+/// is kept, since it replaces the ones before when applied: the last event
+/// of each kind per symbol, the last balance per account, and a viewer's
+/// last snapshot.
+#[derive(Default)]
+struct Held {
+    events: HashMap<(String, std::mem::Discriminant<EventData>), Event>,
+    balances: BTreeMap<String, AccountBalance>,
+    snapshot: Option<Snapshot>,
+}
+
+impl Held {
+    fn len(&self) -> usize {
+        self.events.len() + self.balances.len() + usize::from(self.snapshot.is_some())
+    }
+}
+
+/// Where the account streamer's subscription to an account stands.
+enum AccountStream {
+    Subscribing,
+    Subscribed,
+    Retrying { attempts: u32, error: String },
+}
+
+/// Failed account subscriptions are retried after this, doubling each time
+/// up to `ACCOUNT_RETRY_MAX`.
+const ACCOUNT_RETRY_BASE: Duration = Duration::from_secs(2);
+const ACCOUNT_RETRY_MAX: Duration = Duration::from_secs(300);
+/// After this many failed attempts the warning says balances are stale.
+const ACCOUNT_RETRY_WARN: u32 = 5;
+
+/// Work a keypress asks for that the app can't do on its own.
+enum Action {
+    Quit,
+    RetrySubscriptions,
+    Refresh,
+    ReloadWatchlist,
+    /// Download the option chain of an underlying and browse it.
+    OpenChain(Symbol),
+    /// Bring the quote feed in line with what is wanted.
+    SyncSubscriptions,
+    /// Drop and re-add one symbol's quote subscription.
+    Resubscribe(DxFeedSymbol),
+    /// Cancel the working order with this id, the user having confirmed.
+    CancelOrder(String),
+    /// Check or send the order on the open ticket.
+    Ticket(ticket::Request),
+    /// Look up a symbol typed in to watch.
+    Watch(watchlist::Entry),
+}
+
+/// dxfeed's `dxf_ts_halted` trading status.
+const TRADING_STATUS_HALTED: u32 = 1;
+
+/// Where the optional CREDIT column goes: right after PROFIT.
+const CREDIT_COLUMN: usize = 6;
+
+/// How long the expanded groups and selection have to stay the same before
+/// they are saved.
+const VIEW_SAVE_DELAY: Duration = Duration::from_secs(3);
+
+/// How often an account's accruals are read again as its balance streams.
+/// They only change about once a day.
+const ACCRUAL_RELOAD_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How many of the latest REST requests the stream panel lists.
+const REQUESTS_SHOWN: usize = 5;
+
+/// Terminals narrower than this many columns get the compact layout.
+const COMPACT_WIDTH: u16 = 100;
+
+/// Longest gap between the clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Index of the DAY P/L column, before the CREDIT column is inserted.
+const DAY_COLUMN: usize = 9;
+
+/// Index of the CURRENT column, flagged when the market is wide.
+const CURRENT_COLUMN: usize = 2;
+
+/// Index of the AMOUNT column, which is colored by direction.
+const AMOUNT_COLUMN: usize = 3;
+
+/// Index of the PROFIT column, which is colored by gain or loss.
+const PROFIT_COLUMN: usize = 5;
+
+/// Days of theta counted in the estimated monthly income.
+const INCOME_DAYS: u32 = 30;
+
+/// Frames of the spinner shown while positions are being refreshed.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Single-line text input shown in place of the status line.
+struct Input {
+    prompt: &'static str,
+    buffer: String,
+    target: InputTarget,
+    /// Why what was entered wasn't taken, shown after it.
+    error: Option<String>,
+    /// Whether what was entered is being looked up.
+    busy: bool,
+}
+
+impl Input {
+    fn new(prompt: &'static str, buffer: String, target: InputTarget) -> Self {
+        Self {
+            prompt,
+            buffer,
+            target,
+            error: None,
+            busy: false,
+        }
+    }
+}
+
+/// What an account tab keeps while another one is showing.
+#[derive(Default)]
+struct TabView {
+    state: TableState,
+    table_offset: usize,
+    open_groups: BTreeSet<Symbol>,
+    expired_open: bool,
+}
+
+struct App {
+    state: TableState,
+    /// The account whose tab is showing, or none for all of them.
+    tab: Option<String>,
+    sort_key: SortKey,
+    sort_descending: bool,
+    tab_views: BTreeMap<Option<String>, TabView>,
+    groups: BTreeMap<Symbol, UnderlyingGroup>,
+    /// Option legs past their expiration that the API still reports. They
+    /// are kept out of the live totals.
+    expired: UnderlyingGroup,
+    /// In watch file order.
+    watchlist: Vec<WatchItem>,
+    /// Given with `--watch`, less those that didn't resolve at startup.
+    watch_symbols: Vec<watchlist::Entry>,
+    watch_file: Option<PathBuf>,
+    session_date: NaiveDate,
+    num_lines: usize,
+    balances: BTreeMap<String, AccountBalance>,
+    /// Each account's options approval, where it could be read, for
+    /// checking orders before they are sent.
+    permissions: BTreeMap<String, permissions::Permission>,
+    local_state: LocalState,
+    subscriptions: Subscriptions,
+    theme: Theme,
+    net_liq_tolerance: Decimal,
+    dust_threshold: Option<Decimal>,
+    port_percent_decimals: u32,
+    port_percent_min: Option<Decimal>,
+    wide_spread_percent: Decimal,
+    hedged_delta: f64,
+    hedged_net_liq: Decimal,
+    /// Whether hedged groups are hidden from the table.
+    hide_hedged: bool,
+    /// The groups listed as of the last `update_num_lines`, to tell when
+    /// quotes move one across the dust or hedge thresholds.
+    listed_groups: Vec<Symbol>,
+    /// Whether the groups and selection are still to be set up for the
+    /// first positions.
+    view_pending: bool,
+    /// Whether to start with every group expanded: from --expanded, which
+    /// wins over the saved view, and from the config, which doesn't.
+    expand_flag: bool,
+    expand_default: bool,
+    /// When the view stored in the local state last changed without being
+    /// saved.
+    view_changed: Option<Instant>,
+    /// Whether dust groups are listed at all. They always count towards the
+    /// totals.
+    show_dust: bool,
+    /// Betas of underlyings against the market, for beta weighting.
+    betas: BTreeMap<String, f64>,
+    alerts: alerts::Alerts,
+    /// Alerts fired since they were last acknowledged with `a`.
+    unread_alerts: usize,
+    /// Whether `q` is waiting for the quit to be confirmed.
+    confirm_quit: bool,
+    what_if: Option<WhatIf>,
+    /// The option chain being browsed.
+    chain: Option<chain::Chain>,
+    /// Whether the account streamer is subscribed to each account.
+    account_streams: BTreeMap<String, AccountStream>,
+    /// What the account streamer has sent, for the stream panel.
+    stream_stats: streamstats::StreamStats,
+    /// Outcomes of the REST requests made while running.
+    requests: requests::RequestStats,
+    stream_open: bool,
+    help_open: bool,
+    notices: notify::Notifications,
+    orders: orders::Orders,
+    /// Orders being placed or cancelled that haven't been answered yet.
+    orders_in_flight: usize,
+    /// Whether the working orders pane is open.
+    orders_open: bool,
+    /// The order waiting for the user to confirm its cancellation.
+    confirm_cancel: Option<String>,
+    /// The order being written on a position, which has every key while
+    /// it is open.
+    ticket: Option<ticket::Ticket>,
+    /// The short option being rolled while the chain is open to pick
+    /// where it rolls to.
+    roll: Option<ticket::Roll>,
+    /// The account streamer's position messages already applied.
+    ledger: streamed::Ledger,
+    /// Whether the notification history is open, and how far down it is
+    /// scrolled.
+    notices_open: bool,
+    notice_scroll: usize,
+    /// Table rows that fit on screen at the last draw, for PageUp and
+    /// PageDown.
+    page_rows: usize,
+    /// Where the lines of the table were drawn last, and the first one
+    /// scrolled into view.
+    table_area: Rect,
+    table_offset: usize,
+    /// The line last clicked and when, to tell double clicks.
+    last_click: Option<(usize, Instant)>,
+    /// When each group's greeks last asked for a redraw.
+    greeks_marked: BTreeMap<Symbol, Instant>,
+    /// Set when greeks changed without a redraw being asked for.
+    greeks_pending: bool,
+    /// Cursor of the expiration ladder, while it is open.
+    ladder: Option<usize>,
+    /// Only legs expiring on this date are listed in the table.
+    expiration_filter: Option<NaiveDate>,
+    /// Lists only positions whose symbol or underlying contains this,
+    /// lowercased. Empty lists everything.
+    symbol_filter: String,
+    /// Updates held back while the screen is frozen with `p`.
+    paused: Option<Held>,
+    /// Every order action taken from the TUI is recorded here first.
+    trade_log: tradelog::TradeLog,
+    /// Whether the risk summary popup is showing.
+    risk_open: bool,
+    note_column: bool,
+    premium_column: bool,
+    credit_column: bool,
+    /// Show position rows' greeks per contract or share rather than in
+    /// dollars for the whole position. Group and total rows always use the
+    /// latter.
+    per_lot_greeks: bool,
+    /// Whether the GAMMA and VEGA columns are shown.
+    greek_columns: bool,
+    /// Whether option legs are listed under a line per expiration.
+    nested: bool,
+    /// Set with --read-only.
+    read_only: bool,
+    /// Whether logged into the certification environment.
+    sandbox: bool,
+    fx: fx::Fx,
+    /// Whether the compact layout is forced with --compact.
+    compact: bool,
+    /// Whether the last draw used the compact layout.
+    compact_layout: bool,
+    /// Whether the compact layout shows the detail view instead of the
+    /// table.
+    detail_open: bool,
+    /// Premium collected this year by underlying, as of the last download
+    /// of transactions.
+    premium: BTreeMap<String, Decimal>,
+    opened: BTreeMap<String, OpenReference>,
+    export_dir: PathBuf,
+    export_format: export::Format,
+    input: Option<Input>,
+    status: Option<String>,
+    /// Whether the terminal has focus, as last reported by it.
+    focused: bool,
+    /// When the table was last drawn.
+    last_draw: Option<Instant>,
+    /// Set when something shown has changed since the last draw.
+    dirty: bool,
+    settings: Settings,
+    /// When the position refresh in flight was started.
+    refreshing: Option<Instant>,
+    /// Profile logged in with, if any.
+    profile: Option<String>,
+    /// Set on viewers showing another instance's state.
+    attached_to: Option<share::Address>,
+}
+
+impl App {
+    fn new(
+        profile: Option<String>,
+        accounts: Vec<String>,
+        local_state: LocalState,
+        subscriptions: Subscriptions,
+        config: &Config,
+        settings: Settings,
+        args: &Args,
+    ) -> Self {
+        Self {
+            state: TableState::default(),
+            tab: None,
+            sort_key: SortKey::Symbol,
+            sort_descending: false,
+            tab_views: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            expired: UnderlyingGroup::default(),
+            watchlist: vec![],
+            watch_symbols: args
+                .watch
+                .iter()
+                .filter_map(|symbol| watchlist::entry(symbol))
+                .collect(),
+            watch_file: args.watch_file.clone(),
+            session_date: market::today(),
+            num_lines: 0,
+            balances: BTreeMap::new(),
+            permissions: BTreeMap::new(),
+            local_state,
+            subscriptions,
+            theme: config.theme.clone(),
+            net_liq_tolerance: config.net_liq_tolerance,
+            dust_threshold: config.dust_threshold,
+            port_percent_decimals: config.port_percent_decimals,
+            port_percent_min: config.port_percent_min,
+            wide_spread_percent: config.wide_spread_percent,
+            hedged_delta: config.hedged_delta,
+            hedged_net_liq: config.hedged_net_liq,
+            hide_hedged: false,
+            listed_groups: vec![],
+            view_pending: true,
+            expand_flag: args.expanded,
+            expand_default: config.expanded,
+            view_changed: None,
+            show_dust: true,
+            betas: config.betas.clone(),
+            risk_open: false,
+            what_if: None,
+            chain: None,
+            account_streams: accounts
+                .into_iter()
+                .map(|account| (account, AccountStream::Subscribing))
+                .collect(),
+            stream_stats: streamstats::StreamStats::new(),
+            requests: requests::RequestStats::new(),
+            greeks_marked: BTreeMap::new(),
+            greeks_pending: false,
+            stream_open: false,
+            help_open: false,
+            notices: notify::Notifications::new(),
+            orders: orders::Orders::new(),
+            orders_in_flight: 0,
+            orders_open: false,
+            confirm_cancel: None,
+            ticket: None,
+            roll: None,
+            ledger: streamed::Ledger::default(),
+            notices_open: false,
+            notice_scroll: 0,
+            page_rows: 1,
+            table_area: Rect::default(),
+            table_offset: 0,
+            last_click: None,
+            ladder: None,
+            expiration_filter: None,
+            symbol_filter: String::new(),
+            paused: None,
+            trade_log: tradelog::TradeLog::from_config(config, profile.as_deref()),
+            unread_alerts: 0,
+            confirm_quit: false,
+            alerts: alerts::Alerts::new(
+                config.alerts.clone(),
+                Duration::from_secs(config.alert_grace_secs),
+                config.alert_quote_fraction,
+            ),
+            note_column: args.note_column,
+            premium_column: args.premium_column,
+            credit_column: args.credit_column,
+            per_lot_greeks: false,
+            greek_columns: args.greek_columns,
+            nested: args.nested,
+            read_only: args.read_only,
+            sandbox: args.sandbox,
+            fx: fx::Fx::new(config),
+            compact: args.compact,
+            compact_layout: false,
+            detail_open: false,
+            premium: BTreeMap::new(),
+            opened: BTreeMap::new(),
+            export_dir: args.export_dir.clone(),
+            export_format: args.export_format,
+            input: None,
+            status: None,
+            focused: true,
+            last_draw: None,
+            dirty: true,
+            settings,
+            refreshing: None,
+            attached_to: args.attach.clone(),
+            profile,
+        }
+    }
+
+    /// Whether a position's market is too wide for its mid to mean much.
+    fn is_wide(&self, rec: &PriceRecord) -> bool {
+        !self.wide_spread_percent.is_zero()
+            && rec
+                .quote
+                .as_ref()
+                .and_then(BidAsk::spread_percent)
+                .map_or(false, |percent| percent > self.wide_spread_percent)
+    }
+
+    /// How old a streamed update in `group` may be before it is stale, if
+    /// it can be now. Outside regular hours quiet markets are the norm, and
+    /// nothing trades to quote a halted underlying's legs.
+    fn stale_cutoff(&self, group: &UnderlyingGroup) -> Option<Duration> {
+        self.stale_cutoff_at(group, chrono::Utc::now())
+    }
+
+    /// The cutoff `stale_cutoff` gives at `now`.
+    fn stale_cutoff_at(
+        &self,
+        group: &UnderlyingGroup,
+        now: DateTime<chrono::Utc>,
+    ) -> Option<Duration> {
+        if group.is_halted() {
+            return None;
+        }
+        match market::session(now) {
+            market::Session::Regular { .. } => self.settings.stale_after,
+            _ => None,
+        }
+    }
+
+    pub fn is_dust(&self, group: &UnderlyingGroup) -> bool {
+        self.dust_threshold
+            .map_or(false, |threshold| group.net_liq().abs() < threshold)
+    }
+
+    /// The PORT % cell for `net_liq` out of `total`, rounded as configured,
+    /// or `<x%` under the minimum. Empty while there is no total.
+    fn port_percent(&self, net_liq: Decimal, total: Decimal) -> String {
+        if total.is_zero() {
+            return "".to_owned();
+        }
+        let percent = net_liq * Decimal::from(100) / total;
+        match self.port_percent_min {
+            Some(min) if percent.abs() < min => format!("<{}%", min.normalize()),
+            _ => format!(
+                "{:.*}%",
+                self.port_percent_decimals as usize,
+                percent.round_dp(self.port_percent_decimals)
+            ),
+        }
+    }
+
+    /// Whether a group carries no directional risk to speak of, like a box
+    /// or a collar on shares.
+    pub fn is_hedged(&self, group: &UnderlyingGroup) -> bool {
+        group.net_delta().abs() < self.hedged_delta && group.net_liq().abs() < self.hedged_net_liq
+    }
+
+    /// The groups listed in the table, leaving out dust and hedged groups
+    /// when they're hidden.
+    pub fn visible_groups(&self) -> impl Iterator<Item = (&Symbol, &UnderlyingGroup)> {
+        self.groups.iter().filter(|(_, group)| {
+            (self.show_dust || !self.is_dust(group)) && !(self.hide_hedged && self.is_hedged(group))
+        })
+    }
+
+    pub fn hidden_hedged_count(&self) -> usize {
+        if !self.hide_hedged {
+            return 0;
+        }
+        self.groups
+            .values()
+            .filter(|group| self.is_hedged(group))
+            .count()
+    }
+
+    pub fn toggle_hedged(&mut self) {
+        let selection = self.selection();
+        self.hide_hedged = !self.hide_hedged;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    /// Whether a position is listed under the expiration filter.
+    fn is_listed(&self, rec: &PriceRecord) -> bool {
+        self.expiration_filter.map_or(true, |date| {
+            rec.option
+                .as_ref()
+                .map_or(false, |option| option.expiration == date)
+        })
+    }
+
+    /// Whether `symbol` contains the symbol filter, ignoring case.
+    fn filter_matches(&self, symbol: &Symbol) -> bool {
+        symbol.0.to_lowercase().contains(&self.symbol_filter)
+    }
+
+    /// Whether a position under `underlying` is left in by the symbol
+    /// filter, by its own symbol or the underlying's.
+    fn passes_filter(&self, underlying: &Symbol, rec: &PriceRecord) -> bool {
+        self.filter_matches(underlying) || self.filter_matches(&rec.symbol)
+    }
+
+    /// Whether a position belongs to the account tab showing.
+    fn in_tab(&self, rec: &PriceRecord) -> bool {
+        self.tab
+            .as_ref()
+            .map_or(true, |account| rec.account == *account)
+    }
+
+    /// Every open position in the tab showing, hidden or not.
+    pub fn tab_records(&self) -> impl Iterator<Item = &PriceRecord> {
+        self.groups
+            .values()
+            .flat_map(|group| group.records.values())
+            .filter(|rec| self.in_tab(rec))
+    }
+
+    /// The balances of the accounts in the tab showing.
+    pub fn tab_balances(&self) -> impl Iterator<Item = (&String, &AccountBalance)> {
+        self.balances
+            .iter()
+            .filter(|(account, _)| self.tab.as_ref().map_or(true, |tab| tab == *account))
+    }
+
+    /// The tabs to switch between: all accounts, then each one, when there
+    /// is more than one.
+    fn tabs(&self) -> Vec<Option<String>> {
+        if self.balances.len() < 2 {
+            return vec![None];
+        }
+        std::iter::once(None)
+            .chain(self.balances.keys().cloned().map(Some))
+            .collect()
+    }
+
+    /// Shows the tab `step` places along from the current one, wrapping.
+    fn cycle_tab(&mut self, step: isize) {
+        let tabs = self.tabs();
+        let current = tabs.iter().position(|tab| *tab == self.tab).unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(tabs.len() as isize) as usize;
+        self.switch_tab(tabs[next].clone());
+    }
+
+    /// Shows the `tab`, putting the current one's selection and expanded
+    /// groups aside for when it's shown again. A tab shown for the first
+    /// time keeps the groups as they are, with nothing selected.
+    fn switch_tab(&mut self, tab: Option<String>) {
+        if tab == self.tab {
+            return;
+        }
+        let view = TabView {
+            state: std::mem::take(&mut self.state),
+            table_offset: std::mem::take(&mut self.table_offset),
+            open_groups: self
+                .groups
+                .iter()
+                .filter(|(_, group)| group.open)
+                .map(|(underlying, _)| underlying.clone())
+                .collect(),
+            expired_open: self.expired.open,
+        };
+        self.tab_views.insert(self.tab.take(), view);
+        self.tab = tab;
+        if let Some(view) = self.tab_views.remove(&self.tab) {
+            self.state = view.state;
+            self.table_offset = view.table_offset;
+            for (underlying, group) in self.groups.iter_mut() {
+                group.open = view.open_groups.contains(underlying);
+            }
+            self.expired.open = view.expired_open;
+        }
+        self.update_num_lines();
+    }
+
+    /// The groups listed in the table, in sort order: the visible ones,
+    /// less those with nothing left in the tab or under the filters.
+    /// Everything that maps lines to rows goes through this, so the
+    /// selection follows the sorted order.
+    pub fn table_groups(&self) -> impl Iterator<Item = (&Symbol, &UnderlyingGroup)> {
+        let mut groups: Vec<_> = self
+            .visible_groups()
+            .filter(|(underlying, group)| {
+                group.records.values().any(|rec| {
+                    self.in_tab(rec) && self.passes_filter(underlying, rec) && self.is_listed(rec)
+                })
+            })
+            .collect();
+        // Groups sort by their legs in the tab, summed.
+        let value = |underlying: &Symbol, group: &UnderlyingGroup| -> Decimal {
+            let legs = group
+                .records
+                .values()
+                .filter(|rec| self.in_tab(rec) && self.passes_filter(underlying, rec));
+            match self.sort_key {
+                SortKey::PortPercent => legs
+                    .map(|rec| rec.to_net(rec.current))
+                    .sum::<Decimal>()
+                    .abs(),
+                key => legs.map(|rec| key.value(rec)).sum(),
+            }
+        };
+        if self.sort_key != SortKey::Symbol {
+            groups.sort_by_cached_key(|(underlying, group)| value(underlying, group));
+        }
+        if self.sort_descending {
+            groups.reverse();
+        }
+        groups.into_iter()
+    }
+
+    /// The legs of `underlying`'s group in the tab and through the symbol
+    /// filter, in sort order.
+    pub fn tab_legs<'a>(
+        &'a self,
+        underlying: &Symbol,
+        group: &'a UnderlyingGroup,
+    ) -> Vec<(&'a RecordKey, &'a PriceRecord)> {
+        let mut legs: Vec<_> = group
+            .records
+            .iter()
+            .filter(|(_, rec)| self.in_tab(rec) && self.passes_filter(underlying, rec))
+            .collect();
+        // By symbol they stay in streamer symbol order, as before sorting.
+        if self.sort_key != SortKey::Symbol {
+            let key = self.sort_key;
+            legs.sort_by_cached_key(|(_, rec)| key.value(rec));
+        }
+        if self.sort_descending {
+            legs.reverse();
+        }
+        legs
+    }
+
+    pub fn cycle_sort(&mut self) {
+        let selection = self.selection();
+        self.sort_key = self.sort_key.next();
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    pub fn reverse_sort(&mut self) {
+        let selection = self.selection();
+        self.sort_descending = !self.sort_descending;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    /// Every selectable line of the table, in display order.
+    pub fn lines(&self) -> Vec<Selection> {
+        let listed: Vec<&Symbol> = self
+            .table_groups()
+            .map(|(underlying, _)| underlying)
+            .collect();
+        self.lines_of(&listed)
+    }
+
+    /// The lines of the table if `listed` were the groups in it.
+    fn lines_of(&self, listed: &[&Symbol]) -> Vec<Selection> {
+        let mut lines = vec![];
+        for (underlying, group) in listed
+            .iter()
+            .filter_map(|underlying| self.groups.get_key_value(*underlying))
+        {
+            lines.push(Selection::Group(underlying.clone()));
+            lines.extend(self.group_lines(underlying, group));
+        }
+        let expired: Vec<&RecordKey> = self
+            .expired
+            .records
+            .iter()
+            .filter(|(_, rec)| self.in_tab(rec) && self.filter_matches(&rec.symbol))
+            .map(|(key, _)| key)
+            .collect();
+        if !expired.is_empty() {
+            lines.push(Selection::Expired);
+            if self.expired.open {
+                lines.extend(
+                    expired
+                        .into_iter()
+                        .map(|key| Selection::ExpiredRecord(key.clone())),
+                );
+            }
+        }
+        if !self.watchlist.is_empty() {
+            lines.push(Selection::Watchlist);
+            lines.extend(
+                self.watchlist
+                    .iter()
+                    .map(|item| Selection::Watch(item.symbol.clone())),
+            );
+        }
+        lines
+    }
+
+    /// The lines under a group's header while it is open: its listed
+    /// legs, with the option legs under a line per expiration when nested.
+    fn group_lines(&self, underlying: &Symbol, group: &UnderlyingGroup) -> Vec<Selection> {
+        if !group.open {
+            return vec![];
+        }
+        let legs: Vec<_> = self
+            .tab_legs(underlying, group)
+            .into_iter()
+            .filter(|(_, rec)| self.is_listed(rec))
+            .collect();
+        let record = |key: &RecordKey| Selection::Record(underlying.clone(), key.clone());
+        if !self.nested {
+            return legs.iter().map(|&(key, _)| record(key)).collect();
+        }
+        // Shares and futures stay directly under the header.
+        let mut lines: Vec<Selection> = legs
+            .iter()
+            .filter(|(_, rec)| rec.option.is_none())
+            .map(|&(key, _)| record(key))
+            .collect();
+        let mut expirations: BTreeMap<NaiveDate, Vec<&RecordKey>> = BTreeMap::new();
+        for &(key, rec) in &legs {
+            if let Some(option) = &rec.option {
+                expirations.entry(option.expiration).or_default().push(key);
+            }
+        }
+        for (date, keys) in expirations {
+            lines.push(Selection::Expiration(underlying.clone(), date));
+            if !group.closed_expirations.contains(&date) {
+                lines.extend(keys.into_iter().map(record));
+            }
+        }
+        lines
+    }
+
+    /// Freezes the screen, holding stream updates back, or applies the ones
+    /// held and lets them through again.
+    pub fn toggle_pause(&mut self) {
+        let held = match self.paused.take() {
+            Some(held) => held,
+            None => {
+                self.paused = Some(Held::default());
+                return;
+            }
+        };
+        for (_, event) in held.events {
+            self.apply_quote_event(event);
+        }
+        for (account, balance) in held.balances {
+            self.apply_balance(account, balance);
+        }
+        if let Some(snapshot) = held.snapshot {
+            self.apply_snapshot(snapshot);
+        }
+        self.check_listed_groups();
+    }
+
+    /// Switches between listing a group's legs flat and under their
+    /// expirations.
+    pub fn toggle_nested(&mut self) {
+        let selection = match self.selection() {
+            Some(Selection::Expiration(underlying, _)) => Some(Selection::Group(underlying)),
+            selection => selection,
+        };
+        self.nested = !self.nested;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    pub fn update_num_lines(&mut self) {
+        self.listed_groups = self
+            .table_groups()
+            .map(|(underlying, _)| underlying.clone())
+            .collect();
+        self.num_lines = self.lines().len();
+        if let Some(selected) = self.state.selected() {
+            if selected >= self.num_lines {
+                self.state.select(self.num_lines.checked_sub(1));
+            }
+        }
+    }
+
+    /// Keeps the selection on its line when quotes move a group across the
+    /// dust or hedge thresholds, so that it appears or disappears.
+    pub fn check_listed_groups(&mut self) {
+        let unchanged = self
+            .table_groups()
+            .map(|(underlying, _)| underlying)
+            .eq(self.listed_groups.iter());
+        if unchanged {
+            return;
+        }
+        let before: Vec<&Symbol> = self.listed_groups.iter().collect();
+        let selection = self
+            .state
+            .selected()
+            .and_then(|i| self.lines_of(&before).into_iter().nth(i));
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    pub fn toggle_group(&mut self) {
+        match self.selection() {
+            Some(Selection::Group(underlying)) => {
+                if let Some(group) = self.groups.get_mut(&underlying) {
+                    group.open = !group.open;
+                }
+            }
+            Some(Selection::Expiration(underlying, date)) => {
+                if let Some(group) = self.groups.get_mut(&underlying) {
+                    if !group.closed_expirations.remove(&date) {
+                        group.closed_expirations.insert(date);
+                    }
+                }
+            }
+            Some(Selection::Expired) => self.expired.open = !self.expired.open,
+            _ => return,
+        }
+        self.update_num_lines();
+    }
+
+    /// Opens or closes every group, the expired one too. The selection stays
+    /// on its line, or moves to its group's header when closing hides it.
+    pub fn set_all_open(&mut self, open: bool) {
+        let selection = match self.selection() {
+            Some(Selection::Record(underlying, _) | Selection::Expiration(underlying, _))
+                if !open =>
+            {
+                Some(Selection::Group(underlying))
+            }
+            Some(Selection::ExpiredRecord(_)) if !open => Some(Selection::Expired),
+            selection => selection,
+        };
+        for group in self.groups.values_mut() {
+            group.open = open;
+            if open {
+                group.closed_expirations.clear();
+            }
+        }
+        self.expired.open = open;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    /// Switches the selected group's header between its totals and its
+    /// option legs priced as one strategy.
+    pub fn toggle_strategy(&mut self) {
+        let underlying = match self.selection() {
+            Some(Selection::Group(underlying))
+            | Some(Selection::Expiration(underlying, _))
+            | Some(Selection::Record(underlying, _)) => underlying,
+            _ => return,
+        };
+        if let Some(group) = self.groups.get_mut(&underlying) {
+            if group.strategy().is_some() {
+                group.strategy = !group.strategy;
+            } else {
+                self.status = Some(format!("{} has fewer than two option legs", underlying.0));
+            }
+        }
+    }
+
+    /// Sets up the groups and selection the first time there are any
+    /// groups: everything expanded with --expanded, else as they were left
+    /// last time, else everything expanded if the config says so.
+    fn restore_view(&mut self) {
+        if !self.view_pending || self.groups.is_empty() {
+            return;
+        }
+        self.view_pending = false;
+        match self.local_state.view.clone() {
+            Some(view) if !self.expand_flag => {
+                for (underlying, group) in self.groups.iter_mut() {
+                    group.open = view.open_groups.contains(&underlying.0);
+                }
+                if view.selected == Some(SavedLine::Expired)
+                    || matches!(view.selected, Some(SavedLine::ExpiredPosition(_)))
+                {
+                    self.expired.open = true;
+                }
+                self.update_num_lines();
+                let lines = self.lines();
+                let position = |saved: &SavedLine| {
+                    lines
+                        .iter()
+                        .position(|line| SavedLine::from(line) == *saved)
+                };
+                // A position that has gone falls back to its group.
+                let selected = view.selected.as_ref().and_then(|saved| {
+                    position(saved).or_else(|| match saved {
+                        SavedLine::Position(underlying, _)
+                        | SavedLine::Expiration(underlying, _) => {
+                            position(&SavedLine::Group(underlying.clone()))
+                        }
+                        SavedLine::Watch(_) => position(&SavedLine::Watchlist),
+                        _ => None,
+                    })
+                });
+                self.state.select(selected);
+            }
+            _ if self.expand_flag || self.expand_default => {
+                for group in self.groups.values_mut() {
+                    group.open = true;
+                }
+                self.update_num_lines();
+                let first = self
+                    .lines()
+                    .iter()
+                    .position(|line| matches!(line, Selection::Record(..)));
+                self.state.select(first.or(self.state.selected()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Keeps the view in the local state up to date with the table, and
+    /// saves it once it has stayed the same for a while, so moving around
+    /// doesn't rewrite the file on every key. `now` saves any change
+    /// straight away. Viewers have no state of their own to save.
+    pub fn save_view(&mut self, now: bool) {
+        if self.attached_to.is_some() || self.view_pending {
+            return;
+        }
+        let view = View {
+            open_groups: self
+                .groups
+                .iter()
+                .filter(|(_, group)| group.open)
+                .map(|(underlying, _)| underlying.0.clone())
+                .collect(),
+            selected: self.selection().as_ref().map(SavedLine::from),
+        };
+        if self.local_state.view.as_ref() != Some(&view) {
+            self.local_state.view = Some(view);
+            self.view_changed = Some(Instant::now());
+        }
+        let due = self
+            .view_changed
+            .map_or(false, |changed| now || changed.elapsed() >= VIEW_SAVE_DELAY);
+        if due {
+            self.view_changed = None;
+            if let Err(e) = self.local_state.save() {
+                self.status = Some(format!("Saving the view failed: {:#}", e));
+            }
+        }
+    }
+
+    pub fn toggle_dust(&mut self) {
+        if self.dust_threshold.is_none() {
+            return;
+        }
+        let selection = self.selection();
+        self.show_dust = !self.show_dust;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    /// Selects the line showing `selection` again after the lines changed,
+    /// if it is still listed.
+    fn reselect(&mut self, selection: Option<Selection>) {
+        if let Some(selection) = selection {
+            if let Some(i) = self.lines().iter().position(|line| *line == selection) {
+                self.state.select(Some(i));
+            }
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.num_lines == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i >= self.num_lines - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.num_lines == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.num_lines - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn first(&mut self) {
+        if self.num_lines > 0 {
+            self.state.select(Some(0));
+        }
+    }
+
+    /// Selects the last line listed, which skips the legs of closed groups.
+    pub fn last(&mut self) {
+        if self.num_lines > 0 {
+            self.state.select(Some(self.num_lines - 1));
+        }
+    }
+
+    /// Moves the selection a screenful of rows down, or up when `down` is
+    /// false, stopping at the first and last lines rather than wrapping.
+    pub fn page(&mut self, down: bool) {
+        if self.num_lines == 0 {
+            return;
+        }
+        let i = self.state.selected().unwrap_or(0);
+        let i = if down {
+            (i + self.page_rows).min(self.num_lines - 1)
+        } else {
+            i.saturating_sub(self.page_rows)
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        let selected = self.state.selected()?;
+        self.lines().into_iter().nth(selected)
+    }
+
+    /// What the selected line streams: a position's contract, a group
+    /// header's underlying, or a watched symbol.
+    fn selected_stream_symbol(&self) -> Option<Action> {
+        let symbol = match self.selection()? {
+            Selection::Record(_, key) => key.stream_symbol,
+            Selection::Group(underlying) => {
+                let quote = self.groups.get(&underlying)?.underlying.as_ref();
+                quote?.symbol.clone()
+            }
+            Selection::Watch(symbol) => self
+                .watchlist
+                .iter()
+                .find(|item| item.symbol == symbol)?
+                .quote
+                .symbol
+                .clone(),
+            Selection::Expiration(..)
+            | Selection::Expired
+            | Selection::ExpiredRecord(_)
+            | Selection::Watchlist => return None,
+        };
+        Some(Action::Resubscribe(symbol))
+    }
+
+    /// Re-adds a symbol's quote subscription, saying so in the status line.
+    pub fn resubscribe(&mut self, symbol: DxFeedSymbol, sub: &QuoteSubscription) {
+        self.status = Some(if self.subscriptions.resubscribe(&symbol, sub) {
+            format!("Resubscribed to {}", symbol.0)
+        } else {
+            format!(
+                "{} isn't subscribed; press L after raising the limit",
+                symbol.0
+            )
+        });
+    }
+
+    pub fn selected_record(&self) -> Option<&PriceRecord> {
+        match self.selection()? {
+            Selection::Record(underlying, key) => self.groups.get(&underlying)?.records.get(&key),
+            Selection::ExpiredRecord(key) => self.expired.records.get(&key),
+            Selection::Group(_)
+            | Selection::Expiration(..)
+            | Selection::Expired
+            | Selection::Watchlist
+            | Selection::Watch(_) => None,
+        }
+    }
+
+    /// Moves option legs that expired before today into the EXPIRED section
+    /// and stops asking for quotes on them.
+    pub fn expire_options(&mut self) {
+        let today = market::today();
+        for group in self.groups.values_mut() {
+            let expired: Vec<RecordKey> = group
+                .records
+                .iter()
+                .filter(|(_, rec)| rec.is_expired(today))
+                .map(|(key, _)| key.clone())
+                .collect();
+            // A contract expires in every account holding it at once.
+            for key in expired {
+                if let Some(rec) = group.records.remove(&key) {
+                    self.subscriptions
+                        .unwant(&key.stream_symbol, Priority::Position);
+                    self.expired.records.insert(key, rec);
+                }
+            }
+        }
+        self.drop_empty_groups();
+        self.session_date = today;
+    }
+
+    /// Removes groups with no positions left, along with their underlying's
+    /// subscription.
+    fn drop_empty_groups(&mut self) {
+        let subscriptions = &mut self.subscriptions;
+        self.groups.retain(|_, group| {
+            if !group.records.is_empty() {
+                return true;
+            }
+            if let Some(underlying) = &group.underlying {
+                subscriptions.unwant(&underlying.symbol, Priority::Underlying);
+            }
+            false
+        });
+        let groups = &self.groups;
+        self.greeks_marked
+            .retain(|underlying, _| groups.contains_key(underlying));
+    }
+
+    /// Stops asking for quotes on a position's contract, unless another
+    /// account still holds it.
+    fn release_position_symbol(&mut self, stream_symbol: &DxFeedSymbol) {
+        let held = self.groups.values().any(|group| {
+            group
+                .records
+                .keys()
+                .any(|key| key.stream_symbol == *stream_symbol)
+        });
+        if !held {
+            self.subscriptions.unwant(stream_symbol, Priority::Position);
+        }
+    }
+
+    /// Applies a position sent by the account streamer when it changes:
+    /// the quantity and open price of one the table has, or its removal once
+    /// closed. Each change is applied once however often it is sent, and
+    /// shares assigned from an option come in at its strike. Returns whether
+    /// it is a position the table doesn't have yet and a download should
+    /// bring in.
+    pub fn apply_streamed_position(&mut self, pos: BriefPosition) -> bool {
+        let account = pos.account_number.0.clone();
+        let signed = match pos.quantity_direction {
+            QuantityDirection::Short => -pos.quantity,
+            _ => pos.quantity,
+        };
+        // Position messages carry no transaction id; the time tastytrade
+        // last changed the position stands in for the transaction that did.
+        let message = streamed::Message {
+            id: format!("{} {} {} {}", account, pos.symbol.0, pos.updated_at, signed),
+            account: account.clone(),
+            symbol: pos.symbol.0.clone(),
+            underlying: pos.underlying_symbol.0.clone(),
+            quantity: signed,
+            open: pos.average_open_price,
+        };
+        let found = self.groups.iter().find_map(|(underlying, group)| {
+            group
+                .records
+                .iter()
+                .find(|(key, rec)| key.account == account && rec.symbol == pos.symbol)
+                .map(|(key, rec)| {
+                    (
+                        underlying.clone(),
+                        key.clone(),
+                        (rec.signed_amount(), rec.open),
+                    )
+                })
+        });
+        let today = market::today();
+        let expiring: Vec<streamed::Expiring> = self
+            .groups
+            .iter()
+            .map(|(underlying, group)| (Some(underlying), group))
+            // Expired legs are kept together; their root is their underlying.
+            .chain(std::iter::once((None, &self.expired)))
+            .flat_map(|(underlying, group)| {
+                group.records.values().filter_map(move |rec| {
+                    let option = rec.option.clone()?;
+                    let underlying =
+                        underlying.map_or_else(|| option.root.clone(), |u| u.0.clone());
+                    streamed::settling(option.expiration, today).then(|| streamed::Expiring {
+                        account: rec.account.clone(),
+                        symbol: rec.symbol.0.clone(),
+                        underlying,
+                        option,
+                        quantity: rec.signed_amount(),
+                        multiplier: rec.shares_per_contract(),
+                    })
+                })
+            })
+            .collect();
+        let held = found.as_ref().map(|(_, _, held)| *held);
+        let outcome = self.ledger.apply(&message, held, &expiring, today);
+        let selection = self.selection();
+        match (outcome, found) {
+            (streamed::Outcome::Repeated, _) => return false,
+            (streamed::Outcome::Open { open: None, .. }, _) => return true,
+            (
+                streamed::Outcome::Open {
+                    quantity,
+                    open: Some(open),
+                },
+                _,
+            ) => self.open_assigned_shares(pos, quantity, open),
+            (streamed::Outcome::Close, Some((underlying, key, _))) => {
+                if let Some(group) = self.groups.get_mut(&underlying) {
+                    group.records.remove(&key);
+                }
+                self.release_position_symbol(&key.stream_symbol);
+            }
+            (streamed::Outcome::Update { quantity, open }, Some((underlying, key, _))) => {
+                if let Some(rec) = self
+                    .groups
+                    .get_mut(&underlying)
+                    .and_then(|group| group.records.get_mut(&key))
+                {
+                    rec.amount = quantity.abs();
+                    rec.direction = pos.quantity_direction;
+                    rec.open = open;
+                }
+            }
+            (_, None) => return false,
+        }
+        self.drop_empty_groups();
+        self.update_num_lines();
+        // A closed position's line is gone, so its group is selected instead.
+        let listed = selection
+            .as_ref()
+            .map_or(false, |selection| self.lines().contains(selection));
+        let selection = match selection {
+            Some(Selection::Record(underlying, _)) if !listed => Some(Selection::Group(underlying)),
+            selection => selection,
+        };
+        self.reselect(selection);
+        false
+    }
+
+    /// Adds the shares an assignment or exercise delivered, opened at the
+    /// strike, without waiting for a download. Shares stream under their
+    /// own symbol.
+    fn open_assigned_shares(&mut self, pos: BriefPosition, quantity: Decimal, open: Decimal) {
+        let underlying = pos.underlying_symbol.clone();
+        let current = self.underlying_price(&underlying).unwrap_or(open);
+        let stream_symbol = DxFeedSymbol(pos.symbol.0.clone());
+        let mut record = PriceRecord {
+            account: pos.account_number.0.clone(),
+            symbol: pos.symbol,
+            instrument_type: pos.instrument_type,
+            open,
+            current,
+            amount: quantity.abs(),
+            multiplier: Decimal::ONE,
+            direction: pos.quantity_direction,
+            greeks: SimpleGreeks::default(),
+            quote: None,
+            option: None,
+            deliverables: None,
+            currency: fx::HOME.to_owned(),
+            fx_rate: Some(Decimal::ONE),
+            prev_close: None,
+            updated: None,
+        };
+        record.set_currency(&self.fx);
+        self.subscriptions
+            .want(stream_symbol.clone(), Priority::Position);
+        let key = RecordKey::new(&stream_symbol, &record);
+        self.groups
+            .entry(underlying)
+            .or_default()
+            .records
+            .insert(key, record);
+    }
+
+    /// Handles the exchange date changing while the app is running.
+    pub fn check_rollover(&mut self, sub: &QuoteSubscription) {
+        if market::today() == self.session_date {
+            return;
+        }
+        self.expire_options();
+        self.subscriptions.sync(sub);
+        self.update_num_lines();
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<Action> {
+        if self.input.is_some() {
+            return self.input_key(code);
+        }
+        self.status = None;
+        self.notices.dismiss();
+        if self.confirm_quit {
+            self.confirm_quit = false;
+            return match code {
+                KeyCode::Char('q') | KeyCode::Char('y') => Some(Action::Quit),
+                _ => None,
+            };
+        }
+        // Everything but closing it is swallowed while the help is open.
+        if self.help_open {
+            if matches!(code, KeyCode::Esc | KeyCode::Char('?')) {
+                self.help_open = false;
+            }
+            return None;
+        }
+        if self.notices_open {
+            match code {
+                KeyCode::Esc | KeyCode::Char('N') => self.notices_open = false,
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.notice_scroll = self.notice_scroll.saturating_sub(1)
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let last = self.notices.history.len().saturating_sub(1);
+                    self.notice_scroll = (self.notice_scroll + 1).min(last);
+                }
+                _ => {}
+            }
+            return None;
+        }
+        // A ticket opened from the chain sits over it, and takes every key.
+        if let Some(ticket) = &mut self.ticket {
+            return match ticket.key(code) {
+                Ok((true, request)) => request.map(Action::Ticket),
+                Ok((false, _)) => {
+                    self.ticket = None;
+                    None
+                }
+                Err(e) => {
+                    self.status = Some(format!("{:#}", e));
+                    None
+                }
+            };
+        }
+        if let Some(id) = self.confirm_cancel.take() {
+            return match code {
+                KeyCode::Char('y') => Some(Action::CancelOrder(id)),
+                _ => None,
+            };
+        }
+        if self.chain.is_some() {
+            if let Some(action) = self.chain_key(code) {
+                return action;
+            }
+        }
+        if self.ladder.is_some() && self.ladder_key(code) {
+            return None;
+        }
+        if self.orders_open {
+            if let Some(action) = self.orders_key(code) {
+                return action;
+            }
+        }
+        let command = match keys::command(code) {
+            Some(command) => command,
+            None => return None,
+        };
+        match command {
+            KeyCommand::Quit => return self.quit(),
+            KeyCommand::Help => self.help_open = true,
+            KeyCommand::Orders => self.orders_open = true,
+            KeyCommand::ClosePosition => self.open_closing_ticket(),
+            KeyCommand::Roll => return self.start_roll(),
+            KeyCommand::Notifications => {
+                self.notices_open = true;
+                self.notice_scroll = 0;
+            }
+            KeyCommand::AcknowledgeAlerts => self.unread_alerts = 0,
+            KeyCommand::Risk => self.risk_open = !self.risk_open,
+            KeyCommand::WhatIf => self.start_what_if(),
+            KeyCommand::Chain => return self.start_chain(),
+            KeyCommand::Detail => {
+                if self.compact_layout {
+                    self.detail_open = !self.detail_open;
+                }
+            }
+            KeyCommand::Close => {
+                if self.risk_open || self.what_if.is_some() || self.stream_open || self.detail_open
+                {
+                    self.risk_open = false;
+                    self.what_if = None;
+                    self.stream_open = false;
+                    self.detail_open = false;
+                } else {
+                    self.filter_expiration(None);
+                    self.filter_symbols("");
+                }
+            }
+            KeyCommand::Ladder => self.ladder = Some(0),
+            KeyCommand::Stream => self.stream_open = !self.stream_open,
+            KeyCommand::Next => self.next(),
+            KeyCommand::Previous => self.previous(),
+            KeyCommand::First => self.first(),
+            KeyCommand::Last => self.last(),
+            KeyCommand::PageUp => self.page(false),
+            KeyCommand::PageDown => self.page(true),
+            KeyCommand::NextTab => self.cycle_tab(1),
+            KeyCommand::PreviousTab => self.cycle_tab(-1),
+            KeyCommand::PickTab => {
+                if let KeyCode::Char(c) = code {
+                    let i = c.to_digit(10).unwrap_or_default() as usize;
+                    if let Some(tab) = self.tabs().get(i) {
+                        self.switch_tab(tab.clone());
+                    }
+                }
+            }
+            KeyCommand::ToggleGroup => self.toggle_group(),
+            KeyCommand::OpenAll => self.set_all_open(true),
+            KeyCommand::CloseAll => self.set_all_open(false),
+            KeyCommand::Strategy => self.toggle_strategy(),
+            KeyCommand::Sort => self.cycle_sort(),
+            KeyCommand::ReverseSort => self.reverse_sort(),
+            KeyCommand::Dust => self.toggle_dust(),
+            KeyCommand::Hedged => self.toggle_hedged(),
+            KeyCommand::PerLotGreeks => self.per_lot_greeks = !self.per_lot_greeks,
+            KeyCommand::GreekColumns => self.greek_columns = !self.greek_columns,
+            KeyCommand::Nested => self.toggle_nested(),
+            KeyCommand::Pause => self.toggle_pause(),
+            KeyCommand::Note => self.edit_note(),
+            KeyCommand::Filter => self.start_filter(),
+            KeyCommand::Export => self.export(),
+            KeyCommand::RetrySubscriptions => return Some(Action::RetrySubscriptions),
+            KeyCommand::Resubscribe => return self.selected_stream_symbol(),
+            KeyCommand::Refresh => return Some(Action::Refresh),
+            KeyCommand::ReloadWatchlist => return Some(Action::ReloadWatchlist),
+            KeyCommand::Watch => self.start_watch(),
+            KeyCommand::Unwatch => return self.start_unwatch(),
+        }
+        None
+    }
+
+    /// Quits, unless there is something left to look at, in which case the
+    /// quit has to be confirmed first.
+    fn quit(&mut self) -> Option<Action> {
+        if self.outstanding().is_none() {
+            return Some(Action::Quit);
+        }
+        self.confirm_quit = true;
+        None
+    }
+
+    /// What would be left behind by quitting now, if anything.
+    fn outstanding(&self) -> Option<String> {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut outstanding = vec![];
+        if self.unread_alerts > 0 {
+            outstanding.push(format!(
+                "{} alert{} unread",
+                self.unread_alerts,
+                plural(self.unread_alerts)
+            ));
+        }
+        // Sends and cancels still out, and this session's orders on their
+        // way to being placed, cancelled or replaced.
+        let pending = self.orders_in_flight + self.orders.pending();
+        if pending > 0 {
+            outstanding.push(format!("{} order{} pending", pending, plural(pending)));
+        }
+        (!outstanding.is_empty()).then(|| outstanding.join(", "))
+    }
+
+    /// Handles events other than key presses: the terminal gaining or
+    /// losing focus, and the mouse.
+    pub fn handle_event(&mut self, event: &event::Event) {
+        match event {
+            event::Event::FocusGained => {
+                self.focused = true;
+                // Catch up on everything skipped while unfocused.
+                self.last_draw = None;
+            }
+            event::Event::FocusLost => self.focused = false,
+            event::Event::Mouse(mouse) => self.handle_mouse(mouse),
+            _ => {}
+        }
+    }
+
+    /// Whether anything covers the table, which the mouse then leaves
+    /// alone.
+    fn covered(&self) -> bool {
+        self.input.is_some()
+            || self.confirm_quit
+            || self.help_open
+            || self.notices_open
+            || self.orders_open
+            || self.confirm_cancel.is_some()
+            || self.ticket.is_some()
+            || self.risk_open
+            || self.stream_open
+            || self.detail_open
+            || self.what_if.is_some()
+            || self.ladder.is_some()
+            || self.chain.is_some()
+    }
+
+    /// Clicking a line selects it, and clicking a group's header opens or
+    /// closes it, as does double clicking one of its legs. The wheel moves
+    /// the selection.
+    fn handle_mouse(&mut self, mouse: &event::MouseEvent) {
+        if self.covered() {
+            return;
+        }
+        match mouse.kind {
+            event::MouseEventKind::ScrollDown => self.next(),
+            event::MouseEventKind::ScrollUp => self.previous(),
+            event::MouseEventKind::Down(event::MouseButton::Left) => {
+                let area = self.table_area;
+                // Under the header.
+                let top = area.y + 1;
+                let inside = mouse.column >= area.x
+                    && mouse.column < area.x + area.width
+                    && mouse.row >= top
+                    && mouse.row < area.y + area.height;
+                if !inside {
+                    return;
+                }
+                let i = self.table_offset + usize::from(mouse.row - top);
+                if i >= self.num_lines {
+                    return;
+                }
+                let now = Instant::now();
+                let double = self.last_click.map_or(false, |(line, at)| {
+                    line == i && now.duration_since(at) < DOUBLE_CLICK
+                });
+                self.last_click = Some((i, now));
+                self.state.select(Some(i));
+                match self.selection() {
+                    // The second click of a double click on a header would
+                    // only undo the first.
+                    Some(Selection::Group(_) | Selection::Expiration(..) | Selection::Expired)
+                        if !double =>
+                    {
+                        self.toggle_group()
+                    }
+                    Some(Selection::Record(underlying, _)) if double => {
+                        self.reselect(Some(Selection::Group(underlying)));
+                        self.toggle_group();
+                    }
+                    Some(Selection::ExpiredRecord(_)) if double => {
+                        self.reselect(Some(Selection::Expired));
+                        self.toggle_group();
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Notes the area the lines of the table are drawn in, under its
+    /// header, and scrolls them to keep the selection in view. A taller
+    /// area scrolls back up as far as the last line allows.
+    fn table_drawn(&mut self, area: Rect) {
+        self.table_area = area;
+        self.page_rows = usize::from(area.height.saturating_sub(1)).max(1);
+        self.table_offset = self
+            .table_offset
+            .min(self.num_lines.saturating_sub(self.page_rows));
+        match self.state.selected() {
+            Some(i) if i >= self.table_offset + self.page_rows => {
+                self.table_offset = i + 1 - self.page_rows
+            }
+            Some(i) if i < self.table_offset => self.table_offset = i,
+            _ => {}
+        }
+    }
+
+    /// The rows of the lines scrolled into view, out of one per line, and
+    /// a state selecting the selected one among them.
+    fn visible_rows<'a>(&self, rows: Vec<Row<'a>>) -> (Vec<Row<'a>>, TableState) {
+        let rows = rows
+            .into_iter()
+            .skip(self.table_offset)
+            .take(self.page_rows)
+            .collect();
+        let mut state = TableState::default();
+        state.select(
+            self.state
+                .selected()
+                .and_then(|i| i.checked_sub(self.table_offset)),
+        );
+        (rows, state)
+    }
+
+    /// Which lines are in view, when they don't all fit.
+    fn scroll_position(&self) -> Option<String> {
+        (self.num_lines > self.page_rows).then(|| {
+            let last = (self.table_offset + self.page_rows).min(self.num_lines);
+            format!(
+                "lines {}-{} of {}",
+                self.table_offset + 1,
+                last,
+                self.num_lines
+            )
+        })
+    }
+
+    /// Shortest time allowed between draws. Without focus, redraws are
+    /// throttled further.
+    fn draw_interval(&self) -> Duration {
+        let interval = self.settings.frame_interval.unwrap_or_default();
+        if self.focused {
+            interval
+        } else {
+            interval.max(self.settings.unfocused_interval)
+        }
+    }
+
+    /// When the next draw is due, if anything has changed.
+    pub fn next_draw(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+        Some(
+            self.last_draw
+                .map_or_else(Instant::now, |drawn| drawn + self.draw_interval()),
+        )
+    }
+
+    /// Whether it's time to redraw.
+    pub fn needs_draw(&self) -> bool {
+        self.next_draw().map_or(false, |due| due <= Instant::now())
+    }
+
+    pub fn drawn(&mut self) {
+        self.last_draw = Some(Instant::now());
+        self.dirty = false;
+        self.stream_stats.draws += 1;
+    }
+
+    /// Whether greeks changed that haven't been drawn yet, clearing it.
+    pub fn take_pending_greeks(&mut self) -> bool {
+        std::mem::take(&mut self.greeks_pending)
+    }
+
+    pub fn edit_note(&mut self) {
+        if self.attached_to.is_some() {
+            self.status = Some("Notes can't be edited from an attached viewer".to_owned());
+            return;
+        }
+        let key = match self.selected_record() {
+            Some(rec) => rec.note_key(),
+            None => return,
+        };
+        let buffer = self.local_state.note(&key).unwrap_or_default().to_owned();
+        self.input = Some(Input::new("Note", buffer, InputTarget::Note(key)));
+    }
+
+    /// The price a group's underlying is trading at, falling back to the
+    /// group's shares when the underlying itself isn't streamed.
+    fn underlying_price(&self, underlying: &Symbol) -> Option<Decimal> {
+        let group = self.groups.get(underlying)?;
+        group
+            .underlying
+            .as_ref()
+            .and_then(|quote| quote.price)
+            .or_else(|| {
+                group
+                    .records
+                    .values()
+                    .find(|rec| rec.symbol == *underlying)
+                    .map(|rec| rec.current)
+            })
+    }
+
+    pub fn start_what_if(&mut self) {
+        let underlying = match self.selection() {
+            Some(Selection::Group(underlying)) => underlying,
+            _ => {
+                self.status = Some("Select a group header to try a price on".to_owned());
+                return;
+            }
+        };
+        self.input = Some(Input::new(
+            "Underlying price, or move like +5%",
+            String::new(),
+            InputTarget::WhatIf(underlying),
+        ));
+    }
+
+    fn open_what_if(&mut self, underlying: Symbol, text: &str) {
+        let from = match self.underlying_price(&underlying) {
+            Some(price) => price,
+            None => {
+                self.status = Some(format!("No price for {} yet", underlying.0));
+                return;
+            }
+        };
+        let parse = |text: &str| Decimal::from_str(text.trim_start_matches('+')).ok();
+        let to = match text.strip_suffix('%') {
+            Some(percent) => {
+                parse(percent).map(|percent| from * (Decimal::ONE + percent / Decimal::from(100)))
+            }
+            None => parse(text),
+        };
+        match to {
+            Some(to) => {
+                self.what_if = Some(WhatIf {
+                    underlying,
+                    from,
+                    to,
+                })
+            }
+            None => self.status = Some(format!("Not a price or a percentage: {}", text)),
+        }
+    }
+
+    pub fn start_filter(&mut self) {
+        self.input = Some(Input::new(
+            "Filter symbols",
+            self.symbol_filter.clone(),
+            InputTarget::Filter,
+        ));
+    }
+
+    pub fn input_key(&mut self, code: KeyCode) -> Option<Action> {
+        let input = self.input.as_mut()?;
+        // Waiting on the lookup, which Esc gives up on.
+        if input.busy && code != KeyCode::Esc {
+            return None;
+        }
+        input.error = None;
+        match code {
+            KeyCode::Enter => return self.submit_input(),
+            KeyCode::Esc => {
+                if let Some(InputTarget::Filter) = self.input.take().map(|input| input.target) {
+                    self.filter_symbols("");
+                }
+            }
+            KeyCode::Backspace => {
+                input.buffer.pop();
+            }
+            KeyCode::Char(c) => input.buffer.push(c),
+            _ => {}
+        }
+        if let Some(Input {
+            target: InputTarget::Filter,
+            buffer,
+            ..
+        }) = &self.input
+        {
+            let text = buffer.clone();
+            self.filter_symbols(&text);
+        }
+        None
+    }
+
+    pub fn submit_input(&mut self) -> Option<Action> {
+        let mut input = self.input.take()?;
+        match input.target {
+            InputTarget::Note(key) => {
+                self.local_state.set_note(key, input.buffer.trim());
+                if let Err(e) = self.local_state.save() {
+                    self.status = Some(format!("Saving note failed: {:#}", e));
+                }
+            }
+            InputTarget::WhatIf(underlying) => self.open_what_if(underlying, input.buffer.trim()),
+            // Applied while typing already; Enter only keeps it.
+            InputTarget::Filter => {}
+            InputTarget::Watch => {
+                let entry = watchlist::entry(&input.buffer);
+                let checked = match &entry {
+                    Some(entry) if self.watched(&entry.symbol) => {
+                        Err(format!("Already watching {}", entry.symbol.0.trim()))
+                    }
+                    Some(entry) => watchlist::check(entry).map_err(|e| format!("{:#}", e)),
+                    None => Err("Type a symbol, optionally followed by a label".to_owned()),
+                };
+                let busy = checked.is_ok();
+                input.error = checked.err();
+                input.busy = busy;
+                self.input = Some(input);
+                return entry.filter(|_| busy).map(Action::Watch);
+            }
+            InputTarget::Unwatch => {
+                let entry = watchlist::entry(&input.buffer);
+                match entry.filter(|entry| self.watched(&entry.symbol)) {
+                    Some(entry) => return self.unwatch(&entry.symbol),
+                    None => {
+                        input.error = Some("Not on the watchlist".to_owned());
+                        self.input = Some(input);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn watched(&self, symbol: &Symbol) -> bool {
+        self.watchlist.iter().any(|item| item.symbol == *symbol)
+    }
+
+    pub fn start_watch(&mut self) {
+        if self.attached_to.is_some() {
+            self.status = Some("The watchlist can't be changed from an attached viewer".to_owned());
+            return;
+        }
+        self.input = Some(Input::new(
+            "Watch symbol",
+            String::new(),
+            InputTarget::Watch,
+        ));
+    }
+
+    /// Takes the selected watchlist row off the watchlist, or asks which
+    /// symbol to take off when none is selected.
+    pub fn start_unwatch(&mut self) -> Option<Action> {
+        if self.attached_to.is_some() {
+            self.status = Some("The watchlist can't be changed from an attached viewer".to_owned());
+            return None;
+        }
+        if self.watchlist.is_empty() {
+            self.status = Some("Nothing is being watched".to_owned());
+            return None;
+        }
+        if let Some(Selection::Watch(symbol)) = self.selection() {
+            return self.unwatch(&symbol);
+        }
+        self.input = Some(Input::new(
+            "Stop watching",
+            String::new(),
+            InputTarget::Unwatch,
+        ));
+        None
+    }
+
+    /// Takes in the lookup of a symbol typed in to watch. A failed lookup
+    /// is shown in the input for another try; one that comes back after
+    /// the input was closed is dropped.
+    pub fn watch_resolved(&mut self, entry: watchlist::Entry, resolved: Result<DxFeedSymbol>) {
+        let input = match &mut self.input {
+            Some(input) if matches!(input.target, InputTarget::Watch) && input.busy => input,
+            _ => return,
+        };
+        input.busy = false;
+        let stream_symbol = match resolved {
+            Ok(stream_symbol) => stream_symbol,
+            Err(e) => {
+                input.error = Some(format!("{:#}", e));
+                return;
+            }
+        };
+        self.input = None;
+        self.status = Some(format!("Watching {}", entry.symbol.0.trim()));
+        self.subscriptions
+            .want(stream_symbol.clone(), Priority::Watchlist);
+        self.watch_symbols.push(entry.clone());
+        self.watchlist.push(WatchItem {
+            symbol: entry.symbol,
+            label: entry.label,
+            quote: UnderlyingQuote {
+                symbol: stream_symbol,
+                price: None,
+                prev_close: None,
+                halted: false,
+            },
+            bid: None,
+            ask: None,
+        });
+        self.update_num_lines();
+    }
+
+    /// Takes `symbol` off the watchlist. One from the watch file is back
+    /// when the file is next reloaded.
+    fn unwatch(&mut self, symbol: &Symbol) -> Option<Action> {
+        let listed = self.watch_symbols.len();
+        self.watch_symbols.retain(|entry| entry.symbol != *symbol);
+        let from_file = self.watch_symbols.len() == listed;
+        let index = self
+            .watchlist
+            .iter()
+            .position(|item| item.symbol == *symbol)?;
+        let item = self.watchlist.remove(index);
+        if !self
+            .watchlist
+            .iter()
+            .any(|other| other.quote.symbol == item.quote.symbol)
+        {
+            self.subscriptions
+                .unwant(&item.quote.symbol, Priority::Watchlist);
+        }
+        self.status = Some(if from_file {
+            format!(
+                "Stopped watching {} until the watch file is reloaded",
+                symbol.0.trim()
+            )
+        } else {
+            format!("Stopped watching {}", symbol.0.trim())
+        });
+        self.update_num_lines();
+        Some(Action::SyncSubscriptions)
+    }
+
+    /// Every option leg whose expiration is known, for the expiration
+    /// ladder.
+    pub fn ladder_legs(&self) -> Vec<ladder::Leg> {
+        self.groups
+            .iter()
+            .flat_map(|(underlying, group)| {
+                group.records.values().filter_map(move |rec| {
+                    let option = rec.option.as_ref()?;
+                    Some(ladder::Leg {
+                        underlying: underlying.0.clone(),
+                        expiration: option.expiration,
+                        kind: option.kind,
+                        strike: option.strike,
+                        quantity: rec.signed_amount() * rec.multiplier,
+                        open: rec.open,
+                        theta: rec.net_theta(),
+                        delta: rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap_or_default()),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Opens a ticket closing the selected position at its mid, or every
+    /// option leg of a group priced as a strategy at its net mark.
+    fn open_closing_ticket(&mut self) {
+        if !self.writable("closing positions") {
+            return;
+        }
+        let (underlying, key) = match self.selection() {
+            Some(Selection::Record(underlying, key)) => (underlying, key),
+            Some(Selection::Group(underlying))
+                if self
+                    .groups
+                    .get(&underlying)
+                    .map_or(false, |group| group.strategy) =>
+            {
+                return self.open_strategy_closing_ticket(underlying);
+            }
+            _ => {
+                self.status = Some("Select a position to close".to_owned());
+                return;
+            }
+        };
+        let rec = match self
+            .groups
+            .get(&underlying)
+            .and_then(|group| group.records.get(&key))
+        {
+            Some(rec) => rec,
+            None => return,
+        };
+        let ticket = ticket::Ticket::closing(
+            rec.account.clone(),
+            rec.symbol.clone(),
+            rec.name(&underlying),
+            rec.instrument_type.clone(),
+            rec.amount,
+            matches!(rec.direction, QuantityDirection::Short),
+            rec.current,
+        );
+        match ticket {
+            Ok(ticket) => self.open_ticket(ticket, &underlying),
+            Err(e) => self.status = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Opens one ticket closing all of the option legs `underlying`'s
+    /// strategy row combines, in the ratio they are held.
+    fn open_strategy_closing_ticket(&mut self, underlying: Symbol) {
+        let group = match self.groups.get(&underlying) {
+            Some(group) => group,
+            None => return,
+        };
+        let strategy = match group.strategy() {
+            Some(strategy) => strategy,
+            None => return,
+        };
+        let legs: Vec<&PriceRecord> = group
+            .records
+            .values()
+            .filter(|rec| rec.option.is_some())
+            .collect();
+        let account = legs[0].account.clone();
+        if legs.iter().any(|rec| rec.account != account) {
+            self.status = Some(format!(
+                "{}'s legs are in more than one account; close them one at a time",
+                underlying.0
+            ));
+            return;
+        }
+        let units = Decimal::from(strategy.units);
+        let ticket_legs = legs
+            .iter()
+            .map(|rec| {
+                ticket::Leg::closing(
+                    rec.symbol.clone(),
+                    &rec.name(&underlying),
+                    rec.instrument_type.clone(),
+                    matches!(rec.direction, QuantityDirection::Short),
+                    rec.amount / units,
+                )
+            })
+            .collect();
+        let ticket = ticket::Ticket::closing_strategy(
+            account,
+            format!("{} strategy", underlying.0),
+            ticket_legs,
+            units,
+            strategy.mark,
+        );
+        match ticket {
+            Ok(ticket) => self.open_ticket(ticket, &underlying),
+            Err(e) => self.status = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Shows `ticket`, on an order in `underlying`, checked against what
+    /// its account may trade when that is known.
+    fn open_ticket(&mut self, mut ticket: ticket::Ticket, underlying: &Symbol) {
+        if let Some(permission) = self.permissions.get(&ticket.account) {
+            let shares = self
+                .groups
+                .get(underlying)
+                .into_iter()
+                .flat_map(|group| group.records.values())
+                .filter(|rec| {
+                    rec.account == ticket.account
+                        && matches!(rec.instrument_type, InstrumentType::Equity)
+                })
+                .map(|rec| match rec.direction {
+                    QuantityDirection::Short => -rec.amount,
+                    _ => rec.amount,
+                })
+                .sum();
+            ticket.permit(*permission, shares);
+        }
+        self.ticket = Some(ticket);
+    }
+
+    /// Opens a ticket buying, or selling, one of the contract selected in
+    /// the chain at its mid, in the account holding the underlying's
+    /// positions. The chain stays open under it.
+    fn open_contract_ticket(&mut self, sell: bool) {
+        if !self.writable("placing orders") {
+            return;
+        }
+        let chain = match &self.chain {
+            Some(chain) => chain,
+            None => return,
+        };
+        let contract = match chain.selected() {
+            Some(contract) => contract,
+            None => {
+                self.status = Some("No contract listed there".to_owned());
+                return;
+            }
+        };
+        let mid = match chain.quote(contract).and_then(|quote| quote.mid()) {
+            Some(mid) => mid,
+            None => {
+                self.status = Some(format!(
+                    "{} has no quote yet; it may not be liquid",
+                    contract.symbol.0.trim()
+                ));
+                return;
+            }
+        };
+        let account = self
+            .groups
+            .get(&chain.underlying)
+            .and_then(|group| group.records.values().find(|rec| self.in_tab(rec)))
+            .map(|rec| rec.account.clone())
+            .or_else(|| {
+                self.tab_balances()
+                    .next()
+                    .map(|(account, _)| account.clone())
+            });
+        match account {
+            Some(account) => {
+                let ticket = ticket::Ticket::opening(account, contract.symbol.clone(), sell, mid);
+                let underlying = chain.underlying.clone();
+                self.open_ticket(ticket, &underlying);
+            }
+            None => self.status = Some("No account to trade in".to_owned()),
+        }
+    }
+
+    /// Starts rolling the selected short option: its chain is loaded to
+    /// pick the contract it rolls to, starting at the same strike in the
+    /// next expiration.
+    fn start_roll(&mut self) -> Option<Action> {
+        if !self.writable("rolling positions") {
+            return None;
+        }
+        let (underlying, key) = match self.selection() {
+            Some(Selection::Record(underlying, key)) => (underlying, key),
+            _ => {
+                self.status = Some("Select a short option to roll".to_owned());
+                return None;
+            }
+        };
+        let rec = self.groups.get(&underlying)?.records.get(&key)?;
+        let problem = match (&rec.instrument_type, &rec.option, &rec.direction) {
+            (InstrumentType::FutureOption, _, _) => {
+                Some("Futures options can't be rolled from here")
+            }
+            (InstrumentType::EquityOption, Some(_), QuantityDirection::Short) => None,
+            (InstrumentType::EquityOption, Some(_), _) => {
+                Some("Only short options are rolled from here")
+            }
+            _ => Some("Select a short option to roll"),
+        };
+        if let Some(problem) = problem {
+            self.status = Some(problem.to_owned());
+            return None;
+        }
+        self.roll = Some(ticket::Roll {
+            account: rec.account.clone(),
+            symbol: rec.symbol.clone(),
+            name: rec.name(&underlying),
+            option: rec.option.clone()?,
+            held: rec.amount,
+            mid: rec.current,
+        });
+        self.status = Some(format!("Loading the option chain of {}…", underlying.0));
+        Some(Action::OpenChain(underlying))
+    }
+
+    /// Opens the roll ticket for the contract selected in the chain.
+    fn roll_to_selected(&mut self) -> Option<Action> {
+        let chain = self.chain.as_ref()?;
+        let contract = chain.selected()?;
+        let mid = match chain.quote(contract).and_then(|quote| quote.mid()) {
+            Some(mid) => mid,
+            None => {
+                self.status = Some(format!(
+                    "{} has no quote yet; it may not be liquid",
+                    contract.symbol.0.trim()
+                ));
+                return None;
+            }
+        };
+        let underlying = chain.underlying.clone();
+        let underlying_price = self.underlying_price(&underlying);
+        let roll = self.roll.clone()?;
+        let ticket = match ticket::Ticket::roll(roll, contract, mid, underlying_price) {
+            Ok(ticket) => ticket,
+            Err(e) => {
+                self.status = Some(format!("{:#}", e));
+                return None;
+            }
+        };
+        self.open_ticket(ticket, &underlying);
+        self.roll = None;
+        self.close_chain();
+        Some(Action::SyncSubscriptions)
+    }
+
+    /// Keys of the working orders pane, which has a selection of its own.
+    /// None for keys it leaves to the table.
+    fn orders_key(&mut self, code: KeyCode) -> Option<Option<Action>> {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.orders.previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.orders.next(),
+            KeyCode::Esc | KeyCode::Char('b') => self.orders_open = false,
+            KeyCode::Char('x') => {
+                if self.writable("cancelling orders") {
+                    self.confirm_cancel = self.orders.selected().map(orders::id);
+                }
+            }
+            _ => return None,
+        }
+        Some(None)
+    }
+
+    /// Keys of the expiration ladder. Returns whether the key was used.
+    fn ladder_key(&mut self, code: KeyCode) -> bool {
+        let cursor = match self.ladder {
+            Some(cursor) => cursor,
+            None => return false,
+        };
+        let expirations = ladder::summarize(&self.ladder_legs());
+        match code {
+            KeyCode::Up => self.ladder = Some(cursor.saturating_sub(1)),
+            KeyCode::Down => {
+                self.ladder = Some((cursor + 1).min(expirations.len().saturating_sub(1)))
+            }
+            KeyCode::Enter => {
+                self.ladder = None;
+                self.filter_expiration(expirations.get(cursor).map(|expiration| expiration.date));
+            }
+            KeyCode::Esc | KeyCode::Char('x') => self.ladder = None,
+            _ => return false,
+        }
+        true
+    }
+
+    fn filter_symbols(&mut self, text: &str) {
+        let text = text.trim().to_lowercase();
+        if text == self.symbol_filter {
+            return;
+        }
+        let selection = self.selection();
+        self.symbol_filter = text;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    fn filter_expiration(&mut self, date: Option<NaiveDate>) {
+        let selection = self.selection();
+        self.expiration_filter = date;
+        self.update_num_lines();
+        self.reselect(selection);
+    }
+
+    fn start_chain(&mut self) -> Option<Action> {
+        if self.attached_to.is_some() {
+            self.status = Some("Option chains can't be opened from an attached viewer".to_owned());
+            return None;
+        }
+        match self.selection() {
+            Some(Selection::Group(underlying)) if underlying.0.starts_with('/') => {
+                self.status = Some("Option chains are only shown for equities".to_owned());
+                None
+            }
+            Some(Selection::Group(underlying)) => {
+                self.status = Some(format!("Loading the option chain of {}…", underlying.0));
+                Some(Action::OpenChain(underlying))
+            }
+            _ => {
+                self.status = Some("Select a group header to see its option chain".to_owned());
+                None
+            }
+        }
+    }
+
+    /// Shows a downloaded option chain, starting at the money.
+    pub fn open_chain(&mut self, mut chain: chain::Chain) {
+        self.status = None;
+        if chain.strikes.is_empty() {
+            self.status = Some(format!("{} has no options listed", chain.underlying.0));
+            return;
+        }
+        if let Some(price) = self.underlying_price(&chain.underlying) {
+            chain.center_on(price);
+        } else {
+            chain.strike = chain.strikes.len() / 2;
+        }
+        if let Some(roll) = &self.roll {
+            let option = &roll.option;
+            match chain
+                .expirations
+                .iter()
+                .position(|expiration| *expiration > option.expiration)
+            {
+                Some(next) => {
+                    chain.kind = option.kind;
+                    chain.expiration = next;
+                    chain.center_on(option.strike);
+                }
+                None => {
+                    self.status = Some(format!(
+                        "{} lists no expiration after {} near enough to roll to",
+                        chain.underlying.0,
+                        option.expiration.format("%b %d")
+                    ));
+                    self.roll = None;
+                    return;
+                }
+            }
+        }
+        self.chain = Some(chain);
+        self.stream_chain();
+    }
+
+    /// Streams the contracts the chain view shows, and only those.
+    fn stream_chain(&mut self) {
+        let chain = match &mut self.chain {
+            Some(chain) => chain,
+            None => return,
+        };
+        let visible = chain.visible_symbols();
+        for symbol in chain.streamed.difference(&visible) {
+            self.subscriptions.unwant(symbol, Priority::Chain);
+            chain.quotes.remove(symbol);
+        }
+        for symbol in visible.difference(&chain.streamed) {
+            self.subscriptions.want(symbol.clone(), Priority::Chain);
+        }
+        chain.streamed = visible;
+    }
+
+    fn close_chain(&mut self) {
+        if let Some(chain) = self.chain.take() {
+            for symbol in &chain.streamed {
+                self.subscriptions.unwant(symbol, Priority::Chain);
+            }
+        }
+    }
+
+    /// Keys of the chain view. Returns `None` for keys it leaves to the
+    /// table.
+    fn chain_key(&mut self, code: KeyCode) -> Option<Option<Action>> {
+        let chain = self.chain.as_mut()?;
+        match code {
+            KeyCode::Up => chain.up(),
+            KeyCode::Down => chain.down(),
+            KeyCode::Left => chain.left(),
+            KeyCode::Right => chain.right(),
+            KeyCode::Tab => chain.toggle_kind(),
+            KeyCode::Enter if self.roll.is_some() => return Some(self.roll_to_selected()),
+            KeyCode::Enter | KeyCode::Char('b') => {
+                self.open_contract_ticket(false);
+                return Some(None);
+            }
+            KeyCode::Char('s') => {
+                self.open_contract_ticket(true);
+                return Some(None);
+            }
+            KeyCode::Char('w') => return Some(self.watch_selected_contract()),
+            KeyCode::Esc | KeyCode::Char('O') => {
+                self.roll = None;
+                self.close_chain();
+            }
+            _ => return None,
+        }
+        self.stream_chain();
+        Some(Some(Action::SyncSubscriptions))
+    }
+
+    /// Whether `what` may go ahead. In read-only mode it may not, and the
+    /// status line says why. Every action that changes the account or the
+    /// watchlist goes through here first.
+    fn writable(&mut self, what: &str) -> bool {
+        // The calls themselves are refused as well, through `guard`; this
+        // is for saying so before they are attempted.
+        if self.read_only {
+            self.status = Some(format!(
+                "Read-only mode: {} is disabled (restart without --read-only)",
+                what
+            ));
+        }
+        !self.read_only
+    }
+
+    /// Refuses the calls that would change the account in read-only mode.
+    fn guard(&self) -> readonly::Guard {
+        readonly::Guard::new(self.read_only)
+    }
+
+    fn watch_selected_contract(&mut self) -> Option<Action> {
+        let symbol = self.chain.as_ref()?.selected()?.symbol.clone();
+        if !self.writable("adding to the watchlist") {
+            return None;
+        }
+        let path = match &self.watch_file {
+            Some(path) => path,
+            None => {
+                self.status = Some("Start with --watch-file to add to the watchlist".to_owned());
+                return None;
+            }
+        };
+        match watchlist::append(path, &symbol) {
+            Ok(()) => {
+                self.status = Some(format!("Watching {}", symbol.0));
+                Some(Action::ReloadWatchlist)
+            }
+            Err(e) => {
+                self.status = Some(format!("{:#}", e));
+                None
+            }
+        }
+    }
+
+    pub fn export(&mut self) {
+        let path = self.export_dir.join(format!(
+            "positions-{}.{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            self.export_format.extension()
+        ));
+        let groups = self
+            .groups
+            .iter()
+            .map(|(underlying, group)| {
+                let positions = group
+                    .records
+                    .values()
+                    .map(|rec| PositionRow {
+                        schema_version: export::SCHEMA_VERSION,
+                        account: &rec.account,
+                        underlying: &underlying.0,
+                        symbol: &rec.symbol.0,
+                        quantity: rec.signed_amount(),
+                        unit: rec.unit(),
+                        trade_price: rec.open,
+                        current: rec.current,
+                        bid: rec.quote.as_ref().map(|q| q.bid),
+                        bid_size: rec.quote.as_ref().map(|q| q.bid_size),
+                        ask: rec.quote.as_ref().map(|q| q.ask),
+                        ask_size: rec.quote.as_ref().map(|q| q.ask_size),
+                        profit: rec.to_net(rec.current - rec.open),
+                        credit: rec.open_credit(),
+                        net_liq: rec.to_net(rec.current),
+                        theta: rec.net_theta(),
+                        delta: rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap_or_default()),
+                        note: self.local_state.note(&rec.note_key()).unwrap_or_default(),
+                    })
+                    .collect();
+                GroupRow::new(&underlying.0, positions)
+            })
+            .collect();
+        let cash = self.balances.values().map(|balance| balance.cash).sum();
+        let document = Document::new(groups, cash);
+        self.status = Some(match export::write(&path, self.export_format, &document) {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(e) => format!("Export failed: {:#}", e),
+        });
+    }
+
+    /// Re-reads the subscription limit from the config and subscribes to any
+    /// symbols that now fit under it.
+    pub fn retry_subscriptions(&mut self, sub: &QuoteSubscription) {
+        match Config::load() {
+            Ok(config) => {
+                self.subscriptions.set_limit(config.max_subscriptions);
+                self.subscriptions.sync(sub);
+            }
+            Err(e) => self.status = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Every live position's greeks, scaled to the position, for the risk
+    /// summary.
+    pub fn risk_positions(&self) -> Vec<risk::PositionRisk> {
+        self.groups
+            .iter()
+            .flat_map(|(underlying, group)| {
+                let underlying_price = group
+                    .underlying
+                    .as_ref()
+                    .and_then(|quote| quote.price)
+                    .and_then(|price| price.to_f64());
+                group.records.values().map(move |rec| {
+                    let (delta, gamma) = rec.scaled_delta_gamma();
+                    risk::PositionRisk {
+                        symbol: rec.symbol.0.clone(),
+                        delta,
+                        gamma,
+                        theta: rec.scale(rec.greeks.theta),
+                        vega: rec.scale(rec.greeks.vega),
+                        underlying_price,
+                        beta: self.betas.get(&underlying.0).copied().unwrap_or(1.0),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Streamer symbols of the symbols already watched.
+    pub fn known_watched(&self) -> BTreeMap<Symbol, DxFeedSymbol> {
+        self.watchlist
+            .iter()
+            .map(|item| (item.symbol.clone(), item.quote.symbol.clone()))
+            .collect()
+    }
+
+    /// Notes how subscribing to `account` went, returning how long to wait
+    /// before trying again if it failed.
+    pub fn account_subscribed(&mut self, account: String, result: Result<()>) -> Option<Duration> {
+        let attempts = match self.account_streams.get(&account) {
+            Some(AccountStream::Retrying { attempts, .. }) => *attempts,
+            _ => 0,
+        };
+        match result {
+            Ok(()) => {
+                self.account_streams
+                    .insert(account, AccountStream::Subscribed);
+                None
+            }
+            Err(e) => {
+                let attempts = attempts + 1;
+                self.account_streams.insert(
+                    account,
+                    AccountStream::Retrying {
+                        attempts,
+                        error: format!("{:#}", e),
+                    },
+                );
+                Some(
+                    ACCOUNT_RETRY_BASE
+                        .saturating_mul(2u32.saturating_pow(attempts - 1))
+                        .min(ACCOUNT_RETRY_MAX),
+                )
+            }
+        }
+    }
+
+    /// Takes a balance streamed by the account streamer. Its accruals aren't
+    /// part of the message, so the ones already known are kept until they
+    /// are read again.
+    pub fn apply_balance(&mut self, account: String, mut balance: AccountBalance) {
+        if let Some(held) = &mut self.paused {
+            held.balances.insert(account, balance);
+            return;
+        }
+        if let Some(old) = self.balances.get(&account) {
+            balance.accruals = old.accruals.clone();
+        }
+        self.balances.insert(account, balance);
+    }
+
+    pub fn apply_accruals(&mut self, account: &str, accruals: accruals::Accruals) {
+        if let Some(balance) = self.balances.get_mut(account) {
+            balance.accruals = accruals;
+        }
+    }
+
+    /// Replaces the watchlist with a freshly read watch file. Symbols still
+    /// listed keep their prices; the subscriptions change on the next sync.
+    pub fn apply_watchlist(&mut self, resolved: watchlist::Resolved) {
+        let mut old: BTreeMap<DxFeedSymbol, WatchItem> = self
+            .watchlist
+            .drain(..)
+            .map(|item| (item.quote.symbol.clone(), item))
+            .collect();
+        for (entry, stream_symbol) in resolved.entries {
+            let item = match old.remove(&stream_symbol) {
+                Some(item) => WatchItem {
+                    symbol: entry.symbol,
+                    label: entry.label,
+                    ..item
+                },
+                None => {
+                    self.subscriptions
+                        .want(stream_symbol.clone(), Priority::Watchlist);
+                    WatchItem {
+                        symbol: entry.symbol,
+                        label: entry.label,
+                        quote: UnderlyingQuote {
+                            symbol: stream_symbol,
+                            price: None,
+                            prev_close: None,
+                            halted: false,
+                        },
+                        bid: None,
+                        ask: None,
+                    }
+                }
+            };
+            self.watchlist.push(item);
+        }
+        for symbol in old.keys() {
+            self.subscriptions.unwant(symbol, Priority::Watchlist);
+        }
+        self.update_num_lines();
+        if !resolved.warnings.is_empty() {
+            self.status = Some(resolved.warnings.join("; "));
+        }
+    }
+
+    /// The values alert rules are checked against. Dust hidden from the
+    /// table is left out.
+    pub fn alert_inputs(&self) -> alerts::Inputs {
+        let mut prices = BTreeMap::new();
+        let mut profit = Decimal::zero();
+        let mut net_liq = Decimal::zero();
+        let mut positions = 0;
+        let mut live = 0;
+        for (underlying, group) in self.visible_groups() {
+            if let Some(price) = group.underlying.as_ref().and_then(|quote| quote.price) {
+                prices.insert(
+                    underlying.0.clone(),
+                    alerts::Price {
+                        value: price,
+                        live: true,
+                    },
+                );
+            }
+            for rec in group.records.values() {
+                prices.insert(
+                    rec.symbol.0.clone(),
+                    alerts::Price {
+                        value: rec.current,
+                        live: BidAsk::is_live(&rec.quote),
+                    },
+                );
+                profit += rec.to_net(rec.current - rec.open);
+                net_liq += rec.to_net(rec.current);
+                positions += 1;
+                live += usize::from(BidAsk::is_live(&rec.quote));
+            }
+        }
+        for item in &self.watchlist {
+            if let Some(price) = item.quote.price {
+                prices.insert(
+                    item.symbol.0.clone(),
+                    alerts::Price {
+                        value: price,
+                        live: true,
+                    },
+                );
+            }
+        }
+        net_liq += self
+            .balances
+            .values()
+            .map(|balance| balance.cash)
+            .sum::<Decimal>();
+        alerts::Inputs {
+            prices,
+            profit,
+            net_liq,
+            live_fraction: if positions == 0 {
+                1.0
+            } else {
+                live as f64 / positions as f64
+            },
+        }
+    }
+
+    /// Evaluates the alert rules, returning whether the status line changed.
+    pub fn check_alerts(&mut self) -> bool {
+        let arming = self.alerts.arming();
+        let fired = self.alerts.evaluate(&self.alert_inputs());
+        if !fired.is_empty() {
+            self.status = Some(format!("ALERT: {}", fired.join("; ")));
+            self.unread_alerts += fired.len();
+        }
+        !fired.is_empty() || arming != self.alerts.arming()
+    }
+
+    /// Underlyings whose streamer symbol is already known.
+    pub fn known_underlyings(&self) -> BTreeSet<Symbol> {
+        self.groups
+            .iter()
+            .filter(|(_, group)| group.underlying.is_some())
+            .map(|(underlying, _)| underlying.clone())
+            .collect()
+    }
+
+    /// Brings the table in line with freshly downloaded positions. Positions
+    /// that are still open keep their live prices, new ones are added and
+    /// closed ones removed. The download replaces what was there rather than
+    /// being added to it, so applying the same one twice changes nothing.
+    pub fn apply_positions(&mut self, loaded: LoadedPositions) {
+        let selection = self.selection();
+
+        let mut open = BTreeSet::new();
+        let mut unconverted = BTreeSet::new();
+        for mut pos in loaded.positions {
+            pos.record.set_currency(&self.fx);
+            if pos.record.fx_rate.is_none() {
+                unconverted.insert(pos.record.currency.clone());
+            }
+            let key = RecordKey::new(&pos.stream_symbol, &pos.record);
+            open.insert(key.clone());
+            if let Some(rec) = self.expired.records.get_mut(&key) {
+                rec.update_position(pos.record);
+                continue;
+            }
+            let group = self.groups.entry(pos.underlying).or_default();
+            match group.records.get_mut(&key) {
+                Some(rec) => rec.update_position(pos.record),
+                None => {
+                    self.subscriptions
+                        .want(pos.stream_symbol, Priority::Position);
+                    group.records.insert(key, pos.record);
+                }
+            }
+        }
+        let mut closed = BTreeSet::new();
+        for group in self.groups.values_mut() {
+            group.records.retain(|key, _| {
+                if open.contains(key) {
+                    return true;
+                }
+                closed.insert(key.stream_symbol.clone());
+                false
+            });
+        }
+        for symbol in &closed {
+            self.release_position_symbol(symbol);
+        }
+        self.expired.records.retain(|key, _| open.contains(key));
+
+        for (underlying, symbol) in loaded.underlyings {
+            if let Some(group) = self.groups.get_mut(&underlying) {
+                if group.underlying.is_none() {
+                    self.subscriptions
+                        .want(symbol.clone(), Priority::Underlying);
+                    group.underlying = Some(UnderlyingQuote {
+                        symbol,
+                        price: None,
+                        prev_close: None,
+                        halted: false,
+                    });
+                }
+            }
+        }
+        self.balances.extend(loaded.balances);
+        if let Some(premium) = loaded.premium {
+            self.premium = premium;
+        }
+        // Looked-up closes are kept rather than fetched again, for as long
+        // as the underlying is held.
+        if let Some(opened) = loaded.opened {
+            self.opened.extend(opened);
+        }
+        let groups = &self.groups;
+        self.opened
+            .retain(|underlying, _| groups.contains_key(&Symbol(underlying.clone())));
+        // Drops the groups left empty along with expiring what's due.
+        self.expire_options();
+
+        let mut warnings = loaded.warnings;
+        for currency in unconverted {
+            warnings.push(format!(
+                "No FX rate for {}; its positions are left out of totals",
+                currency
+            ));
+        }
+        let open_keys: BTreeSet<String> = self
+            .groups
+            .values()
+            .chain([&self.expired])
+            .flat_map(|group| group.records.values())
+            .map(PriceRecord::note_key)
+            .collect();
+        self.local_state.prune_notes(&open_keys);
+        if let Err(e) = self.local_state.save() {
+            warnings.push(format!("Saving notes failed: {:#}", e));
+        }
+        if !warnings.is_empty() {
+            self.status = Some(warnings.join("; "));
+        }
+
+        self.update_num_lines();
+        self.reselect(selection);
+        self.restore_view();
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            groups: self
+                .groups
+                .iter()
+                .map(|(underlying, group)| GroupSnapshot {
+                    underlying: underlying.0.clone(),
+                    underlying_quote: group.underlying.as_ref().map(Into::into),
+                    records: group.records.iter().map(RecordSnapshot::from).collect(),
+                })
+                .collect(),
+            expired: self
+                .expired
+                .records
+                .iter()
+                .map(RecordSnapshot::from)
+                .collect(),
+            watchlist: self
+                .watchlist
+                .iter()
+                .map(|item| WatchSnapshot {
+                    symbol: item.symbol.0.clone(),
+                    label: item.label.clone(),
+                    quote: (&item.quote).into(),
+                    bid: item.bid,
+                    ask: item.ask,
+                })
+                .collect(),
+            balances: self.balances.clone(),
+            premium: self.premium.clone(),
+            opened: self.opened.clone(),
+            notes: self
+                .local_state
+                .notes
+                .iter()
+                .map(|(key, note)| (key.clone(), note.text.clone()))
+                .collect(),
+        }
+    }
+
+    /// A record from a snapshot, converted with this viewer's own rates.
+    fn snapshot_record(&self, record: RecordSnapshot) -> (RecordKey, PriceRecord) {
+        let (stream_symbol, mut record) = record.into_record();
+        record.set_currency(&self.fx);
+        (RecordKey::new(&stream_symbol, &record), record)
+    }
+
+    /// Replaces the displayed data with a snapshot from the serving process,
+    /// keeping this viewer's own expanded groups and selection.
+    pub fn apply_snapshot(&mut self, snapshot: Snapshot) {
+        if let Some(held) = &mut self.paused {
+            held.snapshot = Some(snapshot);
+            return;
+        }
+        let mut groups = BTreeMap::new();
+        for group in snapshot.groups {
+            let underlying = Symbol(group.underlying);
+            let (open, strategy, closed_expirations) = self
+                .groups
+                .get(&underlying)
+                .map_or((false, false, BTreeSet::new()), |group| {
+                    (group.open, group.strategy, group.closed_expirations.clone())
+                });
+            let group = UnderlyingGroup {
+                open,
+                closed_expirations,
+                strategy,
+                records: group
+                    .records
+                    .into_iter()
+                    .map(|record| self.snapshot_record(record))
+                    .collect(),
+                underlying: group.underlying_quote.map(Into::into),
+            };
+            groups.insert(underlying, group);
+        }
+        self.groups = groups;
+        self.expired.records = snapshot
+            .expired
+            .into_iter()
+            .map(|record| self.snapshot_record(record))
+            .collect();
+        self.watchlist = snapshot
+            .watchlist
+            .into_iter()
+            .map(|item| WatchItem {
+                symbol: Symbol(item.symbol),
+                label: item.label,
+                quote: item.quote.into(),
+                bid: item.bid,
+                ask: item.ask,
+            })
+            .collect();
+        self.balances = snapshot.balances;
+        self.premium = snapshot.premium;
+        self.opened = snapshot.opened;
+        self.local_state.notes = snapshot
+            .notes
+            .into_iter()
+            .map(|(key, text)| {
+                let note = Note {
+                    text,
+                    closed_since: None,
+                };
+                (key, note)
+            })
+            .collect();
+        self.update_num_lines();
+        self.restore_view();
+    }
+
+    /// Applies a quote feed event, returning whether it should be drawn
+    /// straight away.
+    pub fn apply_quote_event(&mut self, event: Event) -> bool {
+        if let Some(held) = &mut self.paused {
+            let kind = std::mem::discriminant(&event.data);
+            held.events.insert((event.sym.clone(), kind), event);
+            return false;
+        }
+        let sym = DxFeedSymbol(event.sym);
+        match event.data {
+            EventData::Quote(quote) => {
+                self.stream_stats.count_feed("quote");
+                // Sides without a price come as NaN; such quotes leave the
+                // last price standing rather than zeroing it.
+                let (bid, ask) = match (
+                    Decimal::from_f64(quote.bid_price),
+                    Decimal::from_f64(quote.ask_price),
+                ) {
+                    (Some(bid), Some(ask)) if !(bid + ask).is_zero() => (bid, ask),
+                    _ => return false,
+                };
+                let mid = (bid + ask) / Decimal::TWO;
+                let size = |size: f64| if size.is_finite() { size } else { 0.0 };
+                for record in self.records_mut(&sym) {
+                    record.current = mid;
+                    record.updated = Some(Instant::now());
+                    record.quote = Some(BidAsk {
+                        bid,
+                        bid_size: size(quote.bid_size),
+                        ask,
+                        ask_size: size(quote.ask_size),
+                        delayed: false,
+                    });
+                }
+                if let Some(underlying) = self.get_underlying(&sym) {
+                    underlying.price = Some(mid);
+                }
+                for item in self.watched_mut(&sym) {
+                    item.quote.price = Some(mid);
+                    item.bid = Some(bid);
+                    item.ask = Some(ask);
+                }
+                if let Some(chain) = &mut self.chain {
+                    chain.apply_quote(&sym, bid, ask);
+                }
+                true
+            }
+            EventData::Greeks(greeks) => {
+                self.stream_stats.count_feed("greeks");
+                let in_chain = self
+                    .chain
+                    .as_mut()
+                    .map_or(false, |chain| chain.apply_delta(&sym, greeks.delta));
+                let mut underlying = None;
+                for (group_underlying, group) in self.groups.iter_mut() {
+                    for (_, record) in group
+                        .records
+                        .iter_mut()
+                        .filter(|(key, _)| key.stream_symbol == sym)
+                    {
+                        record.greeks = SimpleGreeks {
+                            theta: greeks.theta,
+                            delta: greeks.delta,
+                            gamma: greeks.gamma,
+                            vega: greeks.vega,
+                            rho: greeks.rho,
+                            volatility: Some(greeks.volatility).filter(|iv| iv.is_finite()),
+                        };
+                        record.updated = Some(Instant::now());
+                        underlying.get_or_insert_with(|| group_underlying.clone());
+                    }
+                }
+                let underlying = match underlying {
+                    Some(underlying) => underlying,
+                    None => return in_chain,
+                };
+                let due = self.greeks_marked.get(&underlying).map_or(true, |marked| {
+                    marked.elapsed() >= self.settings.greeks_interval
+                });
+                if due {
+                    self.greeks_marked.insert(underlying, Instant::now());
+                } else {
+                    // Drawn with the next price change, or the next alert
+                    // tick at the latest.
+                    self.greeks_pending = true;
+                    self.stream_stats.greeks_held += 1;
+                }
+                due
+            }
+            EventData::Profile(profile) => {
+                let halted = profile.trading_status as u32 == TRADING_STATUS_HALTED;
+                let mut changed = false;
+                if let Some(underlying) = self.get_underlying(&sym) {
+                    changed |= underlying.halted != halted;
+                    underlying.halted = halted;
+                }
+                for item in self.watched_mut(&sym) {
+                    changed |= item.quote.halted != halted;
+                    item.quote.halted = halted;
+                }
+                changed
+            }
+            EventData::Summary(summary) => {
+                self.stream_stats.count_feed("summary");
+                let prev_close = Decimal::from_f64(summary.prev_day_close_price);
+                for record in self.records_mut(&sym) {
+                    record.prev_close = prev_close;
+                }
+                if let Some(underlying) = self.get_underlying(&sym) {
+                    underlying.prev_close = prev_close;
+                }
+                for item in self.watched_mut(&sym) {
+                    item.quote.prev_close = prev_close;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_underlying(&mut self, symbol: &DxFeedSymbol) -> Option<&mut UnderlyingQuote> {
+        self.groups
+            .values_mut()
+            .filter_map(|group| group.underlying.as_mut())
+            .find(|underlying| underlying.symbol == *symbol)
+    }
+
+    pub fn watched_mut<'a>(
+        &'a mut self,
+        symbol: &'a DxFeedSymbol,
+    ) -> impl Iterator<Item = &'a mut WatchItem> {
+        self.watchlist
+            .iter_mut()
+            .filter(move |item| item.quote.symbol == *symbol)
+    }
+
+    /// Every account's position in the contract `symbol` streams.
+    pub fn records_mut<'a>(
+        &'a mut self,
+        symbol: &'a DxFeedSymbol,
+    ) -> impl Iterator<Item = &'a mut PriceRecord> {
+        self.groups
+            .values_mut()
+            .flat_map(|group| group.records.iter_mut())
+            .filter(move |(key, _)| key.stream_symbol == *symbol)
+            .map(|(_, record)| record)
+    }
+}
+
+/// Puts the terminal back the way it was found when dropped, so returning
+/// early with an error doesn't leave it in raw mode with the mouse captured.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            std::io::stdout(),
+            DisableMouseCapture,
+            DisableFocusChange,
+            LeaveAlternateScreen,
+            cursor::Show
+        );
+    }
+}
+
+fn setup_terminal() -> Result<(Terminal<CrosstermBackend<Stdout>>, TerminalGuard)> {
+    enable_raw_mode()?;
+    let guard = TerminalGuard;
+    let mut stdout = std::io::stdout();
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableFocusChange,
+        EnableMouseCapture
+    )?;
+    let backend = CrosstermBackend::new(stdout);
+    Ok((Terminal::new(backend)?, guard))
+}
+
+/// Downloads every account's positions and balances along with the symbols
+/// needed to stream them, or just the accounts in `shown` when it isn't
+/// empty. Underlyings in `known_underlyings` aren't looked up again, and
+/// `fresh_symbols` skips the streamer symbol cache. With `with_premium`,
+/// the last year's transactions are downloaded as well for the figures
+/// worked out from them. Underlyings without a share trade to say what
+/// they were opened at, and not in `known_opened`, are looked up in the
+/// price history.
+async fn load_positions(
+    tasty: &TastyTrade,
+    known_underlyings: BTreeSet<Symbol>,
+    known_opened: BTreeSet<String>,
+    with_premium: bool,
+    fresh_symbols: bool,
+    shown: &[String],
+) -> Result<LoadedPositions> {
+    let mut positions = Vec::new();
+    let mut balances = BTreeMap::new();
+    let accounts = tasty.accounts().await.context("Downloading accounts")?;
+    for account in accounts
+        .into_iter()
+        .filter(|account| shown.is_empty() || shown.contains(&account.number().0))
+    {
+        positions.extend(account.positions().await.context("Downloading positions")?);
+        let number = account.number().0;
+        let balance = accruals::balance(tasty, &number).await?;
+        balances.insert(
+            number,
+            AccountBalance {
+                cash: balance.cash_balance,
+                reported_net_liq: balance.net_liquidating_value,
+                derivative_buying_power: balance.derivative_buying_power,
+                maintenance_excess: balance.maintenance_excess,
+                accruals: balance.accruals,
+            },
+        );
+    }
+
+    let symbols: Vec<_> = positions
+        .iter()
+        .map(|pos| (pos.instrument_type.clone(), pos.symbol.clone()))
+        .collect();
+    let stream_syms = symbolcache::resolve(tasty, &symbols, fresh_symbols).await;
+    let stream_syms: Result<Vec<_>> = stream_syms.into_iter().collect();
+    let stream_syms = stream_syms.context("Downloading symbols")?;
+
+    let mut loaded: Vec<LoadedPosition> = positions
+        .iter()
+        .zip(stream_syms)
+        .map(|(pos, stream_symbol)| LoadedPosition {
+            underlying: pos.underlying_symbol.clone(),
+            stream_symbol,
+            record: PriceRecord {
+                account: pos.account_number.0.clone(),
+                symbol: pos.symbol.clone(),
+                instrument_type: pos.instrument_type.clone(),
+                open: pos.average_open_price.round_dp(2),
+                current: pos.close_price.round_dp(2),
+                amount: pos.quantity,
+                multiplier: pos.multiplier,
+                direction: pos.quantity_direction,
+                greeks: SimpleGreeks::default(),
+                quote: None,
+                option: OptionSymbol::parse(&pos.symbol.0),
+                deliverables: None,
+                currency: fx::HOME.to_owned(),
+                fx_rate: Some(Decimal::ONE),
+                prev_close: None,
+                updated: None,
+            },
+        })
+        .collect();
+
+    let mut warnings = vec![];
+    // Seeds prices so the first frame isn't yesterday's closes while the
+    // stream warms up.
+    let symbols = loaded
+        .iter()
+        .map(|pos| {
+            (
+                pos.record.instrument_type.clone(),
+                pos.record.symbol.clone(),
+            )
+        })
+        .collect();
+    match marketdata::fetch(tasty, symbols).await {
+        Ok(quotes) => {
+            let quotes: BTreeMap<_, _> = quotes
+                .into_iter()
+                .map(|quote| (quote.symbol.clone(), quote))
+                .collect();
+            for pos in &mut loaded {
+                if let Some(quote) = quotes.get(&pos.record.symbol.0) {
+                    pos.record.seed(quote);
+                }
+            }
+        }
+        Err(e) => warnings.push(format!("{:#}", e)),
+    }
+    let adjusted: BTreeSet<Symbol> = loaded
+        .iter()
+        .map(|pos| &pos.record)
+        .filter(|rec| {
+            rec.option
+                .as_ref()
+                .map_or(false, |option| instrument::is_adjusted_root(&option.root))
+        })
+        .map(|rec| rec.symbol.clone())
+        .collect();
+    let info_futures = adjusted
+        .iter()
+        .map(|symbol| instrument::equity_option(tasty, symbol));
+    let infos = futures::future::join_all(info_futures).await;
+    for (symbol, info) in adjusted.iter().zip(infos) {
+        let info = match info {
+            Ok(info) => info,
+            Err(e) => {
+                warnings.push(format!("{:#}", e));
+                continue;
+            }
+        };
+        for rec in loaded
+            .iter_mut()
+            .map(|pos| &mut pos.record)
+            .filter(|rec| rec.symbol == *symbol)
+        {
+            rec.adjust(&info);
+        }
+    }
+
+    let equity_underlyings: BTreeSet<Symbol> = positions
+        .iter()
+        .filter(|pos| {
+            matches!(
+                pos.instrument_type,
+                InstrumentType::Equity | InstrumentType::EquityOption
+            )
+        })
+        .map(|pos| pos.underlying_symbol.clone())
+        .filter(|underlying| !known_underlyings.contains(underlying))
+        .collect();
+    let underlying_symbols: Vec<_> = equity_underlyings
+        .iter()
+        .map(|underlying| (InstrumentType::Equity, underlying.clone()))
+        .collect();
+    let underlying_syms = symbolcache::resolve(tasty, &underlying_symbols, fresh_symbols).await;
+    // Underlyings that can't be resolved just go without a live price.
+    let underlyings = equity_underlyings
+        .into_iter()
+        .zip(underlying_syms)
+        .filter_map(|(underlying, stream_sym)| Some((underlying, stream_sym.ok()?)))
+        .collect();
+
+    let (premium, mut opened) = if with_premium {
+        let today = market::today();
+        let year_start = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today);
+        let since = year_start.min(today - chrono::Duration::days(365));
+        match transactions::fetch(tasty, balances.keys().cloned().collect(), since).await {
+            Ok(transactions) => (
+                Some(premium::by_underlying(&transactions, year_start)),
+                transactions::opening_prices(&transactions),
+            ),
+            Err(e) => {
+                warnings.push(format!("{:#}", e));
+                (None, BTreeMap::new())
+            }
+        }
+    } else {
+        (None, BTreeMap::new())
+    };
+
+    // The rest count from the close on the day their earliest position
+    // was opened. Without any history the figure is left out.
+    let mut first_opened: BTreeMap<&Symbol, NaiveDate> = BTreeMap::new();
+    let equity_positions = positions.iter().filter(|pos| {
+        matches!(
+            pos.instrument_type,
+            InstrumentType::Equity | InstrumentType::EquityOption
+        )
+    });
+    for pos in equity_positions {
+        let created = match DateTime::parse_from_rfc3339(&pos.created_at) {
+            Ok(created) => created.with_timezone(&New_York).date_naive(),
+            Err(_) => continue,
+        };
+        let date = first_opened
+            .entry(&pos.underlying_symbol)
+            .or_insert(created);
+        *date = (*date).min(created);
+    }
+    for (underlying, date) in first_opened {
+        if opened.contains_key(&underlying.0) || known_opened.contains(&underlying.0) {
+            continue;
+        }
+        match marketdata::close_on(tasty, underlying, date).await {
+            Ok(Some(price)) => {
+                let reference = OpenReference {
+                    price,
+                    date,
+                    from_close: true,
+                };
+                opened.insert(underlying.0.clone(), reference);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warnings.push(format!("{:#}", e));
+                break;
+            }
+        }
+    }
+
+    Ok(LoadedPositions {
+        positions: loaded,
+        underlyings,
+        balances,
+        premium,
+        opened: Some(opened),
+        warnings,
+    })
+}
+
+/// Starts downloading positions again, once any rate limit has run out.
+fn start_refresh<'a>(
+    tasty: &'a TastyTrade,
+    app: &mut App,
+    fresh_symbols: bool,
+    shown: &'a [String],
+) -> LocalBoxFuture<'a, Timed<LoadedPositions>> {
+    let delay = app.requests.backoff().unwrap_or_default();
+    let load = load_positions(
+        tasty,
+        app.known_underlyings(),
+        app.opened.keys().cloned().collect(),
+        app.premium_column,
+        fresh_symbols,
+        shown,
+    );
+    app.refreshing = Some(Instant::now());
+    async move {
+        tokio::time::sleep(delay).await;
+        requests::timed(load).await
+    }
+    .boxed_local()
+}
+
+/// Starts re-reading the watch file, if there is one.
+fn reload_watchlist<'a>(
+    tasty: &'a TastyTrade,
+    app: &App,
+) -> Option<LocalBoxFuture<'a, Timed<watchlist::Resolved>>> {
+    let path = app.watch_file.clone()?;
+    let listed = app.watch_symbols.clone();
+    let known = app.known_watched();
+    Some(
+        requests::timed(async move { watchlist::load(tasty, &listed, Some(&path), known).await })
+            .boxed_local(),
+    )
+}
+
+/// How long a request took, and what came of it.
+type Timed<T> = (Duration, Result<T>);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = Args::parse();
+    if let Some(Command::Diff {
+        old,
+        new,
+        threshold,
+    }) = &args.command
+    {
+        return diff::run(old, new, *threshold);
+    }
+    // Read up front so a bad script fails before logging in.
+    let script = match &args.script {
+        Some(path) => script::load(path)?,
+        None => vec![],
+    };
+    let config = Config::load()?;
+    let settings = Settings::new(
+        &config,
+        args.max_fps,
+        args.snapshot_interval,
+        args.staleness_threshold,
+    )?;
+    if let Some(warning) = config.permissions_warning() {
+        eprintln!("{}", warning);
+    }
+
+    if let Some(address) = &args.attach {
+        return run_viewer(address, &config, settings, &args, script).await;
+    }
+
+    println!("Logging in...");
+
+    let profile = config.select_profile(args.profile.as_deref())?;
+    let profile_name = profile.map(|(name, _)| name.to_owned());
+    args.sandbox |= profile.map_or(false, |(_, profile)| profile.sandbox);
+    // The flags take the environment variables into account already.
+    let login_given = args.login.is_some()
+        || profile.map_or(false, |(_, profile)| profile.login.is_some())
+        || config.login.is_some();
+    let setup = if !args.no_setup && setup::wanted(login_given) {
+        Some(setup::ask_login()?)
+    } else {
+        None
+    };
+    let login = match &setup {
+        Some(setup) => setup.login.as_str(),
+        None => args
+            .login
+            .as_deref()
+            .or_else(|| profile?.1.login.as_deref())
+            .or(config.login.as_deref())
+            .context("No credentials provided via --login, TASTY_LOGIN, or config file")?,
+    };
+    if args.forget_credentials {
+        if credentials::forget_password(login, profile_name.as_deref(), args.sandbox)? {
+            println!("Removed the saved password for {}", login);
+        }
+    }
+    let password = match args
+        .password
+        .as_deref()
+        .or_else(|| profile?.1.password.as_deref())
+        .or(config.password.as_deref())
+        .or(setup.as_ref().map(|setup| setup.password.as_str()))
+    {
+        Some(password) => password.to_owned(),
+        None => match credentials::saved_password(login, profile_name.as_deref(), args.sandbox) {
+            Some(password) => password,
+            None => credentials::prompt_password(login)?,
+        },
+    };
+    if args.check {
+        return check::run(login, &password, args.sandbox).await;
+    }
+    // tastytrade-rs has no way to send a one-time code with the login, so
+    // accounts with two-factor authentication get told so plainly. It also
+    // only logs in with a password and keeps the session and remember
+    // tokens to itself, so a session can't be saved and resumed.
+    let tasty = match TastyTrade::login(login, &password, args.sandbox).await {
+        Ok(tasty) => tasty,
+        Err(e) => {
+            let e = anyhow::Error::from(e);
+            if credentials::needs_otp(&e) {
+                anyhow::bail!(
+                    "tastytrade asked for a one-time code for {}, which can't be sent from here yet",
+                    login
+                );
+            }
+            return Err(e).context("Logging into tastytrade");
+        }
+    };
+    if args.save_credentials || setup.as_ref().map_or(false, |setup| setup.save_password) {
+        // Not being able to save doesn't stop this run.
+        match credentials::save_password(login, profile_name.as_deref(), args.sandbox, &password) {
+            Ok(()) => println!("Saved the password for {} in the keyring", login),
+            Err(e) => eprintln!("{:#}", e),
+        }
+    }
+
+    let account_streamer = tasty.create_account_streamer().await?;
+    let mut accounts = tasty.accounts().await.context("Downloading accounts")?;
+    let mut shown_accounts = if args.accounts.is_empty() {
+        profile
+            .and_then(|(_, profile)| profile.accounts.clone())
+            .unwrap_or_else(|| config.accounts.clone())
+    } else {
+        args.accounts.clone()
+    };
+    let numbers: Vec<String> = accounts.iter().map(|account| account.number().0).collect();
+    if let Some(setup) = &setup {
+        shown_accounts = setup::ask_accounts(&numbers)?;
+        let path = setup::write_config(&setup.login, &shown_accounts)?;
+        println!("Wrote {}", path.display());
+    }
+    let unknown: Vec<&str> = shown_accounts
+        .iter()
+        .filter(|number| !numbers.contains(number))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "No account {} on this login; available: {}",
+            unknown.join(", "),
+            numbers.join(", ")
+        );
+    }
+    accounts.retain(|account| {
+        shown_accounts.is_empty() || shown_accounts.contains(&account.number().0)
+    });
+    // Subscriptions that fail are tried again with a growing delay.
+    let subscribe_account = {
+        let (streamer, accounts) = (&account_streamer, &accounts);
+        move |i: usize, delay: Duration| {
+            async move {
+                tokio::time::sleep(delay).await;
+                let result = streamer
+                    .subscribe_to_account(&accounts[i])
+                    .await
+                    .context("Subscribing to account updates");
+                (i, result)
+            }
+            .boxed_local()
+        }
+    };
+    let mut account_subscriptions: FuturesUnordered<_> = (0..accounts.len())
+        .map(|i| subscribe_account(i, Duration::ZERO))
+        .collect();
+
+    println!("Downloading positions...");
+    let loaded = load_positions(
+        &tasty,
+        BTreeSet::new(),
+        BTreeSet::new(),
+        args.premium_column,
+        args.no_cache,
+        &shown_accounts,
+    )
+    .await?;
+
+    print!("Setting up quote streaming...");
+    let mut quote_streamer = tasty.create_quote_streamer().await?;
+    let quote_sub = quote_streamer.create_sub(
+        dxfeed::DXF_ET_QUOTE
+            | dxfeed::DXF_ET_GREEKS
+            | dxfeed::DXF_ET_SUMMARY
+            | dxfeed::DXF_ET_PROFILE,
+    );
+
+    let mut app = App::new(
+        profile_name.clone(),
+        accounts.iter().map(|account| account.number().0).collect(),
+        LocalState::load(profile_name.as_deref()),
+        Subscriptions::new(config.max_subscriptions),
+        &config,
+        settings,
+        &args,
+    );
+    app.apply_positions(loaded);
+    // Without them orders just aren't checked before the dry run.
+    match permissions::fetch(&tasty, app.balances.keys().cloned().collect()).await {
+        Ok(levels) => app.permissions = levels,
+        Err(e) => {
+            let warning = format!("{:#}", e);
+            app.status = Some(match app.status.take() {
+                Some(status) => format!("{}; {}", status, warning),
+                None => warning,
+            });
+        }
+    }
+    // Later changes come from the account streamer.
+    for account in &accounts {
+        match account.live_orders().await {
+            Ok(orders) => orders
+                .into_iter()
+                .for_each(|order| app.orders.update(order)),
+            Err(e) => {
+                let warning = format!("Downloading working orders: {:#}", e);
+                app.status = Some(match app.status.take() {
+                    Some(status) => format!("{}; {}", status, warning),
+                    None => warning,
+                });
+            }
+        }
+    }
+    if !app.watch_symbols.is_empty() || args.watch_file.is_some() {
+        let resolved = watchlist::load(
+            &tasty,
+            &app.watch_symbols,
+            args.watch_file.as_deref(),
+            BTreeMap::new(),
+        )
+        .await?;
+        app.apply_watchlist(resolved);
+        // Told about once; reloading the watch file doesn't try them again.
+        let watched = app.known_watched();
+        app.watch_symbols
+            .retain(|entry| watched.contains_key(&entry.symbol));
+    }
+    app.subscriptions.sync(&quote_sub);
+
+    let snapshot_tx = match &args.serve_state {
+        Some(address) => Some(share::serve(address, app.snapshot())?),
+        None => None,
+    };
+
+    let (mut terminal, _terminal_guard) = setup_terminal()?;
+
+    let mut keyboard_event_stream = script::events(script);
+    let mut rollover_check = tokio::time::interval(Duration::from_secs(60));
+    let mut snapshot_tick = tokio::time::interval(app.settings.snapshot_interval);
+    let mut spinner_tick = tokio::time::interval(Duration::from_millis(100));
+    let mut alert_tick = tokio::time::interval(Duration::from_secs(1));
+    // At most one refresh runs at a time; asking again while one is in
+    // flight does nothing.
+    let mut refresh: Option<LocalBoxFuture<'_, Timed<LoadedPositions>>> = None;
+    let mut watch_reload: Option<LocalBoxFuture<'_, Timed<watchlist::Resolved>>> = None;
+    // A symbol typed in to watch, being looked up.
+    let mut watch_lookup: Option<LocalBoxFuture<'_, (watchlist::Entry, Timed<DxFeedSymbol>)>> =
+        None;
+    let mut chain_load: Option<LocalBoxFuture<'_, Timed<chain::Chain>>> = None;
+    // The open ticket's dry run, and the order it is sending with its
+    // description.
+    let mut ticket_check: Option<LocalBoxFuture<'_, Timed<confirm::DryRun>>> = None;
+    let mut ticket_send: Option<LocalBoxFuture<'_, (String, Timed<String>)>> = None;
+    // One cancellation at a time, each with the order it was for.
+    let mut order_cancel: Option<LocalBoxFuture<'_, (String, Timed<LiveOrderRecord>)>> = None;
+    // Balance messages don't carry accruals, so they are read again
+    // after one, at most every ACCRUAL_RELOAD_INTERVAL for each account.
+    let mut accrual_reload: Option<LocalBoxFuture<'_, Timed<(String, accruals::Accruals)>>> = None;
+    let mut accruals_read: BTreeMap<String, Instant> = app
+        .balances
+        .keys()
+        .map(|account| (account.clone(), Instant::now()))
+        .collect();
+    let mut hangup = signal(SignalKind::hangup()).context("Listening for SIGHUP")?;
+
+    loop {
+        let next_draw = app.next_draw();
+        tokio::select! {
+            ev = quote_sub.get_event() => {
+                match ev {
+                    Ok(event) => {
+                        app.stream_stats.quote_traffic.event();
+                        if app.apply_quote_event(event) {
+                            app.check_listed_groups();
+                            app.dirty = true;
+                        }
+                    }
+                    Err(e) => {
+                        app.stream_stats.quote_traffic.error(format!("{:?}", e));
+                        app.dirty = true;
+                    }
+                }
+            }
+            _ = rollover_check.tick() => {
+                app.check_rollover(&quote_sub);
+                app.dirty = true;
+            }
+            // Draws held back by --max-fps or lack of focus.
+            _ = tokio::time::sleep_until(next_draw.unwrap_or_else(Instant::now).into()), if next_draw.is_some() => {}
+            (latency, loaded) = async { refresh.as_mut().unwrap().await }, if refresh.is_some() => {
+                app.dirty = true;
+                refresh = None;
+                app.refreshing = None;
+                app.requests.record("positions", latency, &loaded);
+                match loaded {
+                    Ok(loaded) => {
+                        app.apply_positions(loaded);
+                        app.subscriptions.sync(&quote_sub);
+                    }
+                    // The table keeps showing what it had.
+                    Err(e) => app.status = Some(format!("Refresh failed: {:#}", e)),
+                }
+            }
+            _ = spinner_tick.tick(), if app.refreshing.is_some() => app.dirty = true,
+            _ = alert_tick.tick() => {
+                // Both have to run every tick.
+                let alerted = app.check_alerts();
+                app.orders.prune();
+                // The connection bar's rates count down once a stream goes
+                // quiet, so it is redrawn every tick as well.
+                if app.take_pending_greeks() || alerted || connection_bar_height(&app) > 0 {
+                    app.dirty = true;
+                }
+                app.save_view(false);
+            }
+            _ = hangup.recv() => {
+                if watch_reload.is_none() {
+                    watch_reload = reload_watchlist(&tasty, &app);
+                }
+            }
+            (latency, resolved) = async { watch_reload.as_mut().unwrap().await }, if watch_reload.is_some() => {
+                app.dirty = true;
+                watch_reload = None;
+                app.requests.record("watchlist", latency, &resolved);
+                match resolved {
+                    Ok(resolved) => {
+                        app.apply_watchlist(resolved);
+                        app.subscriptions.sync(&quote_sub);
+                    }
+                    Err(e) => app.status = Some(format!("Reloading watchlist failed: {:#}", e)),
+                }
+            }
+            (entry, (latency, resolved)) = async { watch_lookup.as_mut().unwrap().await }, if watch_lookup.is_some() => {
+                watch_lookup = None;
+                app.dirty = true;
+                app.requests.record("watch lookup", latency, &resolved);
+                app.watch_resolved(entry, resolved);
+                app.subscriptions.sync(&quote_sub);
+            }
+            (latency, loaded) = async { chain_load.as_mut().unwrap().await }, if chain_load.is_some() => {
+                chain_load = None;
+                app.dirty = true;
+                app.requests.record("option chain", latency, &loaded);
+                match loaded {
+                    Ok(chain) => {
+                        app.close_chain();
+                        app.open_chain(chain);
+                        app.subscriptions.sync(&quote_sub);
+                    }
+                    Err(e) => {
+                        app.roll = None;
+                        app.status = Some(format!("{:#}", e));
+                    }
+                }
+            }
+            (latency, checked) = async { ticket_check.as_mut().unwrap().await }, if ticket_check.is_some() => {
+                ticket_check = None;
+                app.dirty = true;
+                app.requests.record("dry run", latency, &checked);
+                let dry_run = checked.unwrap_or_else(|e| confirm::DryRun::Failed(format!("{:#}", e)));
+                if let confirm::DryRun::Rejected(error) = &dry_run {
+                    app.notices.push(notify::Kind::Rejected, error.clone());
+                }
+                if let Some(ticket) = &mut app.ticket {
+                    ticket.checked(dry_run);
+                }
+            }
+            (order, (latency, sent)) = async { ticket_send.as_mut().unwrap().await }, if ticket_send.is_some() => {
+                ticket_send = None;
+                app.orders_in_flight -= 1;
+                app.dirty = true;
+                app.requests.record("place order", latency, &sent);
+                // A sent ticket closes whatever came back: an error doesn't
+                // prove the order wasn't placed, and sending it again could
+                // place it twice.
+                app.ticket = None;
+                match sent {
+                    Ok((id, warning)) => {
+                        app.orders.track(id);
+                        app.notices.push(notify::Kind::Info, format!("Order placed: {}", order));
+                        if let Some(warning) = warning {
+                            app.status = Some(warning);
+                        }
+                    }
+                    Err(e) => app.notices.push(
+                        notify::Kind::Rejected,
+                        format!("{:#}; check the working orders before placing it again", e),
+                    ),
+                }
+            }
+            (order, (latency, cancelled)) = async { order_cancel.as_mut().unwrap().await }, if order_cancel.is_some() => {
+                order_cancel = None;
+                app.orders_in_flight -= 1;
+                app.dirty = true;
+                app.requests.record("cancel order", latency, &cancelled);
+                // The order itself leaves the pane when the account streamer
+                // says it is cancelled.
+                match cancelled {
+                    Ok((cancelled, warning)) => {
+                        app.orders.track(orders::id(&cancelled));
+                        app.notices
+                            .push(notify::Kind::Info, format!("Cancel accepted: {}", order));
+                        if let Some(warning) = warning {
+                            app.status = Some(warning);
+                        }
+                    }
+                    Err(e) => app.notices.push(
+                        notify::Kind::Rejected,
+                        format!("Cancel rejected: {}: {:#}", order, e),
+                    ),
+                }
+            }
+            (latency, read) = async { accrual_reload.as_mut().unwrap().await }, if accrual_reload.is_some() => {
+                accrual_reload = None;
+                app.requests.record("accruals", latency, &read);
+                match read {
+                    Ok((account, accruals)) => {
+                        app.apply_accruals(&account, accruals);
+                        app.dirty = true;
+                    }
+                    // The ones already shown are kept.
+                    Err(e) => app.status = Some(format!("Reading accruals failed: {:#}", e)),
+                }
+            }
+            _ = snapshot_tick.tick(), if snapshot_tx.is_some() => {
+                if let Some(tx) = &snapshot_tx {
+                    tx.send_replace(app.snapshot());
+                }
+            }
+            Some((i, result)) = account_subscriptions.next(), if !account_subscriptions.is_empty() => {
+                let account = accounts[i].number().0;
+                if let Some(delay) = app.account_subscribed(account, result) {
+                    account_subscriptions.push(subscribe_account(i, delay));
+                }
+                app.dirty = true;
+            }
+            ev = account_streamer.get_event() => {
+                // Everything is counted for the stream panel, so types the app
+                // doesn't handle yet can be seen arriving.
+                match ev {
+                    Ok(AccountEvent::AccountMessage(msg)) => {
+                        let handled = matches!(
+                            *msg,
+                            AccountMessage::AccountBalance(_)
+                                | AccountMessage::CurrentPosition(_)
+                                | AccountMessage::Order(_)
+                        );
+                        app.stream_stats.record(format!("{:?}", msg), handled);
+                        if let AccountMessage::Order(order) = *msg {
+                            app.notices.order(&order);
+                            app.orders.update(*order);
+                        } else if let AccountMessage::CurrentPosition(pos) = *msg {
+                            // New positions need their streamer symbols,
+                            // which a download looks up along with the rest.
+                            if app.apply_streamed_position(*pos) && refresh.is_none() {
+                                refresh = Some(start_refresh(&tasty, &mut app, args.no_cache, &shown_accounts));
+                            }
+                            app.subscriptions.sync(&quote_sub);
+                        } else if let AccountMessage::AccountBalance(bal) = *msg {
+                            let account = bal.account_number.0.clone();
+                            app.apply_balance(
+                                bal.account_number.0,
+                                AccountBalance {
+                                    cash: bal.cash_balance,
+                                    reported_net_liq: bal.net_liquidating_value,
+                                    derivative_buying_power: bal.derivative_buying_power,
+                                    maintenance_excess: bal.maintenance_excess,
+                                    accruals: Default::default(),
+                                },
+                            );
+                            // Skipped while rate limited or read lately; a
+                            // later balance message asks again.
+                            let due = accruals_read
+                                .get(&account)
+                                .map_or(true, |read| read.elapsed() >= ACCRUAL_RELOAD_INTERVAL);
+                            if due && accrual_reload.is_none() && app.requests.backoff().is_none() {
+                                accruals_read.insert(account.clone(), Instant::now());
+                                accrual_reload = Some(
+                                    requests::timed(accruals::fetch(&tasty, account)).boxed_local(),
+                                );
+                            }
+                        }
+                    }
+                    Ok(other) => app.stream_stats.record(format!("{:?}", other), false),
+                    Err(e) => app.stream_stats.record_error(format!("{:?}", e)),
+                }
+                app.dirty = true;
+            }
+            maybe_event = keyboard_event_stream.next() => {
+                app.dirty = true;
+                match maybe_event {
+                    Some(Ok(event)) => {
+                        app.handle_event(&event);
+                        if let event::Event::Key(key) = event {
+                            if key.kind == KeyEventKind::Press {
+                                match app.handle_key(key.code) {
+                                    Some(Action::Quit) => break,
+                                    Some(Action::RetrySubscriptions) => app.retry_subscriptions(&quote_sub),
+                                    Some(Action::Refresh) => {
+                                        if refresh.is_none() {
+                                            refresh = Some(start_refresh(&tasty, &mut app, args.no_cache, &shown_accounts));
+                                        }
+                                    }
+                                    Some(Action::ReloadWatchlist) => {
+                                        if watch_reload.is_none() {
+                                            watch_reload = reload_watchlist(&tasty, &app);
+                                        }
+                                    }
+                                    Some(Action::Watch(entry)) => {
+                                        let tasty = &tasty;
+                                        watch_lookup = Some(
+                                            async move {
+                                                let resolved = requests::timed(watchlist::resolve(tasty, &entry.symbol)).await;
+                                                (entry, resolved)
+                                            }
+                                            .boxed_local(),
+                                        );
+                                    }
+                                    Some(Action::OpenChain(underlying)) => {
+                                        if chain_load.is_none() {
+                                            chain_load = Some(
+                                                requests::timed(chain::fetch(&tasty, underlying, market::today()))
+                                                    .boxed_local(),
+                                            );
+                                        }
+                                    }
+                                    Some(Action::SyncSubscriptions) => app.subscriptions.sync(&quote_sub),
+                                    Some(Action::Resubscribe(symbol)) => app.resubscribe(symbol, &quote_sub),
+                                    Some(Action::Ticket(request)) => {
+                                        let number = app.ticket.as_ref().map(|ticket| ticket.account.clone());
+                                        let account = accounts.iter().find(|account| Some(account.number().0) == number);
+                                        match (request, account) {
+                                            _ if app.read_only => {
+                                                app.ticket = None;
+                                                if let Err(e) = app.guard().check("placing orders") {
+                                                    app.status = Some(format!("{:#}", e));
+                                                }
+                                            }
+                                            (_, None) => {
+                                                app.ticket = None;
+                                                app.status = Some("The ticket's account isn't shown".to_owned());
+                                            }
+                                            // A newer dry run replaces one still out.
+                                            (ticket::Request::DryRun(order), Some(account)) => {
+                                                ticket_check = Some(requests::timed(ticket::dry_run(app.guard(), account, order)).boxed_local());
+                                            }
+                                            (ticket::Request::Send(order, details), Some(account)) => {
+                                                let described = format!(
+                                                    "{} {} @ {}",
+                                                    details.symbol.clone().unwrap_or_default(),
+                                                    details.quantity.unwrap_or_default().normalize(),
+                                                    details.price.unwrap_or_default()
+                                                );
+                                                let log = app.trade_log.clone();
+                                                let guard = app.guard();
+                                                app.orders_in_flight += 1;
+                                                ticket_send = Some(
+                                                    async move {
+                                                        (described, requests::timed(ticket::send(guard, account, log, order, details)).await)
+                                                    }
+                                                    .boxed_local(),
+                                                );
+                                            }
+                                        }
+                                    }
+                                    Some(Action::CancelOrder(id)) => {
+                                        match app.orders.working_order(&id) {
+                                            _ if app.read_only => {
+                                                if let Err(e) = app.guard().check("cancelling orders") {
+                                                    app.status = Some(format!("{:#}", e));
+                                                }
+                                            }
+                                            _ if order_cancel.is_some() => {
+                                                app.status = Some("Another cancel is still in flight".to_owned())
+                                            }
+                                            Some(order) => {
+                                                let order = order.clone();
+                                                let log = app.trade_log.clone();
+                                                let guard = app.guard();
+                                                let tasty = &tasty;
+                                                app.orders_in_flight += 1;
+                                                order_cancel = Some(
+                                                    async move {
+                                                        let described = orders::describe(&order);
+                                                        let cancelled = requests::timed(orders::cancel(guard, tasty, log, &order)).await;
+                                                        (described, cancelled)
+                                                    }
+                                                    .boxed_local(),
+                                                );
+                                            }
+                                            None => app.status = Some("The order is no longer working".to_owned()),
+                                        }
+                                    }
+                                    None => {}
+                                }
+                            }
+
+                        }
+                    }
+                    Some(Err(e)) => println!("Error: {:?}\r", e),
+                    None => break,
+                }
+            }
+        }
+
+        if app.needs_draw() {
+            terminal.draw(|f| ui(f, &mut app))?;
+            app.drawn();
+        }
+    }
+
+    app.save_view(true);
+    Ok(())
+}
+
+/// Runs only the TUI, fed by the snapshots another instance serves.
+async fn run_viewer(
+    address: &share::Address,
+    config: &Config,
+    settings: Settings,
+    args: &Args,
+    script: Vec<script::Step>,
+) -> Result<()> {
+    let mut snapshots = share::attach(address).await?;
+    let mut app = App::new(
+        None,
+        vec![],
+        LocalState::default(),
+        Subscriptions::new(0),
+        config,
+        settings,
+        args,
+    );
+
+    let (mut terminal, _terminal_guard) = setup_terminal()?;
+    let mut keyboard_event_stream = script::events(script);
+    let mut connected = true;
+
+    loop {
+        let next_draw = app.next_draw();
+        tokio::select! {
+            _ = tokio::time::sleep_until(next_draw.unwrap_or_else(Instant::now).into()), if next_draw.is_some() => {}
+            snapshot = snapshots.recv(), if connected => {
+                app.dirty = true;
+                match snapshot {
+                    Some(Ok(snapshot)) => {
+                        app.apply_snapshot(snapshot);
+                        app.check_listed_groups();
+                    }
+                    Some(Err(e)) => {
+                        app.status = Some(format!("Disconnected: {:#}", e));
+                        connected = false;
+                    }
+                    None => connected = false,
+                }
+            }
+            maybe_event = keyboard_event_stream.next() => {
+                app.dirty = true;
+                match maybe_event {
+                    Some(Ok(event::Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                        // Everything except quitting is handled locally.
+                        if let Some(Action::Quit) = app.handle_key(key.code) {
+                            break;
+                        }
+                    }
+                    Some(Ok(event)) => app.handle_event(&event),
+                    Some(Err(e)) => println!("Error: {:?}\r", e),
+                    None => break,
+                }
+            }
+        }
+
+        if app.needs_draw() {
+            terminal.draw(|f| ui(f, &mut app))?;
+            app.drawn();
+        }
+    }
+
+    Ok(())
+}
+
+fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    // Decided on every draw, so resizing switches layouts both ways.
+    app.compact_layout = app.compact || f.size().width < COMPACT_WIDTH;
+    if app.compact_layout {
+        return compact_ui(f, app);
+    }
+    let strip = account_strip(app);
+    let rects = Layout::default()
+        .constraints(
+            [
+                Constraint::Length(u16::from(strip.is_some())),
+                Constraint::Min(0),
+                Constraint::Length(6),
+                Constraint::Length(1),
+                Constraint::Length(connection_bar_height(app)),
+            ]
+            .as_ref(),
+        )
+        .margin(2)
+        .split(f.size());
+    if let Some(strip) = strip {
+        f.render_widget(strip, rects[0]);
+    }
+
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let normal_style = Style::default().bg(Color::Blue);
+    let mut header_titles = vec![
+        "PORT %",
+        "SYMBOL",
+        "CURRENT",
+        "AMOUNT",
+        "TRADE PRICE",
+        "PROFIT",
+        if app.per_lot_greeks {
+            "THETA/lot"
+        } else {
+            "THETA $"
+        },
+        if app.per_lot_greeks {
+            "DELTA/lot"
+        } else {
+            "DELTA $"
+        },
+        "NET LIQ",
+        "DAY P/L",
+        "DAY %",
+        "P/L %",
+        "DTE",
+    ];
+    if app.credit_column {
+        header_titles.insert(CREDIT_COLUMN, "CREDIT");
+    }
+    if app.greek_columns {
+        if app.per_lot_greeks {
+            header_titles.extend(["GAMMA/lot", "VEGA/lot"]);
+        } else {
+            header_titles.extend(["GAMMA", "VEGA"]);
+        }
+        header_titles.push("IV");
+    }
+    if app.premium_column {
+        header_titles.push("PREMIUM");
+    }
+    if app.note_column {
+        header_titles.push("NOTE");
+    }
+    let header_cells = header_titles.iter().map(|h| sort_header(app, h, h));
+    let header = Row::new(header_cells).style(normal_style).height(1);
+
+    let mut total = app
+        .tab_records()
+        .fold(Decimal::zero(), |acc, rec| acc + rec.to_net(rec.current));
+
+    // Recomputed on every draw, so the split rolls over with the week.
+    let today = market::today();
+    let week_end = market::week_end(today);
+    let mut portfolio_theta = ThetaSplit::new(week_end);
+    for rec in app.tab_records() {
+        portfolio_theta.add(rec);
+    }
+
+    let selection = app.selection();
+    let day_column = DAY_COLUMN + usize::from(app.credit_column);
+    let mut rows: Vec<Row> = app
+        .table_groups()
+        .flat_map(|(underlying_symbol, records)| {
+            let mut rows = vec![];
+            let dust = app.is_dust(records);
+            let stale_cutoff = app.stale_cutoff(records);
+            // Placed in line order once the subtotals are in.
+            let mut leg_rows = BTreeMap::new();
+            let in_tab = app.tab_legs(underlying_symbol, records);
+            let subtotals = Subtotals::new(app, &in_tab, week_end);
+            let min_dte = in_tab
+                .iter()
+                .filter_map(|(_, rec)| rec.days_to_expiration(today))
+                .min();
+            for &(key, rec) in &in_tab {
+                if !records.open || !app.is_listed(rec) {
+                    continue;
+                }
+                let profit = rec.to_net(rec.current - rec.open);
+                let net_liq = rec.to_net(rec.current);
+                let (theta, delta) = if app.per_lot_greeks {
+                    (
+                        format!("{:.3}", rec.greeks.theta),
+                        format!("{:.3}", rec.greeks.delta),
+                    )
+                } else {
+                    (
+                        money(rec.net_theta()),
+                        money(rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap())),
+                    )
+                };
+
+                let mut name = rec.name(underlying_symbol);
+                if rec.deliverables.is_some() {
+                    name += " [ADJ]";
+                }
+                let mut cells = vec![
+                    app.port_percent(net_liq, total),
+                    format!(" {}", name),
+                    // Prices the stream hasn't quoted yet, seeded or left at
+                    // the close, are marked until it does, and so are ones
+                    // it has gone quiet on.
+                    if !BidAsk::is_live(&rec.quote) {
+                        money(rec.current) + "*"
+                    } else if rec.is_stale(stale_cutoff) {
+                        money(rec.current) + "~"
+                    } else {
+                        money(rec.current)
+                    },
+                    rec.amount_text(&app.theme),
+                    money(rec.open),
+                    money(profit),
+                    theta,
+                    delta,
+                    money(net_liq),
+                    rec.day_profit().map_or("".to_owned(), money),
+                    rec.day_percent().map_or("".to_owned(), |percent| {
+                        format!("{:+}%", percent.round_dp(2))
+                    }),
+                    percent_text(rec.profit_percent()),
+                    dte_text(rec.days_to_expiration(today)),
+                ];
+                if app.credit_column {
+                    cells.insert(
+                        CREDIT_COLUMN,
+                        rec.open_credit().map_or("".to_owned(), money),
+                    );
+                }
+                if app.greek_columns {
+                    let (gamma, vega) = rec.scaled_gamma_vega();
+                    if app.per_lot_greeks {
+                        cells.push(format!("{:.4}", rec.greeks.gamma));
+                        cells.push(format!("{:.3}", rec.greeks.vega));
+                    } else {
+                        cells.push(format!("{:.2}", gamma));
+                        cells.push(format!("{:.2}", vega));
+                    }
+                    cells.push(iv_text(rec.option.as_ref().and(rec.greeks.volatility)));
+                }
+                if app.premium_column {
+                    cells.push("".to_owned());
+                }
+                if app.note_column {
+                    cells.push(
+                        app.local_state
+                            .note(&rec.note_key())
+                            .unwrap_or_default()
+                            .to_owned(),
+                    );
+                }
+                let dimmed = dust || app.subscriptions.is_dropped(&key.stream_symbol);
+                let style = if dimmed {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+                let amount_color = app.theme.direction_color(rec.signed_amount());
+                let profit_color = app.theme.pnl_color(profit);
+                let day_color = rec.day_profit().and_then(|day| app.theme.pnl_color(day));
+                let current_color = if rec.is_stale(stale_cutoff) {
+                    Some(Color::DarkGray)
+                } else {
+                    app.is_wide(rec).then_some(Color::Yellow)
+                };
+                let expiring_color =
+                    (rec.days_to_expiration(today) == Some(0)).then_some(Color::Yellow);
+                let cells = cells.into_iter().enumerate().map(|(i, text)| {
+                    let cell = Cell::from(text);
+                    let color = match i {
+                        CURRENT_COLUMN => current_color,
+                        AMOUNT_COLUMN => amount_color,
+                        PROFIT_COLUMN => profit_color,
+                        i if i == day_column || i == day_column + 1 => day_color,
+                        i if i == day_column + 2 => profit_color,
+                        i if i == day_column + 3 => expiring_color,
+                        _ => None,
+                    };
+                    match color {
+                        Some(color) if !dimmed => cell.style(Style::default().fg(color)),
+                        _ => cell,
+                    }
+                });
+                leg_rows.insert(key, Row::new(cells).style(style));
+            }
+            for line in app.group_lines(underlying_symbol, records) {
+                match line {
+                    Selection::Record(_, key) => rows.extend(leg_rows.remove(&key)),
+                    Selection::Expiration(_, date) => {
+                        rows.push(expiration_row(app, &in_tab, date, dust))
+                    }
+                    _ => {}
+                }
+            }
+
+            // The underlying's own price and day change, once streamed.
+            let underlying_quote = records.underlying.as_ref();
+            let mut group_header = vec![
+                app.port_percent(subtotals.net_liq, total),
+                "".to_owned(),
+                underlying_quote
+                    .and_then(|quote| quote.price)
+                    .map_or("".to_owned(), money),
+                "".to_owned(),
+                "".to_owned(),
+                money(subtotals.profit),
+                subtotals.theta.to_string(),
+                money(subtotals.delta),
+                money(subtotals.net_liq),
+                subtotals.day.map_or("".to_owned(), money),
+                percent_text(underlying_quote.and_then(UnderlyingQuote::day_change_percent)),
+                percent_text(percent_of(subtotals.profit, subtotals.cost_basis)),
+                dte_text(min_dte),
+            ];
+            // Computed from the legs' current marks on every draw, so it
+            // moves with each of their quotes.
+            if let Some(strategy) = records.strategy().filter(|_| records.strategy) {
+                group_header[2] = money(strategy.mark);
+                group_header[3] = format!("{}x", strategy.units);
+                group_header[4] = money(strategy.open);
+                let delta: Decimal = records
+                    .records
+                    .values()
+                    .filter(|rec| rec.option.is_some())
+                    .map(|rec| rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap_or_default()))
+                    .sum();
+                group_header[7] = money(delta);
+            }
+            if app.credit_column {
+                group_header.insert(CREDIT_COLUMN, money(subtotals.credit));
+            }
+            if app.greek_columns {
+                let (gamma, vega) = in_tab
+                    .iter()
+                    .map(|(_, rec)| rec.scaled_gamma_vega())
+                    .fold((0.0, 0.0), |(g, v), (gamma, vega)| (g + gamma, v + vega));
+                group_header.push(format!("{:.2}", gamma));
+                group_header.push(format!("{:.2}", vega));
+                group_header.push(iv_text(average_iv(in_tab.iter().map(|(_, rec)| *rec))));
+            }
+            if app.premium_column {
+                group_header.push(
+                    app.premium
+                        .get(&underlying_symbol.0)
+                        .map_or("".to_owned(), |premium| money(*premium)),
+                );
+            }
+            let header_selected =
+                matches!(&selection, Some(Selection::Group(s)) if s == underlying_symbol);
+            let header_style = if dust {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                records
+                    .underlying
+                    .as_ref()
+                    .and_then(UnderlyingQuote::day_change_percent)
+                    .and_then(|percent| app.theme.day_change_color(percent.to_f64()?))
+                    .map_or(Style::default(), |color| {
+                        // The highlight reverses the row, so a foreground tint
+                        // shows up as the highlight bar's color when selected.
+                        if header_selected {
+                            Style::default().fg(color)
+                        } else {
+                            Style::default().bg(color)
+                        }
+                    })
+            };
+            let mut name = vec![Span::raw(if records.is_halted() {
+                format!("{}  HALTED", underlying_symbol.0)
+            } else {
+                underlying_symbol.0.clone()
+            })];
+            if let Some((marker, color)) = app.theme.net_direction(records.net_delta()) {
+                let style = if dust {
+                    Style::default()
+                } else {
+                    Style::default().fg(color)
+                };
+                name.push(Span::styled(format!(" {}", marker), style));
+            }
+            if let Some(note) = subtotals.legs_shown() {
+                name.push(Span::styled(note, Style::default().fg(Color::DarkGray)));
+            }
+            let mut header_cells: Vec<Cell> = group_header.into_iter().map(Cell::from).collect();
+            header_cells[1] = Cell::from(Spans::from(name));
+            if let Some(color) = app.theme.pnl_color(subtotals.profit).filter(|_| !dust) {
+                let cell = std::mem::replace(&mut header_cells[PROFIT_COLUMN], Cell::from(""));
+                header_cells[PROFIT_COLUMN] = cell.style(Style::default().fg(color));
+            }
+            let day_color = subtotals.day.and_then(|day| app.theme.pnl_color(day));
+            let colors = [
+                (day_column, day_color),
+                (day_column + 2, app.theme.pnl_color(subtotals.profit)),
+                (
+                    day_column + 3,
+                    (min_dte == Some(0)).then_some(Color::Yellow),
+                ),
+            ];
+            for (i, color) in colors {
+                if let Some(color) = color.filter(|_| !dust) {
+                    let cell = std::mem::replace(&mut header_cells[i], Cell::from(""));
+                    header_cells[i] = cell.style(Style::default().fg(color));
+                }
+            }
+            rows.insert(0, Row::new(header_cells).style(header_style));
+
+            rows
+        })
+        .collect();
+
+    let expired: Vec<&PriceRecord> = app
+        .expired
+        .records
+        .values()
+        .filter(|rec| app.in_tab(rec) && app.filter_matches(&rec.symbol))
+        .collect();
+    if !expired.is_empty() {
+        let expired_style = Style::default().fg(Color::DarkGray);
+        let final_profit = |rec: &PriceRecord| rec.to_net(rec.current - rec.open);
+        let profit_sum = expired
+            .iter()
+            .fold(Decimal::zero(), |acc, rec| acc + final_profit(rec));
+        rows.push(
+            Row::new(vec![
+                "".to_owned(),
+                "EXPIRED".to_owned(),
+                "".to_owned(),
+                "".to_owned(),
+                "".to_owned(),
+                money(profit_sum),
+            ])
+            .style(expired_style),
+        );
+        if app.expired.open {
+            for rec in expired {
+                rows.push(
+                    Row::new(vec![
+                        "".to_owned(),
+                        format!(" {}", rec.symbol.0),
+                        money(rec.current),
+                        rec.amount_text(&app.theme),
+                        money(rec.open),
+                        money(final_profit(rec)),
+                    ])
+                    .style(expired_style),
+                );
+            }
+        }
+    }
+
+    if !app.watchlist.is_empty() {
+        // No quantities, so nothing here counts towards the totals.
+        rows.push(
+            Row::new(vec!["", "WATCHLIST", "MID", "DAY %", "BID", "ASK"])
+                .style(Style::default().fg(Color::Red)),
+        );
+        for item in &app.watchlist {
+            let name = match &item.label {
+                Some(label) => format!(" {}  {}", item.symbol.0, label),
+                None => format!(" {}", item.symbol.0),
+            };
+            let name = if item.quote.halted {
+                name + "  HALTED"
+            } else {
+                name
+            };
+            let style = if app.subscriptions.is_dropped(&item.quote.symbol) {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            let change = item.quote.day_change_percent();
+            let mut change_cell = Cell::from(change.map_or("".to_owned(), |percent| {
+                format!("{:+}%", percent.round_dp(2))
+            }));
+            let dropped = app.subscriptions.is_dropped(&item.quote.symbol);
+            if let Some(color) = change
+                .and_then(|percent| app.theme.pnl_color(percent))
+                .filter(|_| !dropped)
+            {
+                change_cell = change_cell.style(Style::default().fg(color));
+            }
+            rows.push(
+                Row::new(vec![
+                    Cell::from(""),
+                    Cell::from(name),
+                    Cell::from(
+                        item.quote
+                            .price
+                            .map_or("-".to_owned(), |price| price.round_dp(2).to_string()),
+                    ),
+                    change_cell,
+                    Cell::from(item.bid.map_or("-".to_owned(), money)),
+                    Cell::from(item.ask.map_or("-".to_owned(), money)),
+                ])
+                .style(style),
+            );
+        }
+    }
+
+    // Everything from here on is pinned under the lines, which scroll.
+    let rows_listed = std::mem::take(&mut rows);
+
+    rows.push(Row::new(vec![""]));
+    rows.push(Row::new(vec!["CASH"]));
+    let mut accrued = Decimal::zero();
+    for (account, balance) in app.tab_balances() {
+        rows.push(Row::new(vec![
+            " ".to_owned() + account,
+            balance.cash.to_string(),
+        ]));
+        total += balance.cash;
+        // Not cash yet, so left out of the totals.
+        let accruals = [
+            ("interest", balance.accruals.accrued_interest),
+            ("dividends", balance.accruals.pending_dividends),
+        ];
+        for (label, value) in accruals {
+            if let Some(value) = value {
+                rows.push(
+                    Row::new(vec!["".to_owned(), format!("  {}", label), money(value)])
+                        .style(Style::default().fg(Color::DarkGray)),
+                );
+            }
+        }
+        accrued += balance.accruals.total();
+    }
+
+    rows.push(Row::new(vec![""]));
+    rows.push(
+        Row::new(vec![
+            "BALANCES",
+            "NET LIQ",
+            "DERIV BP",
+            "MAINT EXCESS",
+            "OPTIONS",
+        ])
+        .style(Style::default().fg(Color::Red)),
+    );
+    let balance_row = |label: String, net_liq, buying_power, excess, level: String| {
+        Row::new(vec![
+            label,
+            money(net_liq),
+            money(buying_power),
+            money(excess),
+            level,
+        ])
+    };
+    let mut balance_totals = (Decimal::zero(), Decimal::zero(), Decimal::zero());
+    for (account, balance) in app.tab_balances() {
+        rows.push(balance_row(
+            " ".to_owned() + account,
+            balance.reported_net_liq,
+            balance.derivative_buying_power,
+            balance.maintenance_excess,
+            app.permissions
+                .get(account)
+                .map_or("".to_owned(), ToString::to_string),
+        ));
+        balance_totals.0 += balance.reported_net_liq;
+        balance_totals.1 += balance.derivative_buying_power;
+        balance_totals.2 += balance.maintenance_excess;
+    }
+    rows.push(balance_row(
+        "TOTAL".to_owned(),
+        balance_totals.0,
+        balance_totals.1,
+        balance_totals.2,
+        "".to_owned(),
+    ));
+
+    let reconcile_row = |label: String, computed: Decimal, reported: Decimal| {
+        let diff = computed - reported;
+        let diff_style = if diff.abs() > app.net_liq_tolerance {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        Row::new(vec![
+            Cell::from(label),
+            Cell::from(computed.round_dp(2).to_string()),
+            Cell::from(reported.round_dp(2).to_string()),
+            Cell::from(diff.round_dp(2).to_string()).style(diff_style),
+        ])
+    };
+    rows.push(Row::new(vec![""]));
+    rows.push(
+        Row::new(vec!["NET LIQ", "COMPUTED", "REPORTED", "DIFF"])
+            .style(Style::default().fg(Color::Red)),
+    );
+    let mut reported_total = Decimal::zero();
+    for (account, balance) in app.tab_balances() {
+        let positions = app
+            .groups
+            .values()
+            .flat_map(|group| group.records.values())
+            .filter(|rec| rec.account == *account)
+            .fold(Decimal::zero(), |acc, rec| acc + rec.to_net(rec.current));
+        rows.push(reconcile_row(
+            " ".to_owned() + account,
+            positions + balance.cash,
+            balance.reported_net_liq,
+        ));
+        reported_total += balance.reported_net_liq;
+    }
+    rows.push(reconcile_row("TOTAL".to_owned(), total, reported_total));
+
+    // From the latest greeks on every draw, hidden groups included.
+    let greeks_of = |account: Option<&String>| {
+        app.tab_records()
+            .filter(|rec| account.map_or(true, |account| rec.account == *account))
+            .fold((Decimal::zero(), 0.0), |(theta, delta), rec| {
+                (theta + rec.net_theta(), delta + rec.scaled_delta_gamma().0)
+            })
+    };
+    let greeks_row = |label: String, (theta, delta): (Decimal, f64)| {
+        Row::new(vec![label, money(theta), format!("{:.2}", delta)])
+    };
+    rows.push(Row::new(vec![""]));
+    rows.push(Row::new(vec!["GREEKS", "THETA $", "DELTA"]).style(Style::default().fg(Color::Red)));
+    if app.tab_balances().count() > 1 {
+        for (account, _) in app.tab_balances() {
+            rows.push(greeks_row(
+                " ".to_owned() + account,
+                greeks_of(Some(account)),
+            ));
+        }
+    }
+    rows.push(greeks_row("TOTAL".to_owned(), greeks_of(None)));
+
+    rows.push(Row::new(vec![""]));
+    rows.push(
+        Row::new(vec!["THETA", "THIS WEEK", "LATER", "THIS WEEK %"])
+            .style(Style::default().fg(Color::Red)),
+    );
+    let this_week_percent = if portfolio_theta.total().is_zero() {
+        "-".to_owned()
+    } else {
+        format!(
+            "{}%",
+            (portfolio_theta.this_week * Decimal::from(100) / portfolio_theta.total()).round_dp(1)
+        )
+    };
+    rows.push(Row::new(vec![
+        "TOTAL".to_owned(),
+        portfolio_theta.this_week.round_dp(2).to_string(),
+        portfolio_theta.later.round_dp(2).to_string(),
+        this_week_percent,
+    ]));
+    rows.push(Row::new(vec![
+        "INCOME".to_owned(),
+        format!(
+            "{} / month",
+            money(portfolio_theta.total() * Decimal::from(INCOME_DAYS) + accrued)
+        ),
+    ]));
+
+    let mut widths = vec![
+        Constraint::Length(8),
+        Constraint::Length(25),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(18),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(5),
+    ];
+    if app.credit_column {
+        widths.insert(CREDIT_COLUMN, Constraint::Length(12));
+    }
+    if app.greek_columns {
+        widths.extend([
             Constraint::Length(12),
             Constraint::Length(12),
+            Constraint::Length(7),
         ]);
+    }
+    if app.premium_column {
+        widths.push(Constraint::Length(12));
+    }
+    if app.note_column {
+        widths.push(Constraint::Length(20));
+    }
+
+    let footer = rows;
+    let inner = Block::default().borders(Borders::ALL).inner(rects[1]);
+    // At most half the table, showing its end, where the totals are.
+    let footer_height = (footer.len() as u16).min(inner.height / 2);
+    let parts = Layout::default()
+        .constraints([Constraint::Min(0), Constraint::Length(footer_height)].as_ref())
+        .split(inner);
+    app.table_drawn(parts[0]);
+    f.render_widget(table_block(app), rects[1]);
+    let (rows, mut state) = app.visible_rows(rows_listed);
+    let t = Table::new(rows)
+        .header(header)
+        .highlight_style(selected_style)
+        .highlight_symbol(">> ")
+        .widths(&widths);
+    f.render_stateful_widget(t, parts[0], &mut state);
+    let footer_len = footer.len();
+    let footer = Table::new(
+        footer
+            .into_iter()
+            .skip(footer_len - usize::from(footer_height)),
+    )
+    .highlight_symbol(">> ")
+    .widths(&widths);
+    // Selecting past the end highlights nothing but indents the rows like
+    // the lines above while one of them is selected.
+    let mut footer_state = TableState::default();
+    footer_state.select(state.selected().map(|_| footer_len));
+    f.render_stateful_widget(footer, parts[1], &mut footer_state);
+
+    f.render_widget(detail_pane(app), rects[2]);
+    bottom_line(f, app, rects[3]);
+    f.render_widget(connection_bar(app), rects[4]);
+    popups(f, app);
+}
+
+/// The layout for narrow terminals: group headers and positions with just
+/// their P/L and net liq, a one line summary, and the detail pane as a full
+/// screen view toggled with Enter. Its rows follow `App::lines` just like
+/// the full table's, so the selection carries over between layouts.
+fn compact_ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let rects = Layout::default()
+        .constraints(
+            [
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(connection_bar_height(app)),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    if app.detail_open {
+        let detail = detail_pane(app)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" DETAIL (Enter to go back) "),
+            )
+            .wrap(Wrap { trim: false });
+        f.render_widget(detail, rects[0]);
+    } else {
+        let profit = |rec: &PriceRecord| rec.to_net(rec.current - rec.open);
+        let mut rows = vec![];
+        let week_end = market::week_end(market::today());
+        for (underlying, group) in app.table_groups() {
+            let legs = app.tab_legs(underlying, group);
+            let subtotals = Subtotals::new(app, &legs, week_end);
+            let in_tab: Vec<&PriceRecord> = legs.into_iter().map(|(_, rec)| rec).collect();
+            let mut name = underlying.0.clone();
+            if let Some((marker, _)) = app.theme.net_direction(group.net_delta()) {
+                name = format!("{} {}", name, marker);
+            }
+            if let Some(note) = subtotals.legs_shown() {
+                name += &note;
+            }
+            let style = if app.is_dust(group) {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().add_modifier(Modifier::BOLD)
+            };
+            rows.push(
+                Row::new(vec![
+                    Cell::from(name),
+                    pnl_cell(app, subtotals.profit, !app.is_dust(group)),
+                    Cell::from(money(subtotals.net_liq)),
+                ])
+                .style(style),
+            );
+            for line in app.group_lines(underlying, group) {
+                match line {
+                    Selection::Record(_, key) => {
+                        let rec = &group.records[&key];
+                        rows.push(Row::new(vec![
+                            Cell::from(format!(" {}", rec.name(underlying))),
+                            pnl_cell(app, profit(rec), true),
+                            Cell::from(money(rec.to_net(rec.current))),
+                        ]));
+                    }
+                    Selection::Expiration(_, date) => {
+                        let legs = expiring(&in_tab, date);
+                        rows.push(
+                            Row::new(vec![
+                                Cell::from(format!(" {}", date.format("%d %b %y"))),
+                                pnl_cell(app, legs.iter().copied().map(profit).sum(), true),
+                                Cell::from(money(
+                                    legs.iter().map(|rec| rec.to_net(rec.current)).sum(),
+                                )),
+                            ])
+                            .style(Style::default().add_modifier(Modifier::BOLD)),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let expired: Vec<&PriceRecord> = app
+            .expired
+            .records
+            .values()
+            .filter(|rec| app.in_tab(rec) && app.filter_matches(&rec.symbol))
+            .collect();
+        if !expired.is_empty() {
+            let expired_style = Style::default().fg(Color::DarkGray);
+            rows.push(
+                Row::new(vec![
+                    "EXPIRED".to_owned(),
+                    money(expired.iter().copied().map(profit).sum()),
+                    "".to_owned(),
+                ])
+                .style(expired_style),
+            );
+            if app.expired.open {
+                for rec in expired {
+                    rows.push(
+                        Row::new(vec![
+                            format!(" {}", rec.symbol.0),
+                            money(profit(rec)),
+                            "".to_owned(),
+                        ])
+                        .style(expired_style),
+                    );
+                }
+            }
+        }
+        // Just the mid, where a position has its P/L.
+        if !app.watchlist.is_empty() {
+            rows.push(
+                Row::new(vec!["WATCHLIST", "MID", ""]).style(Style::default().fg(Color::Red)),
+            );
+            for item in &app.watchlist {
+                let style = if app.subscriptions.is_dropped(&item.quote.symbol) {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+                rows.push(
+                    Row::new(vec![
+                        format!(" {}", item.symbol.0),
+                        item.quote
+                            .price
+                            .map_or("-".to_owned(), |price| price.round_dp(2).to_string()),
+                        "".to_owned(),
+                    ])
+                    .style(style),
+                );
+            }
+        }
+        // P/L is the compact name of PROFIT.
+        let header = Row::new(vec![
+            sort_header(app, "SYMBOL", "SYMBOL"),
+            sort_header(app, "P/L", "PROFIT"),
+            sort_header(app, "NET LIQ", "NET LIQ"),
+        ])
+        .style(Style::default().fg(Color::Red).bg(Color::Blue));
+        let widths = [
+            Constraint::Min(12),
+            Constraint::Length(11),
+            Constraint::Length(12),
+        ];
+        let inner = Block::default().borders(Borders::ALL).inner(rects[0]);
+        app.table_drawn(inner);
+        f.render_widget(table_block(app), rects[0]);
+        let (rows, mut state) = app.visible_rows(rows);
+        let t = Table::new(rows)
+            .header(header)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">")
+            .widths(&widths);
+        f.render_stateful_widget(t, inner, &mut state);
+    }
+
+    f.render_widget(compact_summary(app), rects[1]);
+    bottom_line(f, app, rects[2]);
+    f.render_widget(connection_bar(app), rects[3]);
+    popups(f, app);
+}
+
+/// A column title, marked with the sort direction and highlighted when the
+/// table is sorted by `column`.
+fn sort_header(app: &App, title: &str, column: &str) -> Cell<'static> {
+    let style = Style::default().fg(Color::Red);
+    if column != app.sort_key.column() {
+        return Cell::from(title.to_owned()).style(style);
+    }
+    let arrow = if app.sort_descending {
+        "\u{25bc}"
+    } else {
+        "\u{25b2}"
+    };
+    Cell::from(format!("{} {}", title, arrow)).style(style.add_modifier(Modifier::REVERSED))
+}
+
+/// A P/L amount, colored by gain or loss unless `colored` is off.
+fn pnl_cell(app: &App, value: Decimal, colored: bool) -> Cell<'static> {
+    let cell = Cell::from(money(value));
+    match app.theme.pnl_color(value).filter(|_| colored) {
+        Some(color) => cell.style(Style::default().fg(color)),
+        None => cell,
+    }
+}
+
+/// The legs among `legs` expiring on `date`.
+fn expiring<'a>(legs: &[&'a PriceRecord], date: NaiveDate) -> Vec<&'a PriceRecord> {
+    legs.iter()
+        .copied()
+        .filter(|rec| {
+            rec.option
+                .as_ref()
+                .map_or(false, |option| option.expiration == date)
+        })
+        .collect()
+}
+
+/// The line over a group's legs expiring on `date`, with their P/L, theta,
+/// delta and net liq added up. Like the group header's, the sums cover
+/// every leg in the tab.
+fn expiration_row<'a>(
+    app: &App,
+    in_tab: &[(&RecordKey, &PriceRecord)],
+    date: NaiveDate,
+    dust: bool,
+) -> Row<'a> {
+    let legs: Vec<&PriceRecord> = in_tab.iter().map(|&(_, rec)| rec).collect();
+    let legs = expiring(&legs, date);
+    let profit: Decimal = legs
+        .iter()
+        .map(|rec| rec.to_net(rec.current - rec.open))
+        .sum();
+    let delta: Decimal = legs
+        .iter()
+        .map(|rec| rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap_or_default()))
+        .sum();
+    let mut cells = vec![
+        Cell::from(""),
+        Cell::from(format!(" {}", date.format("%d %b %y"))),
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(""),
+        pnl_cell(app, profit, !dust),
+        Cell::from(money(legs.iter().map(|rec| rec.net_theta()).sum())),
+        Cell::from(money(delta)),
+        Cell::from(money(legs.iter().map(|rec| rec.to_net(rec.current)).sum())),
+    ];
+    if app.credit_column {
+        cells.insert(CREDIT_COLUMN, Cell::from(""));
+    }
+    let style = if dust {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD)
+    };
+    Row::new(cells).style(style)
+}
+
+/// A group header's subtotals. They cover every leg of the group in the
+/// tab, whether or not the expiration filter lists it, so they only add up
+/// to the rows shown when all of them are.
+struct Subtotals {
+    profit: Decimal,
+    net_liq: Decimal,
+    theta: ThetaSplit,
+    credit: Decimal,
+    /// Blank until a leg has a close to count from.
+    day: Option<Decimal>,
+    cost_basis: Decimal,
+    delta: Decimal,
+    /// How many of `legs` the filter lists.
+    shown: usize,
+    legs: usize,
+}
+
+impl Subtotals {
+    fn new(app: &App, in_tab: &[(&RecordKey, &PriceRecord)], week_end: NaiveDate) -> Self {
+        let mut subtotals = Self {
+            profit: Decimal::zero(),
+            net_liq: Decimal::zero(),
+            theta: ThetaSplit::new(week_end),
+            credit: Decimal::zero(),
+            day: None,
+            cost_basis: Decimal::zero(),
+            delta: Decimal::zero(),
+            shown: 0,
+            legs: in_tab.len(),
+        };
+        for &(_, rec) in in_tab {
+            subtotals.profit += rec.to_net(rec.current - rec.open);
+            subtotals.net_liq += rec.to_net(rec.current);
+            subtotals.theta.add(rec);
+            subtotals.credit += rec.open_credit().unwrap_or_default();
+            if let Some(day) = rec.day_profit() {
+                subtotals.day = Some(subtotals.day.unwrap_or_default() + day);
+            }
+            subtotals.cost_basis += rec.cost_basis();
+            subtotals.delta += rec.to_net(Decimal::from_f64(rec.greeks.delta).unwrap_or_default());
+            if app.is_listed(rec) {
+                subtotals.shown += 1;
+            }
+        }
+        subtotals
+    }
+
+    /// Explains the subtotals when the expiration filter lists only some
+    /// of the legs they cover.
+    fn legs_shown(&self) -> Option<String> {
+        (self.shown < self.legs).then(|| format!("  {} of {} legs shown", self.shown, self.legs))
+    }
+}
 
-    f.render_stateful_widget(t, rects[0], &mut app.state);
+/// `part` as a percentage of `whole`, unless `whole` is zero.
+fn percent_of(part: Decimal, whole: Decimal) -> Option<Decimal> {
+    (!whole.is_zero()).then(|| part * Decimal::from(100) / whole)
+}
+
+/// A signed percentage to one decimal, or blank without one.
+fn percent_text(percent: Option<Decimal>) -> String {
+    percent.map_or("".to_owned(), |percent| {
+        format!("{:+}%", percent.round_dp(1))
+    })
+}
+
+/// Days to expiration like `12d`, or blank for shares.
+fn dte_text(days: Option<i64>) -> String {
+    days.map_or("".to_owned(), |days| format!("{}d", days))
+}
+
+/// Implied volatility as a percentage, or blank without one.
+fn iv_text(volatility: Option<f64>) -> String {
+    volatility.map_or("".to_owned(), |iv| format!("{:.1}%", iv * 100.0))
+}
+
+/// The implied volatility of a group's option legs, averaged by their
+/// number of contracts.
+fn average_iv<'a>(legs: impl Iterator<Item = &'a PriceRecord>) -> Option<f64> {
+    let (weighted, contracts) = legs
+        .filter(|rec| rec.option.is_some())
+        .filter_map(|rec| Some((rec.greeks.volatility?, rec.amount.abs().to_f64()?)))
+        .fold((0.0, 0.0), |(weighted, contracts), (iv, amount)| {
+            (weighted + iv * amount, contracts + amount)
+        });
+    (contracts > 0.0).then(|| weighted / contracts)
+}
+
+/// Dollar amounts shortened to fit a strip, like 52.3k.
+fn short_money(value: Decimal) -> String {
+    let value = value.to_f64().unwrap_or_default();
+    let magnitude = value.abs();
+    if magnitude >= 1e6 {
+        format!("{:.1}M", value / 1e6)
+    } else if magnitude >= 1e3 {
+        format!("{:.1}k", value / 1e3)
+    } else {
+        format!("{:.0}", value)
+    }
+}
+
+/// Each account's net liq and P/L side by side, then the total, when
+/// more than one account is loaded. Worked out like the reconciliation in
+/// the detail pane. Accounts without a balance yet show as "…". These are
+/// the tabs too, with the one showing highlighted; the total is the tab
+/// for all accounts.
+fn account_strip(app: &App) -> Option<Paragraph> {
+    let mut accounts: BTreeSet<&str> = app.balances.keys().map(String::as_str).collect();
+    for rec in app.groups.values().flat_map(|group| group.records.values()) {
+        accounts.insert(&rec.account);
+    }
+    if accounts.len() < 2 {
+        return None;
+    }
+    let theme = &app.theme;
+    let label_style = |active: bool| {
+        let style = Style::default().fg(Color::Red);
+        if active {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style
+        }
+    };
+    let segment = |label: &str, active: bool, net_liq: Decimal, profit: Decimal| {
+        let (arrow, color) = if profit > Decimal::zero() {
+            ("\u{25b2}", Some(theme.profit))
+        } else if profit < Decimal::zero() {
+            ("\u{25bc}", Some(theme.loss))
+        } else {
+            ("", None)
+        };
+        let mut style = Style::default();
+        if let Some(color) = color {
+            style = style.fg(color);
+        }
+        vec![
+            Span::styled(label.to_owned(), label_style(active)),
+            Span::raw(format!(" {} ", short_money(net_liq))),
+            Span::styled(format!("{}{}", arrow, short_money(profit.abs())), style),
+        ]
+    };
+    let mut spans = vec![];
+    let (mut total_net_liq, mut total_profit) = (Decimal::zero(), Decimal::zero());
+    for account in accounts {
+        let records = app
+            .groups
+            .values()
+            .flat_map(|group| group.records.values())
+            .filter(|rec| rec.account == account);
+        let (mut net_liq, mut profit) = (Decimal::zero(), Decimal::zero());
+        for rec in records {
+            net_liq += rec.to_net(rec.current);
+            profit += rec.to_net(rec.current - rec.open);
+        }
+        total_net_liq += net_liq;
+        total_profit += profit;
+        let active = app.tab.as_deref() == Some(account);
+        match app.balances.get(account) {
+            Some(balance) => {
+                net_liq += balance.cash;
+                total_net_liq += balance.cash;
+                spans.extend(segment(account, active, net_liq, profit));
+            }
+            None => {
+                spans.push(Span::styled(account.to_owned(), label_style(active)));
+                spans.push(Span::raw(" \u{2026}"));
+            }
+        }
+        spans.push(Span::raw(" | "));
+    }
+    spans.extend(segment(
+        "Total",
+        app.tab.is_none(),
+        total_net_liq,
+        total_profit,
+    ));
+    Some(Paragraph::new(Spans::from(spans)))
+}
+
+/// Portfolio totals on one line, for the compact layout.
+fn compact_summary(app: &App) -> Paragraph {
+    let records = || app.tab_records();
+    let profit: Decimal = records()
+        .map(|rec| rec.to_net(rec.current - rec.open))
+        .sum();
+    let cash: Decimal = app.tab_balances().map(|(_, balance)| balance.cash).sum();
+    let net_liq: Decimal = records()
+        .map(|rec| rec.to_net(rec.current))
+        .sum::<Decimal>()
+        + cash;
+    let theta: Decimal = records().map(PriceRecord::net_theta).sum();
+    let label_style = Style::default().fg(Color::Red);
+    let mut spans = vec![];
+    if let Some(account) = &app.tab {
+        spans.push(Span::styled(format!("{}  ", account), label_style));
+    }
+    spans.extend([
+        Span::styled("P/L ", label_style),
+        Span::styled(
+            money(profit),
+            app.theme
+                .pnl_color(profit)
+                .map_or(Style::default(), |color| Style::default().fg(color)),
+        ),
+        Span::styled("  NET LIQ ", label_style),
+        Span::raw(money(net_liq)),
+        Span::styled("  THETA ", label_style),
+        Span::raw(money(theta)),
+    ]);
+    Paragraph::new(Spans::from(spans))
+}
+
+/// The input being typed, or else the status line.
+fn bottom_line<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    match &app.input {
+        Some(input) => {
+            let line = format!("{}: {}", input.prompt, input.buffer);
+            f.set_cursor(area.x + line.len() as u16, area.y);
+            let mut spans = vec![Span::raw(line)];
+            if input.busy {
+                spans.push(Span::styled(
+                    "  looking it up…",
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            if let Some(error) = &input.error {
+                spans.push(Span::styled(
+                    format!("  {}", error),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            f.render_widget(Paragraph::new(Spans::from(spans)), area);
+        }
+        None => f.render_widget(status_line(app), area),
+    }
+}
+
+/// Whichever popups are open, over the rest of the screen.
+fn popups<B: Backend>(f: &mut Frame<B>, app: &App) {
+    if app.risk_open {
+        let area = centered_rect(80, 17, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(risk_popup(app), area);
+    }
+    if let Some(what_if) = &app.what_if {
+        let legs = app
+            .groups
+            .get(&what_if.underlying)
+            .map_or(0, |group| group.records.len());
+        let area = centered_rect(60, legs as u16 + 6, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(what_if_popup(app, what_if), area);
+    }
+    if app.stream_open {
+        let stats = &app.stream_stats;
+        let shown = app.requests.recent.len().min(REQUESTS_SHOWN) as u16;
+        let fx_lines = u16::from(!app.fx.rates.is_empty());
+        let area = centered_rect(
+            100,
+            stats.seen.len() as u16 * 2 + shown + fx_lines + 10,
+            f.size(),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(stream_popup(stats, &app.requests, &app.fx), area);
+    }
+    if let Some(cursor) = app.ladder {
+        let expirations = ladder::summarize(&app.ladder_legs());
+        let area = centered_rect(70, expirations.len() as u16 + 4, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(ladder_popup(&expirations, cursor), area);
+    }
+    if let Some(chain) = &app.chain {
+        let area = f.size();
+        f.render_widget(Clear, area);
+        f.render_widget(chain_view(app, chain), area);
+    }
+    if app.orders_open {
+        let area = centered_rect(100, orders_height(&app.orders), f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(orders_popup(&app.orders), area);
+    }
+    let cancelling = app
+        .confirm_cancel
+        .as_deref()
+        .and_then(|id| app.orders.working_order(id));
+    if let Some(order) = cancelling {
+        let area = centered_rect(70, 5, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(vec![
+                Spans::from(orders::describe(order)),
+                Spans::from(""),
+                Spans::from(Span::styled(
+                    "Cancel this order? y/n",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )),
+            ])
+            .block(
+                Block::default()
+                    .title(format!(" CANCEL ORDER {} ", order.account_number.0))
+                    .borders(Borders::ALL),
+            ),
+            area,
+        );
+    }
+    if let Some(ticket) = &app.ticket {
+        let rows = (ticket.legs.len() + ticket.notes.len()) as u16;
+        let area = centered_rect(80, 11 + rows, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(ticket_popup(app, ticket), area);
+    }
+    if app.notices_open {
+        let area = centered_rect(100, 22, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(notices_popup(app), area);
+    }
+    toasts(f, app);
+    // Last, so it covers whatever it was opened over.
+    if app.help_open {
+        let area = centered_rect(60, keys::BINDINGS.len() as u16 + 2, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(help_popup(), area);
+    }
+}
+
+fn table_block(app: &App) -> Block<'static> {
+    let mut title = vec![];
+    // First, so a sandbox session can't pass for the live account.
+    if app.sandbox {
+        title.push("*** SANDBOX ***".to_owned());
+    }
+    if app.read_only {
+        title.push("READ-ONLY".to_owned());
+    }
+    if let Some(held) = &app.paused {
+        title.push(format!(
+            "*** PAUSED, {} updates held (p to resume) ***",
+            held.len()
+        ));
+    }
+    if let Some(profile) = &app.profile {
+        title.push(profile.clone());
+    }
+    if let Some(date) = app.expiration_filter {
+        title.push(format!(
+            "expiring {} (Esc to show all)",
+            date.format("%b %d")
+        ));
+    }
+    if let Some(position) = app.scroll_position() {
+        title.push(position);
+    }
+    if !app.symbol_filter.is_empty() {
+        title.push(format!(
+            "matching \"{}\" (Esc to show all)",
+            app.symbol_filter
+        ));
+    }
+    let mut block = Block::default().borders(Borders::ALL);
+    if app.paused.is_some() {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    if title.is_empty() {
+        block
+    } else {
+        block.title(format!(" {} ", title.join(" - ")))
+    }
+}
+
+/// A rectangle of the given size centered in `area`, clipped to fit.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn risk_popup(app: &App) -> Paragraph {
+    let label_style = Style::default().fg(Color::Red);
+    let summary = risk::summarize(&app.risk_positions());
+    let mut lines = vec![];
+    for measure in &summary.measures {
+        let top: Vec<_> = measure
+            .top
+            .iter()
+            .map(|(symbol, value)| format!("{} {:.2}", symbol, value))
+            .collect();
+        lines.push(Spans::from(vec![
+            Span::styled(format!("{:<22}", measure.measure.label()), label_style),
+            Span::raw(format!("{:>14.2}", measure.total)),
+        ]));
+        lines.push(Spans::from(Span::raw(format!("  top: {}", top.join(", ")))));
+    }
+    lines.push(Spans::from(vec![
+        Span::styled(format!("{:<22}", "NOTIONAL LONG"), label_style),
+        Span::raw(format!("{:>14.2}", summary.notional_long)),
+    ]));
+    lines.push(Spans::from(vec![
+        Span::styled(format!("{:<22}", "NOTIONAL SHORT"), label_style),
+        Span::raw(format!("{:>14.2}", summary.notional_short)),
+    ]));
+    if summary.unpriced > 0 {
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{} positions without an underlying price left out of $ figures",
+                summary.unpriced
+            ),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(" RISK (v or Esc to close) ")
+            .borders(Borders::ALL),
+    )
+}
+
+fn stream_popup<'a>(
+    stats: &'a streamstats::StreamStats,
+    requests: &'a requests::RequestStats,
+    fx: &'a fx::Fx,
+) -> Paragraph<'a> {
+    let label_style = Style::default().fg(Color::Red);
+    let mut lines = vec![Spans::from(Span::styled(
+        format!(
+            "{:<28}{:>8}{:>10}  {}",
+            "MESSAGE", "COUNT", "LAST", "HANDLED"
+        ),
+        label_style,
+    ))];
+    for (name, seen) in &stats.seen {
+        let style = if seen.handled {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:<28}{:>8}{:>10}  {}",
+                name,
+                seen.count,
+                seen.last_at.format("%H:%M:%S").to_string(),
+                if seen.handled { "yes" } else { "ignored" }
+            ),
+            style,
+        )));
+        lines.push(Spans::from(Span::styled(
+            format!("  {}", seen.sample),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines.push(Spans::from(""));
+    let mut feed: Vec<_> = stats
+        .feed
+        .iter()
+        .map(|(kind, count)| format!("{} {}/s", kind, stats.rate(*count).round()))
+        .collect();
+    feed.push(format!("draws {:.1}/s", stats.rate(stats.draws)));
+    feed.push(format!("greeks held back {}", stats.greeks_held));
+    lines.push(Spans::from(vec![
+        Span::styled("quote feed  ", label_style),
+        Span::raw(feed.join(", ")),
+    ]));
+    lines.push(Spans::from(format!(
+        "{} ignored, {} unparsed",
+        stats.ignored(),
+        stats.unparsed
+    )));
+    if let Some(error) = &stats.last_error {
+        lines.push(Spans::from(Span::styled(
+            format!("last error: {}", error),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    lines.push(Spans::from(""));
+    let mut summary = vec![
+        format!("{:.1}/min", requests.per_minute()),
+        format!("{} failed", requests.errors()),
+        format!("{} rate limited", requests.rate_limited),
+    ];
+    if let Some(latency) = requests.mean_latency() {
+        summary.push(format!("{}ms average", latency.as_millis()));
+    }
+    if let Some(backoff) = requests.backoff() {
+        summary.push(format!("holding requests for {}s", backoff.as_secs() + 1));
+    }
+    lines.push(Spans::from(vec![
+        Span::styled("REST requests  ", label_style),
+        Span::raw(summary.join(", ")),
+    ]));
+    for request in requests.recent.iter().rev().take(REQUESTS_SHOWN) {
+        let (outcome, style) = match &request.error {
+            Some(error) => (error.as_str(), Style::default().fg(Color::Yellow)),
+            None => ("ok", Style::default()),
+        };
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "  {:<14}{:>8}ms  {}",
+                request.name,
+                request.latency.as_millis(),
+                outcome
+            ),
+            style,
+        )));
+    }
+    if !fx.rates.is_empty() {
+        let rates: Vec<_> = fx
+            .rates
+            .iter()
+            .map(|(currency, rate)| format!("{} {}", currency, rate.normalize()))
+            .collect();
+        lines.push(Spans::from(vec![
+            Span::styled("FX rates  ", label_style),
+            Span::raw(format!(
+                "{} {} per unit, from the config read at {}",
+                rates.join(", "),
+                fx::HOME,
+                fx.loaded_at.format("%H:%M:%S")
+            )),
+        ]));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(format!(
+                " ACCOUNT STREAM (D or Esc to close)  tastytrade-cli {} ",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .borders(Borders::ALL),
+    )
+}
+
+/// Order notifications still showing, stacked in the top right corner.
+fn toasts<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let toasts: Vec<_> = app.notices.toasts().collect();
+    if toasts.is_empty() {
+        return;
+    }
+    let hidden = toasts.len().saturating_sub(notify::TOASTS_SHOWN);
+    let mut lines: Vec<Spans> = toasts[hidden..]
+        .iter()
+        .map(|notice| {
+            Spans::from(Span::styled(
+                notice.text.clone(),
+                Style::default().fg(notice_color(notice.kind)),
+            ))
+        })
+        .collect();
+    if hidden > 0 {
+        lines.insert(
+            0,
+            Spans::from(Span::styled(
+                format!("{} more (N)", hidden),
+                Style::default().fg(Color::DarkGray),
+            )),
+        );
+    }
+    let size = f.size();
+    let width = 60.min(size.width);
+    let height = (lines.len() as u16 + 2).min(size.height);
+    let area = Rect::new(size.x + size.width - width, size.y, width, height);
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title(" ORDERS ").borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn notice_color(kind: notify::Kind) -> Color {
+    match kind {
+        notify::Kind::Filled => Color::Green,
+        notify::Kind::PartlyFilled => Color::Cyan,
+        notify::Kind::Rejected => Color::Red,
+        notify::Kind::Cancelled => Color::Yellow,
+        notify::Kind::Info => Color::White,
+    }
+}
+
+/// The lines of an order in the orders pane: one for the order, then one
+/// for each leg when there are several.
+fn order_lines(order: &LiveOrderRecord, style: Style) -> Vec<Spans<'static>> {
+    let side = match order.legs.as_slice() {
+        [leg] => format!("{:?}", leg.action),
+        _ => format!("{} legs", order.legs.len()),
+    };
+    let symbol = match order.legs.as_slice() {
+        [leg] => leg.symbol.0.clone(),
+        _ => order.underlying_symbol.0.clone(),
+    };
+    let mut lines = vec![Spans::from(Span::styled(
+        format!(
+            "{:<24}{:<14}{:>6}  {:<8}{:>10} {:<7}{:<12}{}",
+            symbol,
+            side,
+            order.size,
+            format!("{:?}", order.order_type),
+            order.price.round_dp(2),
+            format!("{:?}", order.price_effect),
+            format!("{:?}", order.status),
+            format!("{:?}", order.time_in_force)
+        ),
+        style,
+    ))];
+    if order.legs.len() > 1 {
+        for leg in &order.legs {
+            lines.push(Spans::from(Span::styled(
+                format!(
+                    "  {:<22}{:<14}{:>6}",
+                    leg.symbol.0,
+                    format!("{:?}", leg.action),
+                    leg.quantity
+                ),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+    lines
+}
+
+fn orders_height(orders: &orders::Orders) -> u16 {
+    let lines: usize = orders
+        .working()
+        .chain(orders.recent())
+        .map(|order| {
+            1 + if order.legs.len() > 1 {
+                order.legs.len()
+            } else {
+                0
+            }
+        })
+        .sum();
+    (lines as u16 + 6).min(30)
+}
+
+/// Working orders, then the ones that just ended, for `RECENT_TIME`.
+fn orders_popup(orders: &orders::Orders) -> Paragraph {
+    let label_style = Style::default().fg(Color::Red);
+    let mut lines = vec![Spans::from(Span::styled(
+        format!(
+            "{:<24}{:<14}{:>6}  {:<8}{:>10} {:<7}{:<12}{}",
+            "SYMBOL", "SIDE", "QTY", "TYPE", "PRICE", "", "STATUS", "TIF"
+        ),
+        label_style,
+    ))];
+    let mut working = 0;
+    for (i, order) in orders.working().enumerate() {
+        let style = if i == orders.selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.extend(order_lines(order, style));
+        working += 1;
+    }
+    if working == 0 {
+        lines.push(Spans::from(Span::styled(
+            "No working orders",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    let mut recent = orders.recent().peekable();
+    if recent.peek().is_some() {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled("RECENT", label_style)));
+        for order in recent {
+            lines.extend(order_lines(order, Style::default().fg(Color::DarkGray)));
+        }
+    }
+    // Keeps the selected order in view when there are more than fit.
+    let selected_line = 1 + orders
+        .working()
+        .take(orders.selected)
+        .map(|order| {
+            1 + if order.legs.len() > 1 {
+                order.legs.len()
+            } else {
+                0
+            }
+        })
+        .sum::<usize>();
+    let visible = usize::from(orders_height(orders)).saturating_sub(2);
+    let scroll = (selected_line + 1).saturating_sub(visible);
+    Paragraph::new(lines).scroll((scroll as u16, 0)).block(
+        Block::default()
+            .title(" ORDERS (Up/Down: select, b or Esc: close) ")
+            .borders(Borders::ALL),
+    )
+}
+
+/// The dry run of an order and what can be done about it, for any dialog
+/// confirming one. Warnings leave sending open; a rejection closes it.
+fn confirmation_lines(confirmation: &confirm::Confirmation) -> Vec<Spans<'static>> {
+    let label_style = Style::default().fg(Color::Red);
+    let prompt = |text: String, color: Color| {
+        Spans::from(Span::styled(
+            text,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ))
+    };
+    let mut lines = vec![Spans::from(confirmation.summary.clone())];
+    match &confirmation.dry_run {
+        confirm::DryRun::Pending => {
+            lines.push(Spans::from("Checking the order with tastytrade…"));
+        }
+        confirm::DryRun::Passed {
+            buying_power_effect,
+            fees,
+            warnings,
+        } => {
+            let mut effect = vec![
+                Span::styled("BUYING POWER ", label_style),
+                Span::raw(money(*buying_power_effect)),
+            ];
+            if let Some(fees) = fees {
+                effect.push(Span::styled("  FEES ", label_style));
+                effect.push(Span::raw(money(*fees)));
+            }
+            lines.push(Spans::from(effect));
+            for warning in warnings {
+                lines.push(Spans::from(Span::styled(
+                    format!("warning: {}", warning),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            lines.push(prompt(
+                "Send this order? y to send, any other key to go back".to_owned(),
+                Color::Yellow,
+            ));
+        }
+        confirm::DryRun::Rejected(error) => {
+            lines.push(Spans::from(Span::styled(
+                format!("rejected: {}", error),
+                Style::default().fg(Color::Red),
+            )));
+            lines.push(prompt(
+                "tastytrade won't take this order; any key to go back".to_owned(),
+                Color::Red,
+            ));
+        }
+        confirm::DryRun::Failed(error) => {
+            lines.push(Spans::from(Span::styled(
+                format!("dry run failed: {}", error),
+                Style::default().fg(Color::Red),
+            )));
+            lines.push(prompt(
+                format!(
+                    "Send it unchecked? {} to send, any other key to go back",
+                    confirm::OVERRIDE_KEY
+                ),
+                Color::Yellow,
+            ));
+        }
+    }
+    lines
+}
+
+fn ticket_popup<'a>(app: &App, ticket: &'a ticket::Ticket) -> Paragraph<'a> {
+    let label_style = Style::default().fg(Color::Red);
+    let field = |field: ticket::Field, value: Decimal| {
+        let text = match &ticket.typed {
+            Some(typed) if ticket.field == field => format!(" {}_ ", typed),
+            _ => format!(" {} ", value.normalize()),
+        };
+        let style = if ticket.field == field && matches!(ticket.stage, ticket::Stage::Editing) {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        Span::styled(text, style)
+    };
+    let mut lines: Vec<Spans> = ticket
+        .legs
+        .iter()
+        .map(|leg| {
+            let quote = app
+                .groups
+                .values()
+                .flat_map(|group| group.records.values())
+                .find(|rec| rec.account == ticket.account && rec.symbol == leg.symbol)
+                .and_then(|rec| rec.quote.as_ref())
+                .map_or(String::new(), |q| {
+                    format!("  bid {} / ask {}", q.bid.round_dp(2), q.ask.round_dp(2))
+                });
+            Spans::from(vec![
+                Span::styled(format!("{:?} ", leg.action), label_style),
+                Span::raw(leg.name.clone()),
+                Span::styled(quote, Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+    lines.push(Spans::from(vec![
+        Span::styled("QTY ", label_style),
+        field(ticket::Field::Quantity, ticket.quantity),
+        Span::raw(match ticket.held {
+            Some(held) => format!(" of {}  ", held.normalize()),
+            None => "  ".to_owned(),
+        }),
+        Span::styled("LIMIT ", label_style),
+        field(ticket::Field::Price, ticket.price),
+        Span::raw(format!(" {:?}, day", ticket.price_effect())),
+    ]));
+    for note in &ticket.notes {
+        lines.push(Spans::from(Span::styled(
+            note.clone(),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    lines.push(Spans::from(""));
+    match &ticket.stage {
+        ticket::Stage::Editing => lines.push(Spans::from(Span::styled(
+            "Up/Down/Left/Right adjust, Tab switches, digits type; Enter checks, Esc cancels",
+            Style::default().fg(Color::DarkGray),
+        ))),
+        ticket::Stage::Confirming { confirmation, .. } => {
+            lines.extend(confirmation_lines(confirmation))
+        }
+        ticket::Stage::Sending => lines.push(Spans::from("Sending the order…")),
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(format!(
+                " {} {} ({}) ",
+                ticket.title,
+                ticket.name.trim(),
+                ticket.account
+            ))
+            .borders(Borders::ALL),
+    )
+}
+
+/// Every order notification kept, newest first.
+fn notices_popup(app: &App) -> Paragraph {
+    let lines: Vec<Spans> = if app.notices.history.is_empty() {
+        vec![Spans::from("No order notifications yet")]
+    } else {
+        app.notices
+            .history
+            .iter()
+            .rev()
+            .map(|notice| {
+                Spans::from(vec![
+                    Span::styled(
+                        format!("{} ", notice.at.format("%H:%M:%S")),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        notice.text.as_str(),
+                        Style::default().fg(notice_color(notice.kind)),
+                    ),
+                ])
+            })
+            .collect()
+    };
+    Paragraph::new(lines)
+        .scroll((app.notice_scroll as u16, 0))
+        .block(
+            Block::default()
+                .title(" NOTIFICATIONS (Up/Down: scroll, N or Esc: close) ")
+                .borders(Borders::ALL),
+        )
+}
+
+fn help_popup() -> Paragraph<'static> {
+    let key_style = Style::default().fg(Color::Red);
+    let lines: Vec<Spans> = keys::BINDINGS
+        .iter()
+        .map(|binding| {
+            Spans::from(vec![
+                Span::styled(format!("{:<12}", binding.label), key_style),
+                Span::raw(binding.description),
+            ])
+        })
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(" KEYS (? or Esc: close) ")
+            .borders(Borders::ALL),
+    )
+}
+
+fn ladder_popup(expirations: &[ladder::Expiration], cursor: usize) -> Paragraph<'static> {
+    let label_style = Style::default().fg(Color::Red);
+    let mut lines = vec![Spans::from(Span::styled(
+        format!(
+            "{:<12}{:>6}{:>14}{:>14}{:>18}",
+            "EXPIRES", "LEGS", "THETA", "DELTA", "WORST CASE"
+        ),
+        label_style,
+    ))];
+    for (i, expiration) in expirations.iter().enumerate() {
+        let worst = expiration.worst_loss.map_or("undefined".to_owned(), money);
+        let style = if i == cursor {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:<12}{:>6}{:>14}{:>14}{:>18}",
+                expiration.date.format("%a %b %d").to_string(),
+                expiration.legs,
+                money(expiration.theta),
+                money(expiration.delta),
+                worst
+            ),
+            style,
+        )));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(" EXPIRATIONS (Enter: filter the table, Esc: close) ")
+            .borders(Borders::ALL),
+    )
+}
+
+/// The chain view, over the whole screen: the expirations across the top,
+/// then the strikes in view with calls on the left and puts on the right.
+fn chain_view<'a>(app: &'a App, chain: &'a chain::Chain) -> Paragraph<'a> {
+    let label_style = Style::default().fg(Color::Red);
+    let mut tabs = vec![];
+    for (i, expiration) in chain.expirations.iter().enumerate() {
+        let style = if i == chain.expiration {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        tabs.push(Span::styled(
+            format!(" {} ", expiration.format("%b %d %Y")),
+            style,
+        ));
+        tabs.push(Span::raw(" "));
+    }
+    let side_header = |side: &str| format!("{:>10}{:>10}{:>8}", side, "ASK", "DELTA");
+    let mut lines = vec![
+        Spans::from(tabs),
+        Spans::from(""),
+        Spans::from(vec![
+            Span::styled(side_header("CALL BID"), label_style),
+            Span::styled(format!("{:^14}", "STRIKE"), label_style),
+            Span::styled(side_header("PUT BID"), label_style),
+        ]),
+    ];
+    let price = app.underlying_price(&chain.underlying);
+    // The strike nearest the money is picked out, to find it by.
+    let at_money = price.and_then(|price| {
+        chain
+            .strikes
+            .iter()
+            .min_by_key(|strike| (**strike - price).abs())
+            .copied()
+    });
+    for strike in chain.visible_strikes() {
+        let side = |kind: OptionKind| {
+            let contract = chain.contract(strike, kind);
+            let quote = contract.and_then(|contract| chain.quote(contract));
+            let text = match contract {
+                Some(_) => format!(
+                    "{:>10}{:>10}{:>8}",
+                    quote.and_then(|q| q.bid).map_or("-".to_owned(), money),
+                    quote.and_then(|q| q.ask).map_or("-".to_owned(), money),
+                    quote
+                        .and_then(|q| q.delta)
+                        .map_or("-".to_owned(), |delta| format!("{:.2}", delta)),
+                ),
+                None => format!("{:28}", ""),
+            };
+            let mut style = match contract {
+                Some(contract) if app.subscriptions.is_dropped(&contract.stream_symbol) => {
+                    Style::default().fg(Color::DarkGray)
+                }
+                _ => Style::default(),
+            };
+            if (strike, kind) == (chain.strike, chain.kind) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Span::styled(text, style)
+        };
+        let value = chain.strikes[strike];
+        let strike_style = if at_money == Some(value) {
+            label_style.add_modifier(Modifier::BOLD)
+        } else {
+            label_style
+        };
+        lines.push(Spans::from(vec![
+            side(OptionKind::Call),
+            Span::styled(
+                format!("{:^14}", value.normalize().to_string()),
+                strike_style,
+            ),
+            side(OptionKind::Put),
+        ]));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        match &app.roll {
+            Some(_) => "Up/Down: strike, Left/Right: expiration, Tab: calls/puts, Enter: roll here, Esc: cancel",
+            None => "Up/Down: strike, Left/Right: expiration, Tab: calls/puts, Enter or b: buy, s: sell, w: watch, Esc: close",
+        },
+        Style::default().fg(Color::DarkGray),
+    )));
+    let title = match &app.roll {
+        Some(roll) => format!(" ROLL {}: pick the contract to roll to ", roll.name.trim()),
+        None => format!(
+            " {} OPTION CHAIN{} ",
+            chain.underlying.0,
+            price.map_or(String::new(), |price| format!(" @ {}", money(price)))
+        ),
+    };
+    Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL))
+}
+
+fn what_if_popup<'a>(app: &'a App, what_if: &'a WhatIf) -> Paragraph<'a> {
+    let label_style = Style::default().fg(Color::Red);
+    let price_move = (what_if.to - what_if.from).to_f64().unwrap_or_default();
+    let percent = if what_if.from.is_zero() {
+        Decimal::zero()
+    } else {
+        (what_if.to - what_if.from) * Decimal::from(100) / what_if.from
+    };
+    let mut lines = vec![
+        Spans::from(vec![
+            Span::styled(format!("{} ", what_if.underlying.0), label_style),
+            Span::raw(format!(
+                "{} -> {} ({:+}%)",
+                what_if.from.round_dp(2),
+                what_if.to.round_dp(2),
+                percent.round_dp(2)
+            )),
+        ]),
+        Spans::from(Span::styled(
+            format!("{:<26}{:>14}{:>14}", "", "DELTA ONLY", "WITH GAMMA"),
+            label_style,
+        )),
+    ];
+    let (mut first_total, mut second_total) = (0.0, 0.0);
+    let legs = app
+        .groups
+        .get(&what_if.underlying)
+        .into_iter()
+        .flat_map(|group| group.records.values());
+    for rec in legs {
+        let (delta, gamma) = rec.scaled_delta_gamma();
+        let (first, second) = risk::move_estimate(delta, gamma, price_move);
+        first_total += first;
+        second_total += second;
+        lines.push(Spans::from(Span::raw(format!(
+            "{:<26}{:>14.2}{:>14.2}",
+            rec.symbol.0, first, second
+        ))));
+    }
+    lines.push(Spans::from(Span::styled(
+        format!("{:<26}{:>14.2}{:>14.2}", "TOTAL", first_total, second_total),
+        Style::default().add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Spans::from(Span::styled(
+        "Estimated P/L change from current greeks; ignores time and volatility",
+        Style::default().fg(Color::DarkGray),
+    )));
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(" WHAT-IF ESTIMATE (Esc to close) ")
+            .borders(Borders::ALL),
+    )
+}
+
+fn status_line(app: &App) -> Paragraph {
+    if app.confirm_quit {
+        let outstanding = app.outstanding().unwrap_or_default();
+        return Paragraph::new(Span::styled(
+            format!("{} — quit anyway? y/n", outstanding),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let mut spans = vec![];
+    let dropped = app.subscriptions.dropped_count();
+    if dropped > 0 {
+        spans.push(Span::styled(
+            format!(
+                "{} symbols not subscribed (limit {})  ",
+                dropped,
+                app.subscriptions.limit()
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(started) = app.refreshing {
+        let frame = started.elapsed().as_millis() / 100 % SPINNER.len() as u128;
+        spans.push(Span::styled(
+            format!("{} refreshing positions…  ", SPINNER[frame as usize]),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    for (account, stream) in &app.account_streams {
+        let short: String = account.chars().take(3).collect();
+        match stream {
+            AccountStream::Subscribed => {}
+            AccountStream::Subscribing => spans.push(Span::styled(
+                format!("acct {}…: subscribing  ", short),
+                Style::default().fg(Color::DarkGray),
+            )),
+            AccountStream::Retrying { attempts, .. } if *attempts < ACCOUNT_RETRY_WARN => spans
+                .push(Span::styled(
+                    format!("acct {}…: retrying  ", short),
+                    Style::default().fg(Color::Yellow),
+                )),
+            AccountStream::Retrying { error, .. } => spans.push(Span::styled(
+                format!(
+                    "acct {}…: not subscribed, balances are stale ({})  ",
+                    short, error
+                ),
+                Style::default().fg(Color::Red),
+            )),
+        }
+    }
+    let session = market::session(chrono::Utc::now());
+    spans.push(Span::styled(
+        format!("{}  ", session),
+        match session {
+            market::Session::Regular { early: true, .. } => Style::default().fg(Color::Yellow),
+            _ => Style::default().fg(Color::DarkGray),
+        },
+    ));
+    if let Some(cutoff) = app
+        .settings
+        .stale_after
+        .filter(|_| matches!(session, market::Session::Regular { .. }))
+    {
+        // Halted underlyings aren't expected to be quoted.
+        let oldest = app
+            .groups
+            .values()
+            .filter(|group| app.stale_cutoff(group).is_some())
+            .flat_map(|group| group.records.values())
+            .filter(|rec| app.in_tab(rec))
+            .filter_map(|rec| rec.updated)
+            .map(|updated| updated.elapsed())
+            .max();
+        if let Some(oldest) = oldest {
+            spans.push(Span::styled(
+                format!("oldest quote {}s  ", oldest.as_secs()),
+                if oldest > cutoff {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                },
+            ));
+        }
+    }
+    if let Some(backoff) = app.requests.backoff() {
+        spans.push(Span::styled(
+            format!("rate limited, requests wait {}s  ", backoff.as_secs() + 1),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let hedged = app.hidden_hedged_count();
+    if hedged > 0 {
+        spans.push(Span::styled(
+            format!("{} hedged groups hidden (z)  ", hedged),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if app.stream_stats.unparsed > 0 {
+        spans.push(Span::styled(
+            format!(
+                "{} account stream messages unparsed (D)  ",
+                app.stream_stats.unparsed
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if app.unread_alerts > 0 {
+        spans.push(Span::styled(
+            format!("{} alerts unread (a)  ", app.unread_alerts),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if app.attached_to.is_none() && app.alerts.arming() {
+        spans.push(Span::styled(
+            "alerts arming…  ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(address) = &app.attached_to {
+        spans.push(Span::styled(
+            format!("read-only view of {}  ", address),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if let Some(status) = &app.status {
+        spans.push(Span::raw(status.clone()));
+    }
+    Paragraph::new(Spans::from(spans))
+}
+
+/// Viewers have no streams of their own, so they go without the bar.
+fn connection_bar_height(app: &App) -> u16 {
+    u16::from(app.attached_to.is_none())
+}
+
+/// A line on how the quote and account streams are doing, red while either
+/// has an error to show.
+fn connection_bar(app: &App) -> Paragraph {
+    let stats = &app.stream_stats;
+    let last = |traffic: &streamstats::Traffic| {
+        traffic
+            .last_at()
+            .map_or("never".to_owned(), |at| at.format("%H:%M:%S").to_string())
+    };
+    let quotes = match &stats.quote_traffic.error {
+        Some(error) => format!("quotes: error: {}", error),
+        None => format!(
+            "quotes: {}, {} subscribed, {}/s, last {}",
+            if stats.quote_traffic.last_at().is_some() {
+                "connected"
+            } else {
+                "waiting"
+            },
+            app.subscriptions.subscribed_count(),
+            stats.quote_traffic.per_second(),
+            last(&stats.quote_traffic)
+        ),
+    };
+    let retrying = app
+        .account_streams
+        .values()
+        .find_map(|stream| match stream {
+            AccountStream::Retrying { error, .. } => Some(error),
+            _ => None,
+        });
+    let subscribed = app
+        .account_streams
+        .values()
+        .filter(|stream| matches!(stream, AccountStream::Subscribed))
+        .count();
+    let account = match (&stats.account_traffic.error, retrying) {
+        (Some(error), _) | (None, Some(error)) => format!("accounts: error: {}", error),
+        (None, None) => format!(
+            "accounts: {}/{} subscribed, {}/s, last {}",
+            subscribed,
+            app.account_streams.len(),
+            stats.account_traffic.per_second(),
+            last(&stats.account_traffic)
+        ),
+    };
+    let errored = stats.quote_traffic.error.is_some()
+        || stats.account_traffic.error.is_some()
+        || retrying.is_some();
+    let style = if errored {
+        Style::default().fg(Color::White).bg(Color::Red)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    Paragraph::new(Span::styled(format!("{}  |  {}", quotes, account), style))
+}
+
+fn detail_pane(app: &App) -> Paragraph {
+    let label_style = Style::default().fg(Color::Red);
+    let lines = match app.selection() {
+        Some(Selection::Group(underlying)) => {
+            let mut lines = vec![Spans::from(vec![
+                Span::styled("UNDERLYING ", label_style),
+                Span::raw(format!(
+                    "{}  ({} positions)",
+                    underlying.0,
+                    app.groups[&underlying].records.len()
+                )),
+            ])];
+            if let Some(since_open) = since_open(app, &underlying) {
+                lines.push(Spans::from(vec![
+                    Span::styled("SINCE OPEN ", label_style),
+                    Span::raw(since_open),
+                ]));
+            }
+            lines
+        }
+        Some(Selection::Expiration(underlying, date)) => vec![Spans::from(vec![
+            Span::styled("EXPIRES ", label_style),
+            Span::raw(format!(
+                "{}  ({} {} legs, {} days out)",
+                date.format("%a %b %d %Y"),
+                app.groups[&underlying]
+                    .records
+                    .values()
+                    .filter(|rec| rec.option.as_ref().map_or(false, |o| o.expiration == date))
+                    .count(),
+                underlying.0,
+                (date - market::today()).num_days()
+            )),
+        ])],
+        Some(Selection::Expired) => vec![Spans::from(vec![
+            Span::styled("EXPIRED ", label_style),
+            Span::raw(format!(
+                "{} positions past expiration, excluded from totals",
+                app.expired.records.len()
+            )),
+        ])],
+        Some(Selection::Watchlist) => vec![Spans::from(vec![
+            Span::styled("WATCHLIST ", label_style),
+            Span::raw(format!(
+                "{} symbols watched, excluded from totals; - stops watching the selected one",
+                app.watchlist.len()
+            )),
+        ])],
+        Some(Selection::Watch(symbol)) => vec![Spans::from(vec![
+            Span::styled("WATCHING ", label_style),
+            Span::raw(format!("{}; - stops watching it", symbol.0.trim())),
+        ])],
+        Some(Selection::Record(..)) | Some(Selection::ExpiredRecord(_)) => {
+            match app.selected_record() {
+                Some(rec) => record_details(app, rec, label_style),
+                None => vec![],
+            }
+        }
+        None => vec![],
+    };
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL))
+}
+
+/// How far the underlying has moved since the group was first opened, e.g.
+/// "SPY +3.2% since open (410.50 on 2023-03-01)".
+fn since_open(app: &App, underlying: &Symbol) -> Option<String> {
+    let opened = app.opened.get(&underlying.0)?;
+    let price = app.underlying_price(underlying)?;
+    if opened.price.is_zero() {
+        return None;
+    }
+    let percent = (price - opened.price) * Decimal::from(100) / opened.price;
+    Some(format!(
+        "{} {:+}% since open ({} {}on {})",
+        underlying.0,
+        percent.round_dp(1),
+        opened.price.round_dp(2),
+        if opened.from_close { "close " } else { "" },
+        opened.date
+    ))
+}
+
+fn record_details<'a>(app: &'a App, rec: &'a PriceRecord, label_style: Style) -> Vec<Spans<'a>> {
+    let mut lines = vec![
+        Spans::from(vec![
+            Span::styled("SYMBOL ", label_style),
+            Span::raw(format!("{}  ", rec.symbol.0)),
+            Span::styled("ACCOUNT ", label_style),
+            Span::raw(rec.account.as_str()),
+        ]),
+        Spans::from(vec![
+            Span::styled("QUOTE ", label_style),
+            Span::raw(match &rec.quote {
+                Some(q) => format!(
+                    "bid {} ×{} / ask {} ×{}{}{}",
+                    q.bid.round_dp(2),
+                    q.bid_size,
+                    q.ask.round_dp(2),
+                    q.ask_size,
+                    q.spread_percent().map_or("".to_owned(), |percent| format!(
+                        ", {}% wide",
+                        percent.round_dp(1)
+                    )),
+                    match (q.delayed, rec.updated) {
+                        (true, _) => " (delayed)".to_owned(),
+                        (false, Some(updated)) =>
+                            format!(" ({}s ago)", updated.elapsed().as_secs()),
+                        (false, None) => "".to_owned(),
+                    }
+                ),
+                None => "-".to_owned(),
+            }),
+        ]),
+        Spans::from(vec![
+            Span::styled("NOTE ", label_style),
+            Span::raw(app.local_state.note(&rec.note_key()).unwrap_or("-")),
+        ]),
+    ];
+    if rec.currency != fx::HOME {
+        let rate = match rec.fx_rate {
+            Some(rate) => format!("{} {} per {}", rate.normalize(), fx::HOME, rec.currency),
+            None => "no FX rate, left out of totals".to_owned(),
+        };
+        lines.push(Spans::from(vec![
+            Span::styled("CURRENCY ", label_style),
+            Span::raw(format!(
+                "{}  net liq {} {}, P/L {} {}  ({})",
+                rec.currency,
+                money(rec.native_net(rec.current)),
+                rec.currency,
+                money(rec.native_net(rec.current - rec.open)),
+                rec.currency,
+                rate
+            )),
+        ]));
+    }
+    if let Some(deliverables) = &rec.deliverables {
+        let described: Vec<_> = deliverables.iter().map(Deliverable::describe).collect();
+        lines.push(Spans::from(vec![
+            Span::styled("ADJUSTED ", label_style),
+            Span::raw(format!(
+                "delivers {} per contract, multiplier {}",
+                described.join(" + "),
+                rec.multiplier.normalize()
+            )),
+        ]));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(symbol: &str, amount: i64, open: &str, current: &str) -> PriceRecord {
+        let direction = if amount < 0 {
+            QuantityDirection::Short
+        } else {
+            QuantityDirection::Long
+        };
+        let option = OptionSymbol::parse(symbol);
+        PriceRecord {
+            account: "5WT00001".to_owned(),
+            symbol: Symbol(symbol.to_owned()),
+            instrument_type: if option.is_some() {
+                InstrumentType::EquityOption
+            } else {
+                InstrumentType::Equity
+            },
+            open: open.parse().unwrap(),
+            current: current.parse().unwrap(),
+            amount: Decimal::from(amount.abs()),
+            multiplier: if option.is_some() {
+                Decimal::ONE_HUNDRED
+            } else {
+                Decimal::ONE
+            },
+            direction,
+            greeks: SimpleGreeks::default(),
+            quote: None,
+            option,
+            deliverables: None,
+            currency: fx::HOME.to_owned(),
+            fx_rate: Some(Decimal::ONE),
+            prev_close: None,
+            updated: None,
+        }
+    }
+
+    #[test]
+    fn split_adjusted_contract_keeps_its_multiplier() {
+        // XYZ after a 1-for-10 reverse split: a contract delivers 10 shares,
+        // and is still quoted against the position's multiplier of 100.
+        let info: instrument::EquityOptionInfo = serde_json::from_str(
+            r#"{
+                "symbol": "XYZ1  240119C00010000",
+                "root-symbol": "XYZ1",
+                "shares-per-contract": 10,
+                "deliverables": [
+                    {"deliverable-type": "Shares", "amount": "10", "symbol": "XYZ"},
+                    {"deliverable-type": "Cash", "amount": "3.50"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut rec = record("XYZ1  240119C00010000", 2, "1.00", "1.50");
+        assert!(instrument::is_adjusted_root(
+            &rec.option.as_ref().unwrap().root
+        ));
+        rec.adjust(&info);
+
+        assert_eq!(rec.multiplier, Decimal::ONE_HUNDRED);
+        assert_eq!(rec.to_net(rec.current), Decimal::from(300));
+        assert_eq!(rec.to_net(rec.current - rec.open), Decimal::from(100));
+        assert_eq!(rec.shares_per_contract(), Decimal::from(10));
+        let described: Vec<_> = rec
+            .deliverables
+            .iter()
+            .flatten()
+            .map(Deliverable::describe)
+            .collect();
+        assert_eq!(described, ["10 XYZ", "$3.50 cash"]);
+    }
+
+    fn app() -> App {
+        let settings = Settings {
+            frame_interval: None,
+            unfocused_interval: Duration::from_secs(2),
+            greeks_interval: Duration::from_secs(1),
+            snapshot_interval: Duration::from_secs(1),
+            stale_after: None,
+        };
+        App::new(
+            None,
+            vec![],
+            LocalState::default(),
+            Subscriptions::new(100),
+            &Config::default(),
+            settings,
+            &Args::parse_from(["tastytrade-cli"]),
+        )
+    }
+
+    fn insert(app: &mut App, underlying: &str, account: &str, mut rec: PriceRecord) {
+        rec.account = account.to_owned();
+        let key = RecordKey::new(&DxFeedSymbol(rec.symbol.0.clone()), &rec);
+        app.groups
+            .entry(Symbol(underlying.to_owned()))
+            .or_default()
+            .records
+            .insert(key, rec);
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn subtotals_cover_every_leg_in_the_tab() {
+        let mut app = app();
+        let (near, far) = (date(2024, 1, 19), date(2024, 2, 16));
+        let mut shares = record("SPY", 100, "470.00", "480.00");
+        shares.prev_close = Some("478.00".parse().unwrap());
+        insert(&mut app, "SPY", "A", shares);
+        let mut near_put = record("SPY   240119P00470000", -1, "3.10", "1.00");
+        near_put.greeks.theta = -0.05;
+        near_put.greeks.delta = 0.3;
+        near_put.prev_close = Some("1.20".parse().unwrap());
+        insert(&mut app, "SPY", "A", near_put);
+        let mut far_put = record("SPY   240216P00460000", -1, "4.00", "5.00");
+        far_put.greeks.theta = -0.02;
+        insert(&mut app, "SPY", "A", far_put);
+        // The same contract in another account.
+        insert(
+            &mut app,
+            "SPY",
+            "B",
+            record("SPY   240119P00470000", 2, "2.00", "1.00"),
+        );
+        // Small enough to be dust, and without delta, hedged.
+        insert(
+            &mut app,
+            "QQQ",
+            "A",
+            record("QQQ   240216C00500000", 1, "0.05", "0.02"),
+        );
+        let expired = record("SPY   231215P00450000", -1, "2.00", "0.00");
+        app.expired.records.insert(
+            RecordKey::new(&DxFeedSymbol(expired.symbol.0.clone()), &expired),
+            expired,
+        );
+        app.dust_threshold = Some(Decimal::from(10));
+        app.hedged_delta = 1.0;
+        app.hedged_net_liq = Decimal::from(10);
+        let week_end = date(2024, 1, 21);
+        let (spy, qqq) = (Symbol("SPY".to_owned()), Symbol("QQQ".to_owned()));
+
+        for filter in [None, Some(near), Some(far)] {
+            for tab in [None, Some("A"), Some("B")] {
+                for show_dust in [false, true] {
+                    for hide_hedged in [false, true] {
+                        app.expiration_filter = filter;
+                        app.tab = tab.map(str::to_owned);
+                        app.show_dust = show_dust;
+                        app.hide_hedged = hide_hedged;
+                        let case = format!(
+                            "filter {:?}, tab {:?}, dust shown {}, hedged hidden {}",
+                            filter, tab, show_dust, hide_hedged
+                        );
+                        let listed = |rec: &PriceRecord| {
+                            filter.map_or(true, |date| {
+                                rec.option.as_ref().map_or(false, |o| o.expiration == date)
+                            })
+                        };
+
+                        for (underlying, group) in &app.groups {
+                            let legs = app.tab_legs(underlying, group);
+                            let subtotals = Subtotals::new(&app, &legs, week_end);
+                            let all: Vec<&PriceRecord> = group
+                                .records
+                                .values()
+                                .filter(|rec| tab.map_or(true, |tab| rec.account == tab))
+                                .collect();
+                            let sum = |value: fn(&PriceRecord) -> Decimal| {
+                                all.iter().map(|rec| value(rec)).sum::<Decimal>()
+                            };
+                            assert_eq!(subtotals.legs, all.len(), "{}", case);
+                            assert_eq!(
+                                subtotals.profit,
+                                sum(|rec| rec.to_net(rec.current - rec.open)),
+                                "{}",
+                                case
+                            );
+                            assert_eq!(
+                                subtotals.net_liq,
+                                sum(|rec| rec.to_net(rec.current)),
+                                "{}",
+                                case
+                            );
+                            assert_eq!(
+                                subtotals.theta.total(),
+                                sum(PriceRecord::net_theta),
+                                "{}",
+                                case
+                            );
+                            assert_eq!(
+                                subtotals.credit,
+                                sum(|rec| rec.open_credit().unwrap_or_default()),
+                                "{}",
+                                case
+                            );
+                            assert_eq!(
+                                subtotals.cost_basis,
+                                sum(PriceRecord::cost_basis),
+                                "{}",
+                                case
+                            );
+                            let shown = all.iter().filter(|rec| listed(rec)).count();
+                            assert_eq!(subtotals.shown, shown, "{}", case);
+                            assert_eq!(
+                                subtotals.legs_shown().is_some(),
+                                shown < all.len(),
+                                "{}",
+                                case
+                            );
+                        }
+
+                        let table: Vec<&Symbol> = app.table_groups().map(|(u, _)| u).collect();
+                        let spy_shown = match (tab, filter) {
+                            (Some("B"), Some(date)) => date == near,
+                            _ => true,
+                        };
+                        assert_eq!(table.contains(&&spy), spy_shown, "{}", case);
+                        let qqq_shown =
+                            show_dust && !hide_hedged && tab != Some("B") && filter != Some(near);
+                        assert_eq!(table.contains(&&qqq), qqq_shown, "{}", case);
+                    }
+                }
+            }
+        }
+
+        // Unfiltered in the first account: the shares and both puts.
+        app.expiration_filter = None;
+        app.tab = Some("A".to_owned());
+        let subtotals = Subtotals::new(&app, &app.tab_legs(&spy, &app.groups[&spy]), week_end);
+        assert_eq!(subtotals.profit, "1110".parse::<Decimal>().unwrap());
+        assert_eq!(subtotals.net_liq, "47400".parse::<Decimal>().unwrap());
+        assert_eq!(subtotals.day, Some("220".parse().unwrap()));
+        assert_eq!(subtotals.theta.this_week, "5".parse::<Decimal>().unwrap());
+        assert_eq!(subtotals.theta.later, "2".parse::<Decimal>().unwrap());
+        assert_eq!(subtotals.legs_shown(), None);
+        // The expired leg is in none of it.
+        app.expiration_filter = Some(near);
+        let subtotals = Subtotals::new(&app, &app.tab_legs(&spy, &app.groups[&spy]), week_end);
+        assert_eq!(
+            subtotals.legs_shown().as_deref(),
+            Some("  1 of 3 legs shown")
+        );
+        assert_eq!(subtotals.profit, "1110".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn halted_group_is_never_stale() {
+        let mut app = app();
+        app.settings.stale_after = Some(Duration::from_secs(30));
+        let mut group = UnderlyingGroup {
+            underlying: Some(UnderlyingQuote {
+                symbol: DxFeedSymbol("SPY".to_owned()),
+                price: Some(Decimal::from(470)),
+                prev_close: None,
+                halted: true,
+            }),
+            ..UnderlyingGroup::default()
+        };
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+        };
+        let regular = at("2026-10-14T10:00:00-04:00");
+        let after_hours = at("2026-10-14T18:30:00-04:00");
+        assert_eq!(app.stale_cutoff_at(&group, regular), None);
+        let mut rec = record("SPY   240119P00470000", -1, "3.10", "1.00");
+        rec.updated = Some(Instant::now() - Duration::from_secs(600));
+        assert!(!rec.is_stale(app.stale_cutoff_at(&group, regular)));
+        // Once trading resumes, it is as stale as the session allows.
+        group.underlying.as_mut().unwrap().halted = false;
+        assert_eq!(
+            app.stale_cutoff_at(&group, regular),
+            Some(Duration::from_secs(30))
+        );
+        assert!(rec.is_stale(app.stale_cutoff_at(&group, regular)));
+        assert_eq!(app.stale_cutoff_at(&group, after_hours), None);
+        assert!(!rec.is_stale(app.stale_cutoff_at(&group, after_hours)));
+    }
+
+    /// Counts what is written to the terminal, where the test can still
+    /// see it once the backend has taken the writer.
+    #[derive(Default, Clone)]
+    struct Counter {
+        bytes: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.bytes.set(self.bytes.get() + buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A synthetic large portfolio: 40 underlyings, each with shares and
+    /// six option legs, every group open and streamed.
+    fn large_portfolio() -> App {
+        let mut app = app();
+        for i in 0..40 {
+            let underlying = format!("U{:02}", i);
+            let mut legs = vec![record(&underlying, 100, "50.00", "52.00")];
+            for (expiration, kind) in [("240119", 'P'), ("240216", 'C'), ("240315", 'P')] {
+                for strike in [45, 55] {
+                    let symbol = format!("{:<6}{}{}{:05}000", underlying, expiration, kind, strike);
+                    legs.push(record(&symbol, -2, "1.50", "1.20"));
+                }
+            }
+            for mut rec in legs {
+                rec.quote = Some(BidAsk {
+                    bid: rec.current - Decimal::new(5, 2),
+                    bid_size: 10.0,
+                    ask: rec.current + Decimal::new(5, 2),
+                    ask_size: 10.0,
+                    delayed: false,
+                });
+                insert(&mut app, &underlying, "5WT00001", rec);
+            }
+            app.groups.get_mut(&Symbol(underlying)).unwrap().open = true;
+        }
+        app.update_num_lines();
+        app
+    }
+
+    /// Bytes sent to a 200x60 terminal over a simulated minute of quotes,
+    /// `per_frame` of them every quarter second, drawing every `draw_every`
+    /// quarter seconds; gated draws skip frames where nothing changed.
+    fn bytes_per_minute(per_frame: usize, draw_every: usize, gated: bool) -> usize {
+        let mut app = large_portfolio();
+        let keys: Vec<(Symbol, RecordKey)> = app
+            .groups
+            .iter()
+            .flat_map(|(underlying, group)| {
+                group
+                    .records
+                    .keys()
+                    .map(move |key| (underlying.clone(), key.clone()))
+            })
+            .collect();
+        let viewport = tui::Viewport::fixed(Rect::new(0, 0, 200, 60));
+        let counter = Counter::default();
+        let backend = CrosstermBackend::new(counter.clone());
+        let mut terminal =
+            Terminal::with_options(backend, tui::TerminalOptions { viewport }).unwrap();
+        // A fixed-seed linear congruential generator picks the legs quoted.
+        let mut seed: u64 = 230;
+        let mut dirty = true;
+        for frame in 0..240 {
+            for _ in 0..per_frame {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let (underlying, key) = &keys[(seed >> 33) as usize % keys.len()];
+                let rec = app
+                    .groups
+                    .get_mut(underlying)
+                    .unwrap()
+                    .records
+                    .get_mut(key)
+                    .unwrap();
+                let tick = Decimal::new(1, 2);
+                rec.current += if seed & 1 == 0 { tick } else { -tick };
+                dirty = true;
+            }
+            if frame % draw_every == 0 && (dirty || !gated) {
+                terminal.draw(|f| ui(f, &mut app)).unwrap();
+                dirty = false;
+            }
+        }
+        terminal.backend_mut().flush().unwrap();
+        counter.bytes.get()
+    }
+
+    /// Not run by default: it draws a few hundred frames of a large table.
+    /// `cargo test bytes_written -- --ignored --nocapture` prints the
+    /// figures. It hasn't been run yet, so there are no figures to go by and
+    /// the measurement is still open.
+    #[test]
+    #[ignore]
+    fn bytes_written_per_minute() {
+        let cases = [
+            ("4 fps, always drawn, quiet market", 0, 1, false),
+            ("4 fps, drawn on change, quiet market", 0, 1, true),
+            ("4 fps, always drawn, 12 quotes a frame", 12, 1, false),
+            ("4 fps, drawn on change, 12 quotes a frame", 12, 1, true),
+            ("--max-fps 0.2, 12 quotes a frame", 12, 20, true),
+        ];
+        let mut measured = vec![];
+        for (name, per_frame, draw_every, gated) in cases {
+            let bytes = bytes_per_minute(per_frame, draw_every, gated);
+            println!("{:<45} {:>10} bytes/min", name, bytes);
+            measured.push(bytes);
+        }
+        // Skipping unchanged frames never costs bytes, and the frame limit
+        // cuts them. By how much is what the run is for: each of its draws
+        // carries more changes, so a twentieth of the draws needn't be a
+        // twentieth of the bytes.
+        assert!(measured[1] <= measured[0]);
+        assert!(measured[3] <= measured[2]);
+        assert!(measured[4] < measured[3]);
+    }
+
+    #[test]
+    fn ticket_from_the_chain_takes_the_keys() {
+        let mut app = app();
+        insert(
+            &mut app,
+            "SPY",
+            "5WT00001",
+            record("SPY", 100, "470.00", "480.00"),
+        );
+        let mut chain = chain::from_body(
+            Symbol("SPY".to_owned()),
+            r#"{"items": [{"expirations": [{
+                "expiration-date": "2099-01-16",
+                "strikes": [
+                    {"strike-price": "470", "call": "SPY   990116C00470000",
+                     "call-streamer-symbol": ".SPY990116C470", "put": "SPY   990116P00470000",
+                     "put-streamer-symbol": ".SPY990116P470"},
+                    {"strike-price": "480", "call": "SPY   990116C00480000",
+                     "call-streamer-symbol": ".SPY990116C480", "put": "SPY   990116P00480000",
+                     "put-streamer-symbol": ".SPY990116P480"}
+                ]
+            }]}]}"#,
+            market::today(),
+        );
+        chain.streamed = chain.visible_symbols();
+        for symbol in [".SPY990116C470", ".SPY990116C480"] {
+            chain.apply_quote(
+                &DxFeedSymbol(symbol.to_owned()),
+                Decimal::new(200, 2),
+                Decimal::new(210, 2),
+            );
+        }
+        app.chain = Some(chain);
+
+        // Enter on the chain opens a ticket for the selected contract.
+        assert!(app.handle_key(KeyCode::Enter).is_none());
+        let price = app.ticket.as_ref().expect("a ticket").price;
+        // The arrows edit the ticket's price, not the chain's cursor.
+        app.handle_key(KeyCode::Down);
+        assert_eq!(app.chain.as_ref().unwrap().strike, 0);
+        assert_eq!(
+            app.ticket.as_ref().unwrap().price,
+            price - Decimal::new(1, 2)
+        );
+        // Enter on the ticket asks for the dry run.
+        assert!(matches!(
+            app.handle_key(KeyCode::Enter),
+            Some(Action::Ticket(ticket::Request::DryRun(_)))
+        ));
+        // Esc backs out of the confirmation, then closes the ticket; the
+        // chain stays open under it.
+        app.handle_key(KeyCode::Esc);
+        app.handle_key(KeyCode::Esc);
+        assert!(app.ticket.is_none());
+        assert!(app.chain.is_some());
+    }
+
+    #[test]
+    fn quitting_counts_unread_alerts_and_pending_orders() {
+        let mut app = app();
+        assert_eq!(app.outstanding(), None);
+        assert!(matches!(
+            app.handle_key(KeyCode::Char('q')),
+            Some(Action::Quit)
+        ));
+
+        app.unread_alerts = 2;
+        app.orders_in_flight = 1;
+        assert_eq!(
+            app.outstanding().as_deref(),
+            Some("2 alerts unread, 1 order pending")
+        );
+        // Placed, and not yet heard of from the account streamer.
+        app.orders_in_flight = 0;
+        app.orders.track("OrderId(7)".to_owned());
+        app.unread_alerts = 0;
+        assert_eq!(app.outstanding().as_deref(), Some("1 order pending"));
+        assert!(app.handle_key(KeyCode::Char('q')).is_none());
+        assert!(app.confirm_quit);
+        assert!(matches!(
+            app.handle_key(KeyCode::Char('y')),
+            Some(Action::Quit)
+        ));
+    }
+
+    #[test]
+    fn minus_stops_watching_the_selected_row() {
+        let mut app = app();
+        insert(&mut app, "SPY", "5WT00001", record("SPY", 10, "400", "410"));
+        for symbol in ["QQQ", "IWM"] {
+            app.subscriptions
+                .want(DxFeedSymbol(symbol.to_owned()), Priority::Watchlist);
+            app.watchlist.push(WatchItem {
+                symbol: Symbol(symbol.to_owned()),
+                label: None,
+                quote: UnderlyingQuote {
+                    symbol: DxFeedSymbol(symbol.to_owned()),
+                    price: None,
+                    prev_close: None,
+                    halted: false,
+                },
+                bid: None,
+                ask: None,
+            });
+        }
+        app.update_num_lines();
+        // Up from the first line wraps round to the last watched symbol.
+        app.state.select(Some(0));
+        app.previous();
+        assert!(app.selection() == Some(Selection::Watch(Symbol("IWM".to_owned()))));
+        app.previous();
+        app.previous();
+        assert!(app.selection() == Some(Selection::Watchlist));
+
+        app.next();
+        assert!(matches!(
+            app.handle_key(KeyCode::Char('-')),
+            Some(Action::SyncSubscriptions)
+        ));
+        assert!(app.input.is_none());
+        assert!(!app.watched(&Symbol("QQQ".to_owned())));
+        assert!(app.selection() == Some(Selection::Watch(Symbol("IWM".to_owned()))));
+
+        // Off the watchlist, - asks which symbol.
+        app.state.select(Some(0));
+        assert!(app.handle_key(KeyCode::Char('-')).is_none());
+        assert!(app.input.is_some());
+    }
 }
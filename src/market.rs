@@ -0,0 +1,215 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::America::New_York;
+
+/// NYSE full-day closures in 2026 and 2027, as (year, month, day). Holidays
+/// falling on a weekend are listed on the weekday observed. Outside those
+/// years only weekends are closed.
+const HOLIDAYS: [(i32, u32, u32); 20] = [
+    (2026, 1, 1),
+    (2026, 1, 19),
+    (2026, 2, 16),
+    (2026, 4, 3),
+    (2026, 5, 25),
+    (2026, 6, 19),
+    (2026, 7, 3),
+    (2026, 9, 7),
+    (2026, 11, 26),
+    (2026, 12, 25),
+    (2027, 1, 1),
+    (2027, 1, 18),
+    (2027, 2, 15),
+    (2027, 3, 26),
+    (2027, 5, 31),
+    (2027, 6, 18),
+    (2027, 7, 5),
+    (2027, 9, 6),
+    (2027, 11, 25),
+    (2027, 12, 24),
+];
+
+/// Sessions that close at 13:00: the days after Thanksgiving and
+/// Christmas Eves that aren't holidays themselves.
+const HALF_DAYS: [(i32, u32, u32); 3] = [(2026, 11, 27), (2026, 12, 24), (2027, 11, 26)];
+
+fn listed(days: &[(i32, u32, u32)], day: NaiveDate) -> bool {
+    days.contains(&(day.year(), day.month(), day.day()))
+}
+
+fn pre_market() -> NaiveTime {
+    NaiveTime::from_hms_opt(4, 0, 0).unwrap()
+}
+
+fn open() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 30, 0).unwrap()
+}
+
+/// When extended hours end after a session closing at `closes`: four hours
+/// later, which is 17:00 on half days.
+fn after_hours_end(closes: NaiveTime) -> NaiveTime {
+    closes + Duration::hours(4)
+}
+
+/// The current date on the exchange's clock.
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&New_York).date_naive()
+}
+
+/// The last day of the Monday to Sunday week `day` falls in.
+pub fn week_end(day: NaiveDate) -> NaiveDate {
+    day + Duration::days(6 - i64::from(day.weekday().num_days_from_monday()))
+}
+
+/// Whether the exchange has a session on `day`.
+pub fn is_trading_day(day: NaiveDate) -> bool {
+    !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) && !listed(&HOLIDAYS, day)
+}
+
+/// When the regular session on `day` closes, on the exchange's clock, if
+/// there is one.
+pub fn close(day: NaiveDate) -> Option<NaiveTime> {
+    if !is_trading_day(day) {
+        return None;
+    }
+    let hour = if listed(&HALF_DAYS, day) { 13 } else { 16 };
+    NaiveTime::from_hms_opt(hour, 0, 0)
+}
+
+/// Where the exchange's trading day is at a moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    /// A weekend or holiday, or overnight.
+    Closed,
+    PreMarket,
+    /// The regular session, which closes at `closes`, early on half days.
+    Regular {
+        closes: NaiveTime,
+        early: bool,
+    },
+    AfterHours,
+}
+
+/// The session at `now`. The exchange's clock is New York time, so the
+/// hours move with daylight saving time on and off.
+pub fn session(now: DateTime<Utc>) -> Session {
+    let local = now.with_timezone(&New_York);
+    let (day, time) = (local.date_naive(), local.time());
+    let closes = match close(day) {
+        Some(closes) => closes,
+        None => return Session::Closed,
+    };
+    if time < pre_market() {
+        Session::Closed
+    } else if time < open() {
+        Session::PreMarket
+    } else if time < closes {
+        Session::Regular {
+            closes,
+            early: listed(&HALF_DAYS, day),
+        }
+    } else if time < after_hours_end(closes) {
+        Session::AfterHours
+    } else {
+        Session::Closed
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Session::Closed => write!(f, "market closed"),
+            Session::PreMarket => write!(f, "pre-market"),
+            Session::Regular { closes, early } if *early => {
+                write!(
+                    f,
+                    "market open, closes early at {} ET",
+                    closes.format("%H:%M")
+                )
+            }
+            Session::Regular { .. } => write!(f, "market open"),
+            Session::AfterHours => write!(f, "after hours"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn regular(hour: u32, early: bool) -> Session {
+        Session::Regular {
+            closes: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            early,
+        }
+    }
+
+    #[test]
+    fn close_on_full_and_half_days() {
+        assert_eq!(close(day(2026, 11, 25)), NaiveTime::from_hms_opt(16, 0, 0));
+        assert_eq!(close(day(2026, 11, 26)), None);
+        assert_eq!(close(day(2026, 11, 27)), NaiveTime::from_hms_opt(13, 0, 0));
+        assert_eq!(close(day(2026, 12, 24)), NaiveTime::from_hms_opt(13, 0, 0));
+        assert_eq!(close(day(2026, 12, 26)), None);
+        // Christmas Eve 2027 is the observed holiday, not a half day.
+        assert_eq!(close(day(2027, 12, 24)), None);
+    }
+
+    #[test]
+    fn half_day_closes_at_13_et() {
+        // The day after Thanksgiving 2026 is on standard time, UTC-5.
+        assert_eq!(session(at("2026-11-27T14:29:00Z")), Session::PreMarket);
+        assert_eq!(session(at("2026-11-27T14:30:00Z")), regular(13, true));
+        assert_eq!(session(at("2026-11-27T17:59:59Z")), regular(13, true));
+        assert_eq!(session(at("2026-11-27T18:00:00Z")), Session::AfterHours);
+        assert_eq!(session(at("2026-11-27T21:59:59Z")), Session::AfterHours);
+        assert_eq!(session(at("2026-11-27T22:00:00Z")), Session::Closed);
+        assert_eq!(
+            regular(13, true).to_string(),
+            "market open, closes early at 13:00 ET"
+        );
+    }
+
+    #[test]
+    fn spring_forward_moves_the_hours_in_utc() {
+        // Clocks go forward at 02:00 on Sunday 8 March 2026. The Friday
+        // before is on UTC-5 and the Monday after on UTC-4.
+        assert_eq!(session(at("2026-03-06T13:30:00Z")), Session::PreMarket);
+        assert_eq!(session(at("2026-03-06T14:30:00Z")), regular(16, false));
+        assert_eq!(session(at("2026-03-06T20:59:59Z")), regular(16, false));
+        assert_eq!(session(at("2026-03-06T21:00:00Z")), Session::AfterHours);
+        // Either side of the change, Sunday stays closed.
+        assert_eq!(session(at("2026-03-08T06:59:59Z")), Session::Closed);
+        assert_eq!(session(at("2026-03-08T07:00:00Z")), Session::Closed);
+        assert_eq!(session(at("2026-03-08T13:30:00Z")), Session::Closed);
+        assert_eq!(session(at("2026-03-09T07:59:59Z")), Session::Closed);
+        assert_eq!(session(at("2026-03-09T08:00:00Z")), Session::PreMarket);
+        assert_eq!(session(at("2026-03-09T13:30:00Z")), regular(16, false));
+        assert_eq!(session(at("2026-03-09T19:59:59Z")), regular(16, false));
+        assert_eq!(session(at("2026-03-09T20:00:00Z")), Session::AfterHours);
+        assert_eq!(session(at("2026-03-10T00:00:00Z")), Session::Closed);
+    }
+
+    #[test]
+    fn sundays_local_date_is_new_york_time() {
+        // 03:30 UTC on Monday is still Sunday evening in New York.
+        let sunday_evening = at("2026-03-09T03:30:00Z");
+        assert_eq!(
+            sunday_evening.with_timezone(&New_York).date_naive(),
+            day(2026, 3, 8)
+        );
+        assert_eq!(session(sunday_evening), Session::Closed);
+        assert_eq!(week_end(day(2026, 3, 9)), day(2026, 3, 15));
+        assert_eq!(week_end(day(2026, 3, 8)), day(2026, 3, 8));
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tastytrade_rs::{
+    api::order::{InstrumentType, Symbol},
+    TastyTrade,
+};
+
+/// Most symbols asked for in one request.
+const BATCH_SIZE: usize = 100;
+
+/// A snapshot quote from the REST market data endpoint, which may be
+/// delayed.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MarketData {
+    pub symbol: String,
+    #[serde(default)]
+    pub bid: Option<Decimal>,
+    #[serde(default)]
+    pub bid_size: Option<Decimal>,
+    #[serde(default)]
+    pub ask: Option<Decimal>,
+    #[serde(default)]
+    pub ask_size: Option<Decimal>,
+    #[serde(default)]
+    pub mark: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    items: Vec<MarketData>,
+}
+
+/// The query parameter `instrument_type` is listed under, for the types the
+/// endpoint knows.
+fn parameter(instrument_type: &InstrumentType) -> Option<&'static str> {
+    match instrument_type {
+        InstrumentType::Equity => Some("equity"),
+        InstrumentType::EquityOption => Some("equity-option"),
+        InstrumentType::Future => Some("future"),
+        InstrumentType::FutureOption => Some("future-option"),
+        _ => None,
+    }
+}
+
+/// Downloads quotes for `symbols` in bulk. Symbols of other instrument
+/// types are skipped.
+pub async fn fetch(
+    tasty: &TastyTrade,
+    symbols: Vec<(InstrumentType, Symbol)>,
+) -> Result<Vec<MarketData>> {
+    let symbols: Vec<_> = symbols
+        .iter()
+        .filter_map(|(instrument_type, symbol)| Some((parameter(instrument_type)?, symbol)))
+        .collect();
+    let mut quotes = vec![];
+    for batch in symbols.chunks(BATCH_SIZE) {
+        let query: Vec<String> = batch
+            .iter()
+            // OCC option symbols pad their roots with spaces.
+            .map(|(parameter, symbol)| format!("{}={}", parameter, symbol.0.replace(' ', "%20")))
+            .collect();
+        let url = format!("/market-data/by-type?{}", query.join("&"));
+        let page: Page = tasty.get(url).await.context("Downloading market data")?;
+        quotes.extend(page.items);
+    }
+    Ok(quotes)
+}
+
+/// One trading day from an equity's price history.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DailyBar {
+    date: NaiveDate,
+    close: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct History {
+    items: Vec<DailyBar>,
+}
+
+/// The close of the equity `symbol` on `date`, or on the next trading day
+/// when the market was shut. None when the history doesn't go back that
+/// far.
+pub async fn close_on(
+    tasty: &TastyTrade,
+    symbol: &Symbol,
+    date: NaiveDate,
+) -> Result<Option<Decimal>> {
+    // A week covers any run of weekends and holidays.
+    let url = format!(
+        "/market-data/history/{}?interval=1d&start-date={}&end-date={}",
+        symbol.0,
+        date,
+        date + chrono::Duration::days(7)
+    );
+    let history: History = tasty
+        .get(url)
+        .await
+        .with_context(|| format!("Downloading the price history of {}", symbol.0))?;
+    Ok(history
+        .items
+        .into_iter()
+        .filter(|bar| bar.date >= date)
+        .min_by_key(|bar| bar.date)
+        .map(|bar| bar.close))
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use tastytrade_rs::api::order::{LiveOrderRecord, OrderStatus};
+
+use crate::orders;
+
+/// How long a notification stays on screen unless a key dismisses it.
+pub const TOAST_TIME: Duration = Duration::from_secs(6);
+/// Most notifications stacked on screen at once. Older ones still showing
+/// are counted below them.
+pub const TOASTS_SHOWN: usize = 4;
+/// Notifications kept for the history.
+const HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Filled,
+    PartlyFilled,
+    Rejected,
+    Cancelled,
+    /// About something asked of tastytrade from here.
+    Info,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Filled => "filled",
+            Kind::PartlyFilled => "partly filled",
+            Kind::Rejected => "rejected",
+            Kind::Cancelled => "cancelled",
+            Kind::Info => "",
+        }
+    }
+}
+
+pub struct Notice {
+    pub kind: Kind,
+    pub at: DateTime<Local>,
+    pub text: String,
+    shown: Instant,
+}
+
+/// Order updates from the account streamer worth telling about, both as
+/// they arrive and afterwards.
+pub struct Notifications {
+    /// Oldest first.
+    pub history: VecDeque<Notice>,
+    /// What was last told of each order, by id, so that repeats of the same
+    /// state aren't told twice.
+    told: HashMap<String, String>,
+    /// How many of the oldest notifications have been taken off the screen.
+    dismissed: usize,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            told: HashMap::new(),
+            dismissed: 0,
+        }
+    }
+
+    /// Notes an order update if it fills, partly fills, rejects or cancels
+    /// the order, returning whether it did.
+    pub fn order(&mut self, order: &LiveOrderRecord) -> bool {
+        let partly = order
+            .legs
+            .iter()
+            .any(|leg| leg.remaining_quantity < leg.quantity);
+        let kind = match order.status {
+            OrderStatus::Filled => Kind::Filled,
+            OrderStatus::Rejected => Kind::Rejected,
+            OrderStatus::Cancelled | OrderStatus::Expired => Kind::Cancelled,
+            _ if partly => Kind::PartlyFilled,
+            _ => return false,
+        };
+        let state = format!(
+            "{:?} {:?}",
+            order.status,
+            order
+                .legs
+                .iter()
+                .map(|leg| &leg.remaining_quantity)
+                .collect::<Vec<_>>()
+        );
+        let id = orders::id(order);
+        if self.told.get(&id) == Some(&state) {
+            return false;
+        }
+        self.told.insert(id, state);
+
+        let legs: Vec<String> = order
+            .legs
+            .iter()
+            .map(|leg| match kind {
+                Kind::PartlyFilled => format!(
+                    "{:?} {}/{} {}",
+                    leg.action,
+                    leg.quantity - leg.remaining_quantity,
+                    leg.quantity,
+                    leg.symbol.0
+                ),
+                _ => format!("{:?} {} {}", leg.action, leg.quantity, leg.symbol.0),
+            })
+            .collect();
+        self.push(
+            kind,
+            format!(
+                "{} {}: {} @ {}",
+                order.underlying_symbol.0,
+                kind.label(),
+                legs.join(", "),
+                order.price
+            ),
+        );
+        true
+    }
+
+    pub fn push(&mut self, kind: Kind, text: String) {
+        self.history.push_back(Notice {
+            kind,
+            at: Local::now(),
+            text,
+            shown: Instant::now(),
+        });
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+            self.dismissed = self.dismissed.saturating_sub(1);
+        }
+    }
+
+    /// Notifications still on screen, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notice> {
+        self.history
+            .iter()
+            .skip(self.dismissed)
+            .filter(|notice| notice.shown.elapsed() < TOAST_TIME)
+    }
+
+    /// Takes every notification off the screen. They stay in the history.
+    pub fn dismiss(&mut self) {
+        self.dismissed = self.history.len();
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+/// An equity option symbol in OCC format, e.g. `SPY   240119C00470000`: the
+/// root padded to six characters, the expiration as YYMMDD, C or P, and the
+/// strike times 1000 as eight digits.
+#[derive(Debug, Clone)]
+pub struct OptionSymbol {
+    pub root: String,
+    pub expiration: NaiveDate,
+    pub kind: OptionKind,
+    pub strike: Decimal,
+}
+
+impl OptionSymbol {
+    /// Returns `None` for anything that isn't an OCC option symbol, such as
+    /// shares or futures options.
+    pub fn parse(symbol: &str) -> Option<Self> {
+        if !symbol.is_ascii() || symbol.len() < 16 {
+            return None;
+        }
+        let (root, rest) = symbol.split_at(symbol.len() - 15);
+        let root = root.trim_end();
+        if root.is_empty() || root.contains(char::is_whitespace) {
+            return None;
+        }
+        let expiration = NaiveDate::parse_from_str(&rest[..6], "%y%m%d").ok()?;
+        let kind = match &rest[6..7] {
+            "C" => OptionKind::Call,
+            "P" => OptionKind::Put,
+            _ => return None,
+        };
+        let strike = &rest[7..];
+        if !strike.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let strike = Decimal::new(strike.parse().ok()?, 3).normalize();
+        Some(Self {
+            root: root.to_owned(),
+            expiration,
+            kind,
+            strike,
+        })
+    }
+}
+
+/// Written like `19 Jan 24 4700 P`, for rows listed under their underlying.
+/// The root is left out, so weekly roots like SPXW read the same as the
+/// monthly ones.
+impl fmt::Display for OptionSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            OptionKind::Call => "C",
+            OptionKind::Put => "P",
+        };
+        write!(
+            f,
+            "{} {} {}",
+            self.expiration.format("%d %b %y"),
+            self.strike,
+            kind
+        )
+    }
+}
+
+/// Pads the root of an option symbol written without the padding, like
+/// `SPY240119C00470000`, out to OCC format.
+pub fn occ(symbol: &str) -> Option<String> {
+    OptionSymbol::parse(symbol)?;
+    let (root, rest) = symbol.split_at(symbol.len() - 15);
+    Some(format!("{:<6}{}", root.trim_end(), rest))
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::BTreeSet,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use tastytrade_rs::{
+    api::order::{LiveOrderRecord, OrderStatus},
+    TastyTrade,
+};
+
+use crate::{
+    readonly,
+    tradelog::{OrderAction, OrderDetails, TradeLog},
+};
+
+/// How long orders that filled or were cancelled stay listed as recent.
+pub const RECENT_TIME: Duration = Duration::from_secs(60);
+
+/// An order's id as text. Used to match updates to the orders they update.
+pub fn id(order: &LiveOrderRecord) -> String {
+    format!("{:?}", order.id)
+}
+
+/// Whether the order is done with, one way or another.
+pub fn is_done(status: &OrderStatus) -> bool {
+    matches!(
+        status,
+        OrderStatus::Filled
+            | OrderStatus::Cancelled
+            | OrderStatus::Expired
+            | OrderStatus::Rejected
+            | OrderStatus::Removed
+            | OrderStatus::PartiallyRemoved
+    )
+}
+
+/// Whether the order is on its way somewhere: being placed, or asked to
+/// be cancelled or replaced and not yet.
+pub fn is_transitional(status: &OrderStatus) -> bool {
+    matches!(
+        status,
+        OrderStatus::Received
+            | OrderStatus::Routed
+            | OrderStatus::InFlight
+            | OrderStatus::CancelRequested
+            | OrderStatus::ReplaceRequested
+    )
+}
+
+/// The order's side, quantity and price in a few words, like `BuyToOpen 1
+/// SPY   240119C00470000 @ 1.25 Debit`.
+pub fn describe(order: &LiveOrderRecord) -> String {
+    let legs: Vec<String> = order
+        .legs
+        .iter()
+        .map(|leg| format!("{:?} {} {}", leg.action, leg.quantity, leg.symbol.0))
+        .collect();
+    format!(
+        "{}: {} @ {} {:?}",
+        order.underlying_symbol.0,
+        legs.join(", "),
+        order.price.round_dp(2),
+        order.price_effect
+    )
+}
+
+/// Asks tastytrade to cancel `order`, through the trade log. The order
+/// only leaves the working orders once the account streamer says it has
+/// been cancelled. A cancel accepted but not logged comes with a warning.
+pub async fn cancel(
+    guard: readonly::Guard,
+    tasty: &TastyTrade,
+    log: TradeLog,
+    order: &LiveOrderRecord,
+) -> Result<(LiveOrderRecord, Option<String>)> {
+    guard.check("cancelling orders")?;
+    let account = order.account_number.0.clone();
+    let order_id = id(order);
+    let url = format!("/accounts/{}/orders/{}", account, order.id.0);
+    let details = OrderDetails {
+        action: OrderAction::Cancel,
+        account,
+        symbol: Some(order.underlying_symbol.0.clone()),
+        quantity: Decimal::from_str(&order.size.to_string()).ok(),
+        price: Some(order.price),
+        buying_power_effect: None,
+        order_id: Some(order_id),
+    };
+    let call = async move { tasty.delete(url).await.context("Cancelling the order") };
+    log.record(details, call, |order| Some(id(order))).await
+}
+
+struct Tracked {
+    id: String,
+    order: LiveOrderRecord,
+    /// When it was first seen done.
+    ended: Option<Instant>,
+}
+
+/// The accounts' orders: the ones still working, and for a while the ones
+/// that have just ended.
+pub struct Orders {
+    /// In the order they were first seen.
+    orders: Vec<Tracked>,
+    /// Which working order is selected, for acting on it.
+    pub selected: usize,
+    /// Ids of the orders placed or cancelled from this session.
+    session: BTreeSet<String>,
+}
+
+impl Orders {
+    pub fn new() -> Self {
+        Self {
+            orders: vec![],
+            selected: 0,
+            session: BTreeSet::new(),
+        }
+    }
+
+    /// Notes an order placed or cancelled from this session, which counts
+    /// as pending until an update says it has got where it was going.
+    pub fn track(&mut self, id: String) {
+        self.session.insert(id);
+    }
+
+    /// How many of this session's orders are still transitional. One no
+    /// update has arrived for yet counts too.
+    pub fn pending(&self) -> usize {
+        self.session
+            .iter()
+            .filter(|id| {
+                self.orders
+                    .iter()
+                    .find(|tracked| tracked.id == **id)
+                    .map_or(true, |tracked| is_transitional(&tracked.order.status))
+            })
+            .count()
+    }
+
+    /// Takes in an order as downloaded or sent by the account streamer.
+    pub fn update(&mut self, order: LiveOrderRecord) {
+        let id = id(&order);
+        let done = is_done(&order.status);
+        match self.orders.iter_mut().find(|tracked| tracked.id == id) {
+            Some(tracked) => {
+                tracked.ended = if done {
+                    tracked.ended.or_else(|| Some(Instant::now()))
+                } else {
+                    None
+                };
+                tracked.order = order;
+            }
+            None => self.orders.push(Tracked {
+                id,
+                order,
+                ended: done.then(Instant::now),
+            }),
+        }
+        self.clamp();
+    }
+
+    /// Drops the orders that ended over `RECENT_TIME` ago, returning
+    /// whether there were any.
+    pub fn prune(&mut self) -> bool {
+        let before = self.orders.len();
+        let session = &mut self.session;
+        self.orders.retain(|tracked| {
+            let kept = tracked
+                .ended
+                .map_or(true, |ended| ended.elapsed() < RECENT_TIME);
+            if !kept {
+                session.remove(&tracked.id);
+            }
+            kept
+        });
+        self.clamp();
+        self.orders.len() != before
+    }
+
+    pub fn working(&self) -> impl Iterator<Item = &LiveOrderRecord> {
+        self.orders
+            .iter()
+            .filter(|tracked| tracked.ended.is_none())
+            .map(|tracked| &tracked.order)
+    }
+
+    /// Orders that have ended, most recent first.
+    pub fn recent(&self) -> impl Iterator<Item = &LiveOrderRecord> {
+        let mut recent: Vec<_> = self
+            .orders
+            .iter()
+            .filter_map(|tracked| Some((tracked.ended?, &tracked.order)))
+            .collect();
+        recent.sort_by_key(|(ended, _)| std::cmp::Reverse(*ended));
+        recent.into_iter().map(|(_, order)| order)
+    }
+
+    pub fn selected(&self) -> Option<&LiveOrderRecord> {
+        self.working().nth(self.selected)
+    }
+
+    /// The working order with id `id`, if it is still working.
+    pub fn working_order(&self, id: &str) -> Option<&LiveOrderRecord> {
+        self.orders
+            .iter()
+            .find(|tracked| tracked.id == id && tracked.ended.is_none())
+            .map(|tracked| &tracked.order)
+    }
+
+    pub fn next(&mut self) {
+        self.selected += 1;
+        self.clamp();
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    fn clamp(&mut self) {
+        let working = self.working().count();
+        self.selected = self.selected.min(working.saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unseen_session_orders_are_pending() {
+        let mut orders = Orders::new();
+        assert_eq!(orders.pending(), 0);
+        orders.track("OrderId(1)".to_owned());
+        orders.track("OrderId(2)".to_owned());
+        orders.track("OrderId(1)".to_owned());
+        assert_eq!(orders.pending(), 2);
+        assert!(is_transitional(&OrderStatus::CancelRequested));
+        assert!(is_transitional(&OrderStatus::ReplaceRequested));
+        assert!(!is_transitional(&OrderStatus::Live));
+        assert!(!is_transitional(&OrderStatus::Filled));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tastytrade_rs::TastyTrade;
+
+/// An account's options approval, from least to most allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Long options and covered calls.
+    Limited,
+    /// Adds cash-secured puts.
+    CoveredAndCashSecured,
+    /// Adds spreads whose short legs are covered by long ones.
+    DefinedRisk,
+    NoRestrictions,
+}
+
+impl Level {
+    /// The level named in an account's trading status. Names it doesn't
+    /// know leave the account unchecked rather than blocked.
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Limited" => Some(Level::Limited),
+            "Covered And Cash Secured" => Some(Level::CoveredAndCashSecured),
+            "Defined Risk Spreads" | "Net Credit Debit Spreads" => Some(Level::DefinedRisk),
+            "No Restrictions" => Some(Level::NoRestrictions),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Limited => write!(f, "limited"),
+            Level::CoveredAndCashSecured => write!(f, "covered"),
+            Level::DefinedRisk => write!(f, "spreads"),
+            Level::NoRestrictions => write!(f, "unlimited"),
+        }
+    }
+}
+
+/// What an account may trade: its options level, and whether it is an
+/// IRA. IRAs never hold naked short calls or short shares, whatever the
+/// level says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permission {
+    pub level: Level,
+    pub ira: bool,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ira {
+            write!(f, "{}, IRA", self.level)
+        } else {
+            write!(f, "{}", self.level)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TradingStatus {
+    #[serde(default)]
+    options_level: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct AccountDetails {
+    #[serde(default)]
+    account_type_name: String,
+}
+
+/// Reads the options level and account type of each of `accounts`.
+/// Accounts whose level isn't known are left out.
+pub async fn fetch(
+    tasty: &TastyTrade,
+    accounts: Vec<String>,
+) -> Result<BTreeMap<String, Permission>> {
+    let mut permissions = BTreeMap::new();
+    for account in accounts {
+        let url = format!("/accounts/{}/trading-status", account);
+        let status: TradingStatus = tasty
+            .get(url)
+            .await
+            .with_context(|| format!("Downloading the trading status of {}", account))?;
+        let level = match status.options_level.as_deref().and_then(Level::parse) {
+            Some(level) => level,
+            None => continue,
+        };
+        let url = format!("/customers/me/accounts/{}", account);
+        let details: AccountDetails = tasty
+            .get(url)
+            .await
+            .with_context(|| format!("Downloading the details of {}", account))?;
+        // Like "Roth IRA", "Traditional IRA" or "SEP IRA".
+        let ira = details
+            .account_type_name
+            .split_whitespace()
+            .any(|word| word == "IRA");
+        permissions.insert(account, Permission { level, ira });
+    }
+    Ok(permissions)
+}
+
+/// One leg of an order as the rules see it. Quantities are signed, short
+/// negative, in contracts for options and shares otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leg {
+    Shares(Decimal),
+    Call(Decimal),
+    Put(Decimal),
+}
+
+/// Why an order opening `legs` isn't permitted with `permission`, if it
+/// isn't. `held` is the shares of the underlying already in the account,
+/// which with any bought in the order cover short calls at 100 a contract.
+/// From `DefinedRisk` long calls in the order cover them too. Short puts
+/// count as cash secured; whether the cash is there is left to the dry run.
+pub fn check(permission: Permission, legs: &[Leg], held: Decimal) -> Result<(), String> {
+    let refused = |what: String| {
+        Err(format!(
+            "{}: not permitted in this account (options level: {})",
+            what, permission
+        ))
+    };
+    let (mut shares, mut short_calls, mut long_calls) = (held, Decimal::ZERO, Decimal::ZERO);
+    let mut short_puts = false;
+    for leg in legs {
+        match *leg {
+            Leg::Shares(quantity) => shares += quantity,
+            Leg::Call(quantity) if quantity.is_sign_negative() => short_calls -= quantity,
+            Leg::Call(quantity) => long_calls += quantity,
+            Leg::Put(quantity) => short_puts |= quantity.is_sign_negative(),
+        }
+    }
+    if permission.ira && shares.is_sign_negative() {
+        return refused("short shares".to_owned());
+    }
+    let level = permission.level;
+    if level == Level::NoRestrictions && !permission.ira {
+        return Ok(());
+    }
+    let mut cover = (shares.max(Decimal::ZERO) / Decimal::from(100)).floor();
+    if level >= Level::DefinedRisk {
+        cover += long_calls;
+    }
+    if short_calls > cover {
+        return refused(format!("{} uncovered short calls", short_calls - cover));
+    }
+    if short_puts && level < Level::CoveredAndCashSecured {
+        return refused("short puts".to_owned());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(level: Level) -> Permission {
+        Permission { level, ira: false }
+    }
+
+    fn ira(level: Level) -> Permission {
+        Permission { level, ira: true }
+    }
+
+    fn d(n: i64) -> Decimal {
+        Decimal::from(n)
+    }
+
+    #[test]
+    fn levels_against_leg_structures() {
+        use Level::*;
+        let long_call = [Leg::Call(d(1))];
+        let naked_call = [Leg::Call(d(-1))];
+        let naked_put = [Leg::Put(d(-1))];
+        let call_spread = [Leg::Call(d(-1)), Leg::Call(d(1))];
+        let buy_write = [Leg::Shares(d(100)), Leg::Call(d(-1))];
+        // (legs, shares held, permitted at Limited, Covered, DefinedRisk, NoRestrictions)
+        let table: [(&[Leg], i64, [bool; 4]); 7] = [
+            (&long_call, 0, [true, true, true, true]),
+            (&naked_call, 0, [false, false, false, true]),
+            (&naked_call, 100, [true, true, true, true]),
+            (&naked_call, 99, [false, false, false, true]),
+            (&naked_put, 0, [false, true, true, true]),
+            (&call_spread, 0, [false, false, true, true]),
+            (&buy_write, 0, [true, true, true, true]),
+        ];
+        let levels = [Limited, CoveredAndCashSecured, DefinedRisk, NoRestrictions];
+        for (legs, held, permitted) in table {
+            for (level, permitted) in levels.into_iter().zip(permitted) {
+                assert_eq!(
+                    check(account(level), legs, d(held)).is_ok(),
+                    permitted,
+                    "{:?} holding {} at {:?}",
+                    legs,
+                    held,
+                    level
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn iras_refuse_naked_calls_and_short_shares_at_any_level() {
+        let naked_call = [Leg::Call(d(-1))];
+        assert!(check(account(Level::NoRestrictions), &naked_call, d(0)).is_ok());
+        let refused = check(ira(Level::NoRestrictions), &naked_call, d(0)).unwrap_err();
+        assert_eq!(
+            refused,
+            "1 uncovered short calls: not permitted in this account (options level: unlimited, IRA)"
+        );
+        assert!(check(ira(Level::NoRestrictions), &naked_call, d(100)).is_ok());
+        assert!(check(
+            ira(Level::DefinedRisk),
+            &[Leg::Call(d(-1)), Leg::Call(d(1))],
+            d(0)
+        )
+        .is_ok());
+        assert!(check(ira(Level::CoveredAndCashSecured), &[Leg::Put(d(-1))], d(0)).is_ok());
+
+        let short_sale = [Leg::Shares(d(-100))];
+        assert!(check(account(Level::Limited), &short_sale, d(0)).is_ok());
+        assert!(check(ira(Level::NoRestrictions), &short_sale, d(0)).is_err());
+        assert!(check(ira(Level::Limited), &short_sale, d(100)).is_ok());
+    }
+}
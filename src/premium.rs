@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_decimal::{prelude::Zero, Decimal};
+
+use crate::transactions::Transaction;
+
+/// Net premium collected on each underlying since `since`: option credits
+/// minus debits, including the cash from assignments, exercises and cash
+/// settlements. Share deliveries resulting from an assignment aren't premium
+/// and are left out.
+pub fn by_underlying(transactions: &[Transaction], since: NaiveDate) -> BTreeMap<String, Decimal> {
+    let mut premium = BTreeMap::new();
+    let option_flows = transactions
+        .iter()
+        .filter(|t| t.is_option() && t.executed_at.date_naive() >= since);
+    for transaction in option_flows {
+        if let Some(underlying) = &transaction.underlying_symbol {
+            *premium
+                .entry(underlying.clone())
+                .or_insert_with(Decimal::zero) += transaction.signed_value();
+        }
+    }
+    premium
+}
@@ -0,0 +1,38 @@
+use anyhow::{bail, Result};
+
+/// Whether this process may ask tastytrade to change anything. Every call
+/// that could, placing, dry running and cancelling orders, takes one and
+/// asks it first, so `--read-only` holds however the call was reached.
+#[derive(Debug, Clone, Copy)]
+pub struct Guard {
+    read_only: bool,
+}
+
+impl Guard {
+    pub fn new(read_only: bool) -> Self {
+        Self { read_only }
+    }
+
+    /// Fails, naming `what`, in read-only mode.
+    pub fn check(self, what: &str) -> Result<()> {
+        if self.read_only {
+            bail!("Read-only mode: {} is refused", what);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_refuses() {
+        assert!(Guard::new(false).check("placing orders").is_ok());
+        let refused = Guard::new(true).check("placing orders").unwrap_err();
+        assert_eq!(
+            refused.to_string(),
+            "Read-only mode: placing orders is refused"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+/// How far back requests are kept for the rates.
+const WINDOW: Duration = Duration::from_secs(300);
+
+/// How long requests are held back after tastytrade answers one with 429.
+/// The client doesn't pass on the Retry-After header, so this stands in
+/// for it.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// One finished call to the REST API, or a batch of them made together
+/// like a position refresh.
+pub struct Request {
+    pub name: &'static str,
+    pub at: Instant,
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+/// The outcomes of recent REST requests, for the stream panel, and whether
+/// they are being held back after hitting the rate limit.
+pub struct RequestStats {
+    pub recent: VecDeque<Request>,
+    pub rate_limited: usize,
+    backoff_until: Option<Instant>,
+}
+
+/// Awaits `request`, timing it.
+pub async fn timed<T>(request: impl Future<Output = Result<T>>) -> (Duration, Result<T>) {
+    let started = Instant::now();
+    let result = request.await;
+    (started.elapsed(), result)
+}
+
+fn is_rate_limit(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string();
+        message.contains("429") || message.contains("Too Many Requests")
+    })
+}
+
+impl RequestStats {
+    pub fn new() -> Self {
+        Self {
+            recent: VecDeque::new(),
+            rate_limited: 0,
+            backoff_until: None,
+        }
+    }
+
+    pub fn record<T>(&mut self, name: &'static str, latency: Duration, result: &Result<T>) {
+        let now = Instant::now();
+        if let Err(e) = result {
+            if is_rate_limit(e) {
+                self.rate_limited += 1;
+                self.backoff_until = Some(now + RATE_LIMIT_BACKOFF);
+            }
+        }
+        self.recent.push_back(Request {
+            name,
+            at: now,
+            latency,
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        });
+        while let Some(oldest) = self.recent.front() {
+            if now - oldest.at <= WINDOW {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+
+    /// How long requests should still wait after the last 429, if at all.
+    pub fn backoff(&self) -> Option<Duration> {
+        let until = self.backoff_until?;
+        until
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+    }
+
+    pub fn per_minute(&self) -> f64 {
+        self.recent.len() as f64 * 60.0 / WINDOW.as_secs_f64()
+    }
+
+    pub fn errors(&self) -> usize {
+        self.recent
+            .iter()
+            .filter(|request| request.error.is_some())
+            .count()
+    }
+
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let count = u32::try_from(self.recent.len()).ok().filter(|n| *n > 0)?;
+        Some(
+            self.recent
+                .iter()
+                .map(|request| request.latency)
+                .sum::<Duration>()
+                / count,
+        )
+    }
+}
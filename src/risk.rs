@@ -0,0 +1,124 @@
+/// How many of the largest contributors are kept per measure.
+const TOP_COUNT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measure {
+    Delta,
+    BetaDelta,
+    Gamma,
+    Theta,
+    Vega,
+}
+
+impl Measure {
+    pub const ALL: [Measure; 5] = [
+        Measure::Delta,
+        Measure::BetaDelta,
+        Measure::Gamma,
+        Measure::Theta,
+        Measure::Vega,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Measure::Delta => "DELTA",
+            Measure::BetaDelta => "BETA-WEIGHTED $ DELTA",
+            Measure::Gamma => "GAMMA",
+            Measure::Theta => "THETA",
+            Measure::Vega => "VEGA",
+        }
+    }
+}
+
+/// One position's greeks, already scaled to its size.
+pub struct PositionRisk {
+    pub symbol: String,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    /// Price of the underlying, when there is one to go by.
+    pub underlying_price: Option<f64>,
+    pub beta: f64,
+}
+
+impl PositionRisk {
+    /// Dollars the position gains per dollar the underlying moves, times the
+    /// underlying price.
+    fn dollar_delta(&self) -> Option<f64> {
+        Some(self.delta * self.underlying_price?)
+    }
+
+    fn value(&self, measure: Measure) -> Option<f64> {
+        match measure {
+            Measure::Delta => Some(self.delta),
+            Measure::BetaDelta => Some(self.dollar_delta()? * self.beta),
+            Measure::Gamma => Some(self.gamma),
+            Measure::Theta => Some(self.theta),
+            Measure::Vega => Some(self.vega),
+        }
+    }
+}
+
+pub struct MeasureSummary {
+    pub measure: Measure,
+    pub total: f64,
+    /// The positions contributing the most either way, largest first.
+    pub top: Vec<(String, f64)>,
+}
+
+pub struct RiskSummary {
+    pub measures: Vec<MeasureSummary>,
+    /// Dollar delta of the positions that gain when their underlying rises.
+    pub notional_long: f64,
+    /// Dollar delta of the positions that gain when their underlying falls.
+    pub notional_short: f64,
+    /// Positions left out of the dollar figures for lack of a price.
+    pub unpriced: usize,
+}
+
+pub fn summarize(positions: &[PositionRisk]) -> RiskSummary {
+    let measures = Measure::ALL
+        .into_iter()
+        .map(|measure| {
+            let mut values: Vec<(String, f64)> = positions
+                .iter()
+                .filter_map(|pos| Some((pos.symbol.clone(), pos.value(measure)?)))
+                .collect();
+            let total = values.iter().map(|(_, value)| value).sum();
+            values.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+            values.truncate(TOP_COUNT);
+            MeasureSummary {
+                measure,
+                total,
+                top: values,
+            }
+        })
+        .collect();
+
+    let mut summary = RiskSummary {
+        measures,
+        notional_long: 0.0,
+        notional_short: 0.0,
+        unpriced: 0,
+    };
+    for pos in positions {
+        match pos.dollar_delta() {
+            Some(notional) if notional >= 0.0 => summary.notional_long += notional,
+            Some(notional) => summary.notional_short += notional,
+            None => summary.unpriced += 1,
+        }
+    }
+    summary
+}
+
+/// Estimated change in a position's value when its underlying moves by
+/// `price_move`, from greeks already scaled to the position: first from
+/// delta alone, then with the gamma term added.
+pub fn move_estimate(delta: f64, gamma: f64, price_move: f64) -> (f64, f64) {
+    let first_order = delta * price_move;
+    (
+        first_order,
+        first_order + gamma * price_move * price_move / 2.0,
+    )
+}
@@ -0,0 +1,80 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
+use futures::stream::{self, BoxStream, StreamExt};
+
+/// One line of a `--script`: wait `delay`, then press `key`.
+pub struct Step {
+    delay: Duration,
+    key: KeyCode,
+}
+
+fn key(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Space" => KeyCode::Char(' '),
+        "Tab" => KeyCode::Tab,
+        "BackTab" => KeyCode::BackTab,
+        "Backspace" => KeyCode::Backspace,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('F'), Some(_)) => KeyCode::F(name[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+/// Reads a script of lines like `500 Down`: a delay in milliseconds and a
+/// key. Blank lines and ones starting with `#` are skipped.
+pub fn load(path: &Path) -> Result<Vec<Step>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+    let mut steps = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let step = line
+            .split_once(char::is_whitespace)
+            .and_then(|(delay, name)| {
+                Some(Step {
+                    delay: Duration::from_millis(delay.parse().ok()?),
+                    key: key(name.trim())?,
+                })
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} line {}: expected a delay and a key",
+                    path.display(),
+                    i + 1
+                )
+            })?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// The terminal's events with the script's key presses mixed in, each
+/// after its delay. An empty script leaves just the terminal's.
+pub fn events(steps: Vec<Step>) -> BoxStream<'static, io::Result<Event>> {
+    let scripted = stream::iter(steps).then(|step| async move {
+        tokio::time::sleep(step.delay).await;
+        Ok(Event::Key(KeyEvent::new(step.key, KeyModifiers::NONE)))
+    });
+    stream::select(EventStream::new(), scripted).boxed()
+}
@@ -0,0 +1,131 @@
+use std::{ops::RangeInclusive, time::Duration};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::Config;
+
+/// Redraw rates accepted, in frames a second.
+const FPS_RANGE: RangeInclusive<f64> = 0.01..=60.0;
+
+/// Snapshot intervals accepted, in seconds.
+const SNAPSHOT_INTERVAL_RANGE: RangeInclusive<f64> = 0.05..=3600.0;
+
+/// Staleness thresholds accepted, in seconds, besides 0 for off.
+const STALENESS_THRESHOLD_RANGE: RangeInclusive<f64> = 1.0..=3600.0;
+
+/// How often the table is redrawn while the terminal doesn't have focus.
+const UNFOCUSED_REDRAW_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Greeks change nearly as often as quotes, so each group's only ask for a
+/// redraw this often. Prices still redraw straight away.
+const GREEKS_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often things are drawn and sent, taken from the command line or else
+/// the config.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Shortest time between draws. Unlimited when unset.
+    pub frame_interval: Option<Duration>,
+    /// Shortest time between draws without focus.
+    pub unfocused_interval: Duration,
+    /// Shortest time between redraws asked for by one group's greeks.
+    pub greeks_interval: Duration,
+    /// How often viewers attached with --attach are sent the state.
+    pub snapshot_interval: Duration,
+    /// How long a position may go without a streamed update during regular
+    /// hours before its price is marked stale. None turns it off.
+    pub stale_after: Option<Duration>,
+}
+
+fn check(value: f64, range: RangeInclusive<f64>) -> Result<f64> {
+    if !range.contains(&value) {
+        bail!("Must be between {} and {}", range.start(), range.end());
+    }
+    Ok(value)
+}
+
+pub fn parse_fps(arg: &str) -> Result<f64> {
+    check(arg.parse().context("Not a number")?, FPS_RANGE)
+}
+
+pub fn parse_snapshot_interval(arg: &str) -> Result<f64> {
+    check(
+        arg.parse().context("Not a number")?,
+        SNAPSHOT_INTERVAL_RANGE,
+    )
+}
+
+/// A staleness threshold in seconds, where 0 turns the marking off.
+fn check_staleness_threshold(value: f64) -> Result<f64> {
+    if value == 0.0 {
+        return Ok(value);
+    }
+    check(value, STALENESS_THRESHOLD_RANGE).context("or 0 to turn it off")
+}
+
+pub fn parse_staleness_threshold(arg: &str) -> Result<f64> {
+    check_staleness_threshold(arg.parse().context("Not a number")?)
+}
+
+impl Settings {
+    /// Settings from the flags given, falling back to the config. The
+    /// flags are checked as they are parsed; the config is checked here.
+    pub fn new(
+        config: &Config,
+        max_fps: Option<f64>,
+        snapshot_interval: Option<f64>,
+        staleness_threshold: Option<f64>,
+    ) -> Result<Self> {
+        let max_fps = match max_fps {
+            Some(fps) => Some(fps),
+            None => config
+                .max_fps
+                .map(|fps| check(fps, FPS_RANGE).context("max_fps in the config"))
+                .transpose()?,
+        };
+        let snapshot_interval = match snapshot_interval {
+            Some(interval) => interval,
+            None => check(config.snapshot_interval, SNAPSHOT_INTERVAL_RANGE)
+                .context("snapshot_interval in the config")?,
+        };
+        let staleness_threshold = match staleness_threshold {
+            Some(threshold) => threshold,
+            None => check_staleness_threshold(config.stale_quote_secs as f64)
+                .context("stale_quote_secs in the config")?,
+        };
+        Ok(Self {
+            frame_interval: max_fps.map(|fps| Duration::from_secs_f64(1.0 / fps)),
+            unfocused_interval: UNFOCUSED_REDRAW_INTERVAL,
+            greeks_interval: GREEKS_REDRAW_INTERVAL,
+            snapshot_interval: Duration::from_secs_f64(snapshot_interval),
+            stale_after: (staleness_threshold > 0.0)
+                .then(|| Duration::from_secs_f64(staleness_threshold)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staleness_threshold_is_checked() {
+        assert_eq!(parse_staleness_threshold("0").unwrap(), 0.0);
+        assert_eq!(parse_staleness_threshold("45").unwrap(), 45.0);
+        for refused in ["0.5", "3601", "-1", "soon"] {
+            assert!(parse_staleness_threshold(refused).is_err(), "{}", refused);
+        }
+
+        let mut config = Config::default();
+        config.stale_quote_secs = 30;
+        config.snapshot_interval = 0.5;
+        let settings = Settings::new(&config, None, None, None).unwrap();
+        assert_eq!(settings.stale_after, Some(Duration::from_secs(30)));
+        let settings = Settings::new(&config, None, None, Some(0.0)).unwrap();
+        assert_eq!(settings.stale_after, None);
+        let settings = Settings::new(&config, None, None, Some(90.0)).unwrap();
+        assert_eq!(settings.stale_after, Some(Duration::from_secs(90)));
+        config.stale_quote_secs = 86400;
+        assert!(Settings::new(&config, None, None, None).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{config::Config, credentials};
+
+/// What the first-run setup asked for before logging in.
+pub struct Login {
+    pub login: String,
+    pub password: String,
+    /// Whether the password is to go in the keyring once it works.
+    pub save_password: bool,
+}
+
+/// Whether to run the setup: there is no config file, nothing else gives a
+/// login, and someone is at the terminal to answer.
+pub fn wanted(login_given: bool) -> bool {
+    !login_given && io::stdin().is_terminal() && Config::path().map_or(false, |path| !path.exists())
+}
+
+fn ask(question: &str) -> Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        bail!("Setup cancelled");
+    }
+    Ok(answer.trim().to_owned())
+}
+
+pub fn ask_login() -> Result<Login> {
+    println!("No config file yet; answer a few questions to create one.");
+    let login = loop {
+        let login = ask("tastytrade username or email: ")?;
+        if !login.is_empty() {
+            break login;
+        }
+    };
+    let password = credentials::prompt_password(&login)?;
+    let save_password =
+        ask("Save the password in the OS keyring? [y/N] ")?.eq_ignore_ascii_case("y");
+    Ok(Login {
+        login,
+        password,
+        save_password,
+    })
+}
+
+/// Asks which of `accounts` to show. An empty answer means all of them,
+/// which is saved as no list at all.
+pub fn ask_accounts(accounts: &[String]) -> Result<Vec<String>> {
+    if accounts.len() < 2 {
+        return Ok(vec![]);
+    }
+    for (i, account) in accounts.iter().enumerate() {
+        println!("  {}) {}", i + 1, account);
+    }
+    loop {
+        let answer = ask("Accounts to show, by number separated by spaces (Enter for all): ")?;
+        let picked: Option<Vec<String>> = answer
+            .split_whitespace()
+            .map(|n| {
+                let i = n.parse::<usize>().ok()?.checked_sub(1)?;
+                accounts.get(i).cloned()
+            })
+            .collect();
+        match picked {
+            Some(picked) if picked.len() < accounts.len() => return Ok(picked),
+            Some(_) => return Ok(vec![]),
+            None => println!("Pick from 1 to {}", accounts.len()),
+        }
+    }
+}
+
+/// Writes a config with the answers, keeping everything else at its
+/// default.
+pub fn write_config(login: &str, accounts: &[String]) -> Result<PathBuf> {
+    let path = Config::path().context("No config directory available")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Creating config directory")?;
+    }
+    let quoted: Vec<String> = accounts.iter().map(|a| format!("{:?}", a)).collect();
+    let contents = format!(
+        "# Written by the first-run setup. See the README for the other settings.\n\
+         login = {:?}\n\
+         accounts = [{}]\n",
+        login,
+        quoted.join(", ")
+    );
+    fs::write(&path, contents).with_context(|| format!("Writing {}", path.display()))?;
+    Ok(path)
+}
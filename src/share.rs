@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::api::{
+    order::{InstrumentType, Symbol},
+    position::QuantityDirection,
+    quote_streaming::DxFeedSymbol,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, watch},
+};
+
+use crate::{
+    fx, instrument::Deliverable, option_symbol::OptionSymbol, transactions::OpenReference,
+    AccountBalance, BidAsk, PriceRecord, RecordKey, SimpleGreeks, UnderlyingQuote,
+};
+
+/// A socket address given as `unix:<path>`.
+#[derive(Debug, Clone)]
+pub enum Address {
+    Unix(PathBuf),
+}
+
+impl FromStr for Address {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("unix", path)) if !path.is_empty() => Ok(Address::Unix(path.into())),
+            _ => bail!("expected an address like unix:/tmp/tasty.sock"),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Everything a viewer needs to render the table.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub groups: Vec<GroupSnapshot>,
+    pub expired: Vec<RecordSnapshot>,
+    #[serde(default)]
+    pub watchlist: Vec<WatchSnapshot>,
+    pub balances: BTreeMap<String, AccountBalance>,
+    #[serde(default)]
+    pub premium: BTreeMap<String, Decimal>,
+    #[serde(default)]
+    pub opened: BTreeMap<String, OpenReference>,
+    pub notes: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupSnapshot {
+    pub underlying: String,
+    pub underlying_quote: Option<UnderlyingSnapshot>,
+    pub records: Vec<RecordSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WatchSnapshot {
+    pub symbol: String,
+    pub label: Option<String>,
+    pub quote: UnderlyingSnapshot,
+    #[serde(default)]
+    pub bid: Option<Decimal>,
+    #[serde(default)]
+    pub ask: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnderlyingSnapshot {
+    pub symbol: String,
+    pub price: Option<Decimal>,
+    pub prev_close: Option<Decimal>,
+    #[serde(default)]
+    pub halted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordSnapshot {
+    pub stream_symbol: String,
+    pub account: String,
+    pub symbol: String,
+    pub instrument_type: InstrumentType,
+    pub open: Decimal,
+    pub current: Decimal,
+    pub amount: Decimal,
+    pub multiplier: Decimal,
+    pub short: bool,
+    pub theta: f64,
+    pub delta: f64,
+    #[serde(default)]
+    pub gamma: f64,
+    #[serde(default)]
+    pub vega: f64,
+    #[serde(default)]
+    pub rho: f64,
+    #[serde(default)]
+    pub volatility: Option<f64>,
+    #[serde(default)]
+    pub prev_close: Option<Decimal>,
+    /// Seconds since the stream last updated the position.
+    #[serde(default)]
+    pub updated_secs_ago: Option<f64>,
+    pub quote: Option<QuoteSnapshot>,
+    pub deliverables: Option<Vec<Deliverable>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteSnapshot {
+    pub bid: Decimal,
+    pub bid_size: f64,
+    pub ask: Decimal,
+    pub ask_size: f64,
+    #[serde(default)]
+    pub delayed: bool,
+}
+
+impl From<&UnderlyingQuote> for UnderlyingSnapshot {
+    fn from(quote: &UnderlyingQuote) -> Self {
+        Self {
+            symbol: quote.symbol.0.clone(),
+            price: quote.price,
+            prev_close: quote.prev_close,
+            halted: quote.halted,
+        }
+    }
+}
+
+impl From<UnderlyingSnapshot> for UnderlyingQuote {
+    fn from(snapshot: UnderlyingSnapshot) -> Self {
+        Self {
+            symbol: DxFeedSymbol(snapshot.symbol),
+            price: snapshot.price,
+            prev_close: snapshot.prev_close,
+            halted: snapshot.halted,
+        }
+    }
+}
+
+impl From<(&RecordKey, &PriceRecord)> for RecordSnapshot {
+    fn from((key, rec): (&RecordKey, &PriceRecord)) -> Self {
+        Self {
+            stream_symbol: key.stream_symbol.0.clone(),
+            account: rec.account.clone(),
+            symbol: rec.symbol.0.clone(),
+            instrument_type: rec.instrument_type.clone(),
+            open: rec.open,
+            current: rec.current,
+            amount: rec.amount,
+            multiplier: rec.multiplier,
+            short: matches!(rec.direction, QuantityDirection::Short),
+            theta: rec.greeks.theta,
+            delta: rec.greeks.delta,
+            gamma: rec.greeks.gamma,
+            vega: rec.greeks.vega,
+            rho: rec.greeks.rho,
+            volatility: rec.greeks.volatility,
+            prev_close: rec.prev_close,
+            updated_secs_ago: rec.updated.map(|updated| updated.elapsed().as_secs_f64()),
+            quote: rec.quote.as_ref().map(|q| QuoteSnapshot {
+                bid: q.bid,
+                bid_size: q.bid_size,
+                ask: q.ask,
+                ask_size: q.ask_size,
+                delayed: q.delayed,
+            }),
+            deliverables: rec.deliverables.clone(),
+        }
+    }
+}
+
+impl RecordSnapshot {
+    pub fn into_record(self) -> (DxFeedSymbol, PriceRecord) {
+        let record = PriceRecord {
+            account: self.account,
+            option: OptionSymbol::parse(&self.symbol),
+            symbol: Symbol(self.symbol),
+            instrument_type: self.instrument_type,
+            open: self.open,
+            current: self.current,
+            amount: self.amount,
+            multiplier: self.multiplier,
+            direction: if self.short {
+                QuantityDirection::Short
+            } else {
+                QuantityDirection::Long
+            },
+            greeks: SimpleGreeks {
+                theta: self.theta,
+                delta: self.delta,
+                gamma: self.gamma,
+                vega: self.vega,
+                rho: self.rho,
+                volatility: self.volatility,
+            },
+            quote: self.quote.map(|q| BidAsk {
+                bid: q.bid,
+                bid_size: q.bid_size,
+                ask: q.ask,
+                ask_size: q.ask_size,
+                delayed: q.delayed,
+            }),
+            deliverables: self.deliverables,
+            currency: fx::HOME.to_owned(),
+            fx_rate: Some(Decimal::ONE),
+            prev_close: self.prev_close,
+            updated: self
+                .updated_secs_ago
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .and_then(|secs| Instant::now().checked_sub(Duration::from_secs_f64(secs))),
+        };
+        (DxFeedSymbol(self.stream_symbol), record)
+    }
+}
+
+/// Starts accepting viewers on `address`. Each one is sent the latest
+/// snapshot as a line of JSON whenever it changes; nothing is read back.
+pub fn serve(address: &Address, initial: Snapshot) -> Result<watch::Sender<Snapshot>> {
+    let Address::Unix(path) = address;
+    // A socket left behind by a previous run would make the bind fail.
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path).with_context(|| format!("Listening on {}", address))?;
+    let (tx, rx) = watch::channel(initial);
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            };
+            let rx = rx.clone();
+            tokio::spawn(async move {
+                // The viewer going away is the only way this ends.
+                let _ = send_snapshots(stream, rx).await;
+            });
+        }
+    });
+    Ok(tx)
+}
+
+async fn send_snapshots(mut stream: UnixStream, mut rx: watch::Receiver<Snapshot>) -> Result<()> {
+    loop {
+        let mut line = serde_json::to_string(&*rx.borrow_and_update())?;
+        line.push('\n');
+        stream.write_all(line.as_bytes()).await?;
+        rx.changed().await?;
+    }
+}
+
+/// Connects to a serving process and yields the snapshots it sends. The
+/// channel ends after the first error.
+pub async fn attach(address: &Address) -> Result<mpsc::UnboundedReceiver<Result<Snapshot>>> {
+    let Address::Unix(path) = address;
+    let stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("Connecting to {}", address))?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        loop {
+            let snapshot = match lines.next_line().await {
+                Ok(Some(line)) => serde_json::from_str(&line).context("Reading snapshot"),
+                Ok(None) => Err(anyhow!("Server closed the connection")),
+                Err(e) => Err(e.into()),
+            };
+            let failed = snapshot.is_err();
+            if tx.send(snapshot).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::api::order::Symbol;
+
+/// How long a note outlives the position it was written for.
+const NOTE_RETENTION_DAYS: i64 = 7;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    /// First time the position was found missing from the account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_since: Option<DateTime<Utc>>,
+}
+
+/// A line of the table, by what it shows rather than where.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SavedLine {
+    Group(String),
+    /// A group's line for an expiration, while nested.
+    Expiration(String, NaiveDate),
+    /// A position, by underlying and streamer symbol.
+    Position(String, String),
+    Expired,
+    ExpiredPosition(String),
+    Watchlist,
+    /// A watched symbol.
+    Watch(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct View {
+    /// Underlyings whose groups were expanded.
+    #[serde(default)]
+    pub open_groups: BTreeSet<String>,
+    #[serde(default)]
+    pub selected: Option<SavedLine>,
+}
+
+/// Everything the app remembers between runs, stored as JSON in the user's
+/// data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LocalState {
+    #[serde(default)]
+    pub notes: BTreeMap<String, Note>,
+    /// How the table was left, to be set up the same way next time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<View>,
+    /// The profile the state belongs to, which decides where it is saved.
+    #[serde(skip)]
+    profile: Option<String>,
+}
+
+/// The app's data directory. Each profile gets its own under it so that
+/// nothing is shared between logins.
+pub fn data_dir(profile: Option<&str>) -> Option<PathBuf> {
+    let dir = dirs::data_dir()?.join("tastytrade-cli");
+    Some(match profile {
+        Some(profile) => dir.join("profiles").join(profile),
+        None => dir,
+    })
+}
+
+pub fn note_key(account: &str, symbol: &Symbol) -> String {
+    format!("{}:{}", account, symbol.0)
+}
+
+impl LocalState {
+    fn path(profile: Option<&str>) -> Option<PathBuf> {
+        data_dir(profile).map(|dir| dir.join("state.json"))
+    }
+
+    /// Loads the state file of `profile`, starting fresh if it is missing or
+    /// unreadable.
+    pub fn load(profile: Option<&str>) -> Self {
+        let state: Self = Self::path(profile)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            profile: profile.map(str::to_owned),
+            ..state
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path(self.profile.as_deref()).context("No data directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Creating data directory")?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Writing {}", path.display()))
+    }
+
+    pub fn note(&self, key: &str) -> Option<&str> {
+        self.notes.get(key).map(|note| note.text.as_str())
+    }
+
+    /// Sets the note for a position, removing it when `text` is empty.
+    pub fn set_note(&mut self, key: String, text: &str) {
+        if text.is_empty() {
+            self.notes.remove(&key);
+        } else {
+            self.notes.entry(key).or_default().text = text.to_owned();
+        }
+    }
+
+    /// Marks notes whose position is gone as closed and drops the ones that
+    /// have been closed for longer than the retention period.
+    pub fn prune_notes(&mut self, open_keys: &BTreeSet<String>) {
+        let now = Utc::now();
+        self.notes.retain(|key, note| {
+            if open_keys.contains(key) {
+                note.closed_since = None;
+                return true;
+            }
+            let closed_since = *note.closed_since.get_or_insert(now);
+            now - closed_since < Duration::days(NOTE_RETENTION_DAYS)
+        });
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::option_symbol::{OptionKind, OptionSymbol};
+
+/// A position as the account streamer sent it. Quantities here are signed,
+/// short negative.
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Tells the change the message reports from any other. The streamer
+    /// repeats messages in the bursts around expiration, and a repeat has
+    /// the same id.
+    pub id: String,
+    pub account: String,
+    pub symbol: String,
+    pub underlying: String,
+    pub quantity: Decimal,
+    pub open: Decimal,
+}
+
+/// Whether an option expiring on `expiration` may still be settling
+/// `today`: assignments come in on the evening it expires and over the
+/// weekend after.
+pub fn settling(expiration: NaiveDate, today: NaiveDate) -> bool {
+    (0..=3).contains(&(today - expiration).num_days())
+}
+
+/// An option position that may still be settling, which shares arriving
+/// now may have been assigned or exercised from.
+#[derive(Debug, Clone)]
+pub struct Expiring {
+    pub account: String,
+    pub symbol: String,
+    pub underlying: String,
+    pub option: OptionSymbol,
+    pub quantity: Decimal,
+    /// Shares a contract delivers.
+    pub multiplier: Decimal,
+}
+
+impl Expiring {
+    /// The shares one contract brings into the account when it is
+    /// assigned or exercised: short puts and long calls buy them, short
+    /// calls and long puts sell them.
+    fn delivers(&self) -> Decimal {
+        let side = match self.option.kind {
+            OptionKind::Call => Decimal::ONE,
+            OptionKind::Put => -Decimal::ONE,
+        };
+        self.quantity.signum() * side * self.multiplier
+    }
+}
+
+/// What a message does to the position it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// It was already applied.
+    Repeated,
+    Close,
+    Update {
+        quantity: Decimal,
+        open: Decimal,
+    },
+    /// A position the table doesn't have yet. `open` is the strike when
+    /// the shares came from an assignment or exercise; otherwise the
+    /// position is left to the next download.
+    Open {
+        quantity: Decimal,
+        open: Option<Decimal>,
+    },
+}
+
+/// Applies the account streamer's position messages once each, and prices
+/// shares that arrive by assignment at the strike.
+#[derive(Debug, Default)]
+pub struct Ledger {
+    applied: BTreeSet<String>,
+    /// Options closed out on their expiration day before the shares they
+    /// deliver have arrived.
+    closed: Vec<Expiring>,
+    /// Contracts of each option, by account and symbol, already matched to
+    /// the shares they delivered.
+    delivered: BTreeMap<(String, String), Decimal>,
+}
+
+impl Ledger {
+    /// What `message` does to the position `held`, as its quantity and open
+    /// price, if the table has it. `expiring` are the options the table
+    /// still holds that are `settling` `today`. The streamer can send the shares of
+    /// an assignment before or after the option closes, so both are
+    /// matched: options already closed today and ones still held.
+    pub fn apply(
+        &mut self,
+        message: &Message,
+        held: Option<(Decimal, Decimal)>,
+        expiring: &[Expiring],
+        today: NaiveDate,
+    ) -> Outcome {
+        if !self.applied.insert(message.id.clone()) {
+            return Outcome::Repeated;
+        }
+        let (before, held_open) = held.unwrap_or_default();
+        let quantity = message.quantity;
+        let mut open = message.open;
+        match OptionSymbol::parse(&message.symbol) {
+            Some(option) => {
+                let closed = before - quantity;
+                if settling(option.expiration, today)
+                    && !closed.is_zero()
+                    && closed.signum() == before.signum()
+                {
+                    // Contracts already matched to shares close first.
+                    let key = (message.account.clone(), message.symbol.clone());
+                    let delivered = self.delivered.entry(key).or_default();
+                    let matched = (*delivered).min(closed.abs());
+                    *delivered -= matched;
+                    let contracts = closed.abs() - matched;
+                    if contracts > Decimal::ZERO {
+                        let multiplier = expiring
+                            .iter()
+                            .find(|held| {
+                                held.account == message.account && held.symbol == message.symbol
+                            })
+                            .map_or(Decimal::ONE_HUNDRED, |held| held.multiplier);
+                        self.closed.push(Expiring {
+                            account: message.account.clone(),
+                            symbol: message.symbol.clone(),
+                            underlying: message.underlying.clone(),
+                            option,
+                            quantity: contracts * before.signum(),
+                            multiplier,
+                        });
+                    }
+                }
+            }
+            None => {
+                let change = quantity - before;
+                if let Some(price) = self.assigned_price(message, change, expiring, today) {
+                    open = if before.is_zero() || before.signum() == -quantity.signum() {
+                        // New, or turned from short to long or back.
+                        price
+                    } else if change.signum() == before.signum() {
+                        (before.abs() * held_open + change.abs() * price) / quantity.abs()
+                    } else {
+                        // Shares delivered against ones held keep their price.
+                        held_open
+                    };
+                    if held.is_none() {
+                        return Outcome::Open {
+                            quantity,
+                            open: Some(open.round_dp(2)),
+                        };
+                    }
+                }
+            }
+        }
+        match held {
+            _ if quantity.is_zero() => Outcome::Close,
+            Some(_) => Outcome::Update {
+                quantity,
+                open: open.round_dp(2),
+            },
+            None => Outcome::Open {
+                quantity,
+                open: None,
+            },
+        }
+    }
+
+    /// The price shares changing by `change` came in at, when all of them
+    /// are matched to options of the same account and underlying assigned
+    /// or exercised lately: the strikes, weighted by the shares each
+    /// delivered. Options already closed are matched first.
+    fn assigned_price(
+        &mut self,
+        message: &Message,
+        change: Decimal,
+        expiring: &[Expiring],
+        today: NaiveDate,
+    ) -> Option<Decimal> {
+        if change.is_zero() {
+            return None;
+        }
+        let matches = |option: &Expiring| {
+            option.account == message.account
+                && option.underlying == message.underlying
+                && settling(option.option.expiration, today)
+                && option.delivers().signum() == change.signum()
+        };
+        let mut left = change.abs();
+        let mut cost = Decimal::ZERO;
+        let mut take = |option: &Expiring, available: Decimal| {
+            let per_contract = option.delivers().abs();
+            let contracts = available.min((left / per_contract).floor());
+            if contracts <= Decimal::ZERO {
+                return Decimal::ZERO;
+            }
+            left -= contracts * per_contract;
+            cost += contracts * per_contract * option.option.strike;
+            contracts
+        };
+        let mut from_closed = vec![];
+        for (i, option) in self.closed.iter().enumerate() {
+            if matches(option) {
+                from_closed.push((i, take(option, option.quantity.abs())));
+            }
+        }
+        let mut from_held = vec![];
+        for option in expiring.iter().filter(|option| matches(option)) {
+            let key = (option.account.clone(), option.symbol.clone());
+            let delivered = self.delivered.get(&key).copied().unwrap_or_default();
+            from_held.push((key, take(option, option.quantity.abs() - delivered)));
+        }
+        if !left.is_zero() {
+            return None;
+        }
+        for (i, contracts) in from_closed {
+            let option = &mut self.closed[i];
+            option.quantity -= contracts * option.quantity.signum();
+        }
+        self.closed.retain(|option| !option.quantity.is_zero());
+        for (key, contracts) in from_held {
+            *self.delivered.entry(key).or_default() += contracts;
+        }
+        Some(cost / change.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn message(
+        id: &str,
+        account: &str,
+        symbol: &str,
+        underlying: &str,
+        quantity: &str,
+        open: &str,
+    ) -> Message {
+        Message {
+            id: id.to_owned(),
+            account: account.to_owned(),
+            symbol: symbol.to_owned(),
+            underlying: underlying.to_owned(),
+            quantity: d(quantity),
+            open: d(open),
+        }
+    }
+
+    /// A table of positions by account and symbol, as quantity and open
+    /// price, with an underlying for each.
+    type Book = BTreeMap<(String, String), (String, Decimal, Decimal)>;
+
+    /// Plays `messages` into `book` the way the app applies them.
+    fn replay(book: &mut Book, messages: &[Message], today: NaiveDate) {
+        let mut ledger = Ledger::default();
+        for message in messages {
+            let key = (message.account.clone(), message.symbol.clone());
+            let expiring: Vec<Expiring> = book
+                .iter()
+                .filter_map(|((account, symbol), (underlying, quantity, _))| {
+                    let option = OptionSymbol::parse(symbol)
+                        .filter(|option| settling(option.expiration, today))?;
+                    Some(Expiring {
+                        account: account.clone(),
+                        symbol: symbol.clone(),
+                        underlying: underlying.clone(),
+                        option,
+                        quantity: *quantity,
+                        multiplier: d("100"),
+                    })
+                })
+                .collect();
+            let held = book.get(&key).map(|(_, quantity, open)| (*quantity, *open));
+            match ledger.apply(message, held, &expiring, today) {
+                Outcome::Repeated => {}
+                Outcome::Close => {
+                    book.remove(&key);
+                }
+                Outcome::Update { quantity, open }
+                | Outcome::Open {
+                    quantity,
+                    open: Some(open),
+                } => {
+                    book.insert(key, (message.underlying.clone(), quantity, open));
+                }
+                // The download the app starts would bring it in.
+                Outcome::Open {
+                    quantity,
+                    open: None,
+                } => {
+                    book.insert(key, (message.underlying.clone(), quantity, message.open));
+                }
+            }
+        }
+    }
+
+    fn book(positions: &[(&str, &str, &str, &str, &str)]) -> Book {
+        positions
+            .iter()
+            .map(|&(account, symbol, underlying, quantity, open)| {
+                (
+                    (account.to_owned(), symbol.to_owned()),
+                    (underlying.to_owned(), d(quantity), d(open)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn friday_evening_burst_matches_the_next_mornings_positions() {
+        let friday = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        let put = "SPY   240119P00470000";
+        let call = "AAPL  240119C00190000";
+        let worthless = "QQQ   240119P00400000";
+        let later = "SPY   240216P00450000";
+        let mut positions = book(&[
+            ("5WT1", put, "SPY", "-2", "3.10"),
+            ("5WT1", call, "AAPL", "-1", "1.25"),
+            ("5WT1", "AAPL", "AAPL", "100", "150.00"),
+            ("5WT1", worthless, "QQQ", "-1", "0.80"),
+            ("5WT1", later, "SPY", "-1", "4.00"),
+            // Another account with the same contract isn't touched.
+            ("5WT2", put, "SPY", "-1", "3.00"),
+        ]);
+        let burst = [
+            // Shares of the put assignment before the put closes.
+            message("1", "5WT1", "SPY", "SPY", "200", "0"),
+            message("2", "5WT1", put, "SPY", "0", "0"),
+            // Repeated in the burst.
+            message("2", "5WT1", put, "SPY", "0", "0"),
+            message("1", "5WT1", "SPY", "SPY", "200", "0"),
+            // The call closes before the shares called away leave.
+            message("3", "5WT1", call, "AAPL", "0", "0"),
+            message("4", "5WT1", "AAPL", "AAPL", "0", "0"),
+            message("4", "5WT1", "AAPL", "AAPL", "0", "0"),
+            message("5", "5WT1", worthless, "QQQ", "0", "0"),
+            message("5", "5WT1", worthless, "QQQ", "0", "0"),
+        ];
+        replay(&mut positions, &burst, friday);
+
+        let morning = book(&[
+            ("5WT1", "SPY", "SPY", "200", "470"),
+            ("5WT1", later, "SPY", "-1", "4.00"),
+            ("5WT2", put, "SPY", "-1", "3.00"),
+        ]);
+        assert_eq!(positions, morning);
+    }
+
+    #[test]
+    fn assigned_shares_join_those_held_at_the_strike() {
+        let friday = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        let put = "SPY   240119P00470000";
+        let mut positions = book(&[
+            ("5WT1", put, "SPY", "-1", "3.10"),
+            ("5WT1", "SPY", "SPY", "100", "480.00"),
+        ]);
+        replay(
+            &mut positions,
+            &[
+                message("1", "5WT1", put, "SPY", "0", "0"),
+                message("2", "5WT1", "SPY", "SPY", "200", "480.00"),
+            ],
+            friday,
+        );
+        assert_eq!(positions, book(&[("5WT1", "SPY", "SPY", "200", "475")]));
+    }
+
+    #[test]
+    fn shares_not_matched_to_an_option_keep_the_streamed_price() {
+        let friday = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        let mut ledger = Ledger::default();
+        let outcome = ledger.apply(
+            &message("1", "5WT1", "SPY", "SPY", "50", "471.20"),
+            Some((d("0"), d("0"))),
+            &[],
+            friday,
+        );
+        assert_eq!(
+            outcome,
+            Outcome::Update {
+                quantity: d("50"),
+                open: d("471.20")
+            }
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+/// How long the last message of each type is kept, in characters.
+const SAMPLE_LENGTH: usize = 200;
+
+/// What has been seen of one type of account stream message.
+pub struct Seen {
+    pub count: usize,
+    /// Whether the app does anything with messages of this type.
+    pub handled: bool,
+    pub last_at: DateTime<Local>,
+    /// The start of the last one, as debug output.
+    pub sample: String,
+}
+
+/// One stream's recent traffic, for the connection bar. Counting an event
+/// only touches the clock, so it can be done for every one.
+pub struct Traffic {
+    last: Option<Instant>,
+    /// The last error, until the stream delivers again.
+    pub error: Option<String>,
+    second_started: Instant,
+    this_second: usize,
+    last_second: usize,
+}
+
+impl Traffic {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            error: None,
+            second_started: Instant::now(),
+            this_second: 0,
+            last_second: 0,
+        }
+    }
+
+    pub fn event(&mut self) {
+        let now = Instant::now();
+        self.roll(now);
+        self.this_second += 1;
+        self.last = Some(now);
+        self.error = None;
+    }
+
+    pub fn error(&mut self, error: String) {
+        self.roll(Instant::now());
+        self.error = Some(error);
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.second_started);
+        if elapsed >= Duration::from_secs(1) {
+            self.last_second = if elapsed < Duration::from_secs(2) {
+                self.this_second
+            } else {
+                0
+            };
+            self.this_second = 0;
+            self.second_started = now;
+        }
+    }
+
+    /// Events over the last whole second.
+    pub fn per_second(&self) -> usize {
+        match self.second_started.elapsed().as_secs() {
+            0 => self.last_second,
+            1 => self.this_second,
+            _ => 0,
+        }
+    }
+
+    /// The wall clock time of the last event, if there has been one.
+    pub fn last_at(&self) -> Option<DateTime<Local>> {
+        let ago = chrono::Duration::from_std(self.last?.elapsed()).ok()?;
+        Some(Local::now() - ago)
+    }
+}
+
+/// Counts the account streamer's messages by type, so that ones the app
+/// ignores, and ones it couldn't parse, can be seen arriving. Quote feed
+/// events and redraws are counted alongside to show their rates.
+pub struct StreamStats {
+    pub seen: BTreeMap<String, Seen>,
+    pub unparsed: usize,
+    pub last_error: Option<String>,
+    pub started: Instant,
+    /// Quote feed events by kind.
+    pub feed: BTreeMap<&'static str, usize>,
+    pub draws: usize,
+    /// Greeks updates applied without asking for a redraw of their own.
+    pub greeks_held: usize,
+    pub quote_traffic: Traffic,
+    pub account_traffic: Traffic,
+}
+
+impl StreamStats {
+    pub fn new() -> Self {
+        Self {
+            seen: BTreeMap::new(),
+            unparsed: 0,
+            last_error: None,
+            started: Instant::now(),
+            feed: BTreeMap::new(),
+            draws: 0,
+            greeks_held: 0,
+            quote_traffic: Traffic::new(),
+            account_traffic: Traffic::new(),
+        }
+    }
+
+    pub fn count_feed(&mut self, kind: &'static str) {
+        *self.feed.entry(kind).or_default() += 1;
+    }
+
+    /// Average rate of `count` per second since the app started.
+    pub fn rate(&self, count: usize) -> f64 {
+        count as f64 / self.started.elapsed().as_secs_f64().max(1.0)
+    }
+
+    /// Counts a message from its debug output, which starts with the name of
+    /// its type.
+    pub fn record(&mut self, debug: String, handled: bool) {
+        self.account_traffic.event();
+        let name = debug
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        let sample: String = debug.chars().take(SAMPLE_LENGTH).collect();
+        let seen = self.seen.entry(name).or_insert_with(|| Seen {
+            count: 0,
+            handled,
+            last_at: Local::now(),
+            sample: String::new(),
+        });
+        seen.count += 1;
+        seen.last_at = Local::now();
+        seen.sample = sample;
+    }
+
+    pub fn record_error(&mut self, error: String) {
+        self.unparsed += 1;
+        self.account_traffic.error(error.clone());
+        self.last_error = Some(error);
+    }
+
+    pub fn ignored(&self) -> usize {
+        self.seen
+            .values()
+            .filter(|seen| !seen.handled)
+            .map(|seen| seen.count)
+            .sum()
+    }
+}
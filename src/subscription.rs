@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use tastytrade_rs::api::quote_streaming::{DxFeedSymbol, QuoteSubscription};
+
+/// Which symbols keep their subscription when there are more than the feed
+/// allows. Lower variants win.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Position,
+    Underlying,
+    /// Contracts shown in the option chain view.
+    Chain,
+    Watchlist,
+}
+
+/// Tracks the symbols we want quotes for against the ones actually
+/// subscribed, keeping the subscription count under a limit.
+pub struct Subscriptions {
+    limit: usize,
+    /// Every reason each symbol is wanted for. The first one is what it
+    /// ranks by.
+    wanted: BTreeMap<DxFeedSymbol, BTreeSet<Priority>>,
+    subscribed: BTreeSet<DxFeedSymbol>,
+}
+
+impl Subscriptions {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            wanted: BTreeMap::new(),
+            subscribed: BTreeSet::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Asks for quotes on `symbol`. A symbol wanted for several reasons keeps
+    /// its most important priority.
+    pub fn want(&mut self, symbol: DxFeedSymbol, priority: Priority) {
+        self.wanted.entry(symbol).or_default().insert(priority);
+    }
+
+    /// Withdraws one reason for wanting `symbol`. Once none are left it is
+    /// unsubscribed on the next sync.
+    pub fn unwant(&mut self, symbol: &DxFeedSymbol, priority: Priority) {
+        if let Some(priorities) = self.wanted.get_mut(symbol) {
+            priorities.remove(&priority);
+            if priorities.is_empty() {
+                self.wanted.remove(symbol);
+            }
+        }
+    }
+
+    pub fn is_dropped(&self, symbol: &DxFeedSymbol) -> bool {
+        self.wanted.contains_key(symbol) && !self.subscribed.contains(symbol)
+    }
+
+    pub fn subscribed_count(&self) -> usize {
+        self.subscribed.len()
+    }
+
+    /// Symbols wanted but left unsubscribed. Ones unwanted since the last
+    /// sync are still subscribed, so they don't count against this.
+    pub fn dropped_count(&self) -> usize {
+        self.wanted
+            .keys()
+            .filter(|symbol| !self.subscribed.contains(*symbol))
+            .count()
+    }
+
+    /// Drops `symbol` from the feed and adds it straight back, for when its
+    /// stream has stopped. Returns whether it was subscribed to begin with.
+    pub fn resubscribe(&mut self, symbol: &DxFeedSymbol, sub: &QuoteSubscription) -> bool {
+        if !self.subscribed.contains(symbol) {
+            return false;
+        }
+        let symbols = [symbol.clone()];
+        sub.remove_symbols(&symbols);
+        sub.add_symbols(&symbols);
+        true
+    }
+
+    /// Adds and removes symbols on the feed so that the highest priority
+    /// wanted symbols, up to the limit, are the ones subscribed.
+    pub fn sync(&mut self, sub: &QuoteSubscription) {
+        let mut ranked: Vec<_> = self.wanted.iter().collect();
+        ranked.sort_by_key(|(_, priorities)| priorities.first().copied());
+        let keep: BTreeSet<DxFeedSymbol> = ranked
+            .into_iter()
+            .take(self.limit)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+
+        let added: Vec<_> = keep.difference(&self.subscribed).cloned().collect();
+        let removed: Vec<_> = self.subscribed.difference(&keep).cloned().collect();
+        if !removed.is_empty() {
+            sub.remove_symbols(&removed);
+        }
+        if !added.is_empty() {
+            sub.add_symbols(&added);
+        }
+        self.subscribed = keep;
+    }
+}
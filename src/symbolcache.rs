@@ -0,0 +1,113 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::{
+    api::{
+        order::{InstrumentType, Symbol},
+        quote_streaming::DxFeedSymbol,
+    },
+    TastyTrade,
+};
+
+use crate::state;
+
+/// How long a resolved streamer symbol is trusted.
+const TTL_DAYS: i64 = 7;
+
+/// Most lookups in flight at once.
+const CONCURRENCY: usize = 8;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Entry {
+    stream_symbol: String,
+    resolved_at: DateTime<Utc>,
+}
+
+/// The cache is shared by all profiles, since symbols are the same for
+/// everyone.
+fn path() -> Option<PathBuf> {
+    state::data_dir(None).map(|dir| dir.join("streamer-symbols.json"))
+}
+
+/// The saved entries, or none when the file is missing or can't be read.
+fn load() -> BTreeMap<String, Entry> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the entries. Failing only costs the lookups next time, so errors
+/// are ignored.
+fn save(entries: &BTreeMap<String, Entry>) {
+    let path = match path() {
+        Some(path) => path,
+        None => return,
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(contents) = serde_json::to_string(entries) {
+        let _ = fs::write(path, contents);
+    }
+}
+
+fn key(instrument_type: &InstrumentType, symbol: &Symbol) -> String {
+    format!("{:?} {}", instrument_type, symbol.0)
+}
+
+/// The streamer symbols of `symbols`, in the same order. Ones resolved in
+/// the last week come from the cache unless `fresh` is set; the rest are
+/// looked up a few at a time and saved for next time.
+pub async fn resolve(
+    tasty: &TastyTrade,
+    symbols: &[(InstrumentType, Symbol)],
+    fresh: bool,
+) -> Vec<Result<DxFeedSymbol>> {
+    let mut cache = load();
+    let now = Utc::now();
+    let keys: Vec<String> = symbols
+        .iter()
+        .map(|(instrument_type, symbol)| key(instrument_type, symbol))
+        .collect();
+    let mut results: Vec<Option<Result<DxFeedSymbol>>> = keys
+        .iter()
+        .map(|key| {
+            let entry = cache.get(key).filter(|_| !fresh)?;
+            let current = now - entry.resolved_at < Duration::days(TTL_DAYS);
+            current.then(|| Ok(DxFeedSymbol(entry.stream_symbol.clone())))
+        })
+        .collect();
+
+    let missing: Vec<usize> = (0..symbols.len())
+        .filter(|i| results[*i].is_none())
+        .collect();
+    let lookups: Vec<_> = stream::iter(missing.iter().map(|i| {
+        let (instrument_type, symbol) = &symbols[*i];
+        tasty.get_streamer_symbol(instrument_type, symbol)
+    }))
+    .buffered(CONCURRENCY)
+    .collect()
+    .await;
+    for (i, result) in missing.iter().zip(lookups) {
+        if let Ok(stream_symbol) = &result {
+            cache.insert(
+                keys[*i].clone(),
+                Entry {
+                    stream_symbol: stream_symbol.0.clone(),
+                    resolved_at: now,
+                },
+            );
+        }
+        results[*i] = Some(result.map_err(anyhow::Error::from));
+    }
+    if !missing.is_empty() {
+        save(&cache);
+    }
+
+    results.into_iter().flatten().collect()
+}
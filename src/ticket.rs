@@ -0,0 +1,694 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyCode;
+use rust_decimal::Decimal;
+use tastytrade_rs::{
+    api::{
+        account::Account,
+        order::{
+            Action, InstrumentType, Order, OrderBuilder, OrderLegBuilder, OrderType, PriceEffect,
+            Symbol, TimeInForce,
+        },
+    },
+    TastyError,
+};
+
+use crate::{
+    chain::Contract,
+    confirm::{Answer, Confirmation, DryRun},
+    option_symbol::{OptionKind, OptionSymbol},
+    orders,
+    permissions::{self, Permission},
+    readonly,
+    tradelog::{OrderAction, OrderDetails, TradeLog},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Quantity,
+    Price,
+}
+
+/// Where an order ticket is between being opened and being sent.
+#[derive(Debug, Clone)]
+pub enum Stage {
+    Editing,
+    /// Sending is confirmed for exactly the quantity and price that were
+    /// dry run.
+    Confirming {
+        quantity: Decimal,
+        price: Decimal,
+        confirmation: Confirmation,
+    },
+    Sending,
+}
+
+/// What a key pressed on the ticket asks of the event loop.
+pub enum Request {
+    DryRun(Order),
+    Send(Order, OrderDetails),
+}
+
+/// One leg of the order a ticket writes.
+pub struct Leg {
+    pub symbol: Symbol,
+    /// How the leg is shown on the ticket.
+    pub name: String,
+    instrument_type: InstrumentType,
+    pub action: Action,
+    /// Contracts, or shares, of the leg in each one ordered.
+    ratio: Decimal,
+}
+
+impl Leg {
+    /// A leg closing `ratio` of a position for each one ordered.
+    pub fn closing(
+        symbol: Symbol,
+        name: &str,
+        instrument_type: InstrumentType,
+        short: bool,
+        ratio: Decimal,
+    ) -> Self {
+        Self {
+            symbol,
+            name: name.trim().to_owned(),
+            instrument_type,
+            action: if short {
+                Action::BuyToClose
+            } else {
+                Action::SellToClose
+            },
+            ratio,
+        }
+    }
+}
+
+/// A short equity option about to be rolled, kept while the chain it is
+/// rolled into is picked from.
+#[derive(Clone)]
+pub struct Roll {
+    pub account: String,
+    pub symbol: Symbol,
+    pub name: String,
+    pub option: OptionSymbol,
+    /// Contracts short.
+    pub held: Decimal,
+    pub mid: Decimal,
+}
+
+/// A limit order edited before it is sent: closing one position, rolling a
+/// short option out to another contract, or opening an option position.
+pub struct Ticket {
+    pub account: String,
+    /// What the ticket does, like `CLOSE`.
+    pub title: &'static str,
+    /// How the position is shown in the table.
+    pub name: String,
+    pub legs: Vec<Leg>,
+    price_effect: PriceEffect,
+    /// The whole position, for orders on one; the order may close less.
+    pub held: Option<Decimal>,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub field: Field,
+    /// Digits typed into the selected field, replacing it on Enter or Tab.
+    pub typed: Option<String>,
+    pub stage: Stage,
+    /// Shown on the ticket under the order, like warnings about it.
+    pub notes: Vec<String>,
+    /// What the account may trade and the shares of the underlying it
+    /// holds, checked before the dry run. Unknown leaves it to the dry run.
+    permission: Option<(Permission, Decimal)>,
+}
+
+impl Ticket {
+    /// A ticket closing all of a position at `mid`. Only equities and
+    /// equity options can be closed from here.
+    pub fn closing(
+        account: String,
+        symbol: Symbol,
+        name: String,
+        instrument_type: InstrumentType,
+        held: Decimal,
+        short: bool,
+        mid: Decimal,
+    ) -> Result<Self> {
+        if !matches!(
+            instrument_type,
+            InstrumentType::Equity | InstrumentType::EquityOption
+        ) {
+            bail!("Only shares and equity options can be closed from here");
+        }
+        let leg = Leg::closing(symbol, &name, instrument_type, short, Decimal::ONE);
+        Ok(Self::new(
+            account,
+            "CLOSE",
+            name,
+            vec![leg],
+            // Closing a short pays, closing a long is paid.
+            if short {
+                PriceEffect::Debit
+            } else {
+                PriceEffect::Credit
+            },
+            Some(held),
+            mid,
+        ))
+    }
+
+    /// A ticket closing every leg of a strategy at once, `units` of it
+    /// held, at `mark`: the net mark of one unit, negative for a credit
+    /// strategy.
+    pub fn closing_strategy(
+        account: String,
+        name: String,
+        legs: Vec<Leg>,
+        units: Decimal,
+        mark: Decimal,
+    ) -> Result<Self> {
+        if legs.len() > 4 {
+            bail!(
+                "{} has {} option legs; tastytrade takes at most 4 in one order",
+                name,
+                legs.len()
+            );
+        }
+        if let Some(leg) = legs
+            .iter()
+            .find(|leg| !matches!(leg.instrument_type, InstrumentType::EquityOption))
+        {
+            bail!("{}: only equity options can be closed from here", leg.name);
+        }
+        Ok(Self::new(
+            account,
+            "CLOSE",
+            name,
+            legs,
+            // Selling a net long is paid, buying back a net short pays.
+            if mark > Decimal::ZERO {
+                PriceEffect::Credit
+            } else {
+                PriceEffect::Debit
+            },
+            Some(units),
+            mark.abs(),
+        ))
+    }
+
+    /// A ticket buying, or selling, one equity option at `mid` to open a
+    /// position.
+    pub fn opening(account: String, symbol: Symbol, sell: bool, mid: Decimal) -> Self {
+        let leg = Leg {
+            name: symbol.0.clone(),
+            symbol: symbol.clone(),
+            instrument_type: InstrumentType::EquityOption,
+            action: if sell {
+                Action::SellToOpen
+            } else {
+                Action::BuyToOpen
+            },
+            ratio: Decimal::ONE,
+        };
+        Self::new(
+            account,
+            "OPEN",
+            symbol.0,
+            vec![leg],
+            if sell {
+                PriceEffect::Credit
+            } else {
+                PriceEffect::Debit
+            },
+            None,
+            mid,
+        )
+    }
+
+    /// A ticket buying back the short option of `roll` and selling `to` in
+    /// its place, at the difference of their mids. `underlying_price` is for
+    /// warning that an option in the money may be assigned first.
+    pub fn roll(
+        roll: Roll,
+        to: &Contract,
+        to_mid: Decimal,
+        underlying_price: Option<Decimal>,
+    ) -> Result<Self> {
+        if to.symbol == roll.symbol {
+            bail!("That is the option being rolled; pick another strike or expiration");
+        }
+        if to_mid <= Decimal::ZERO {
+            bail!(
+                "{} isn't quoted; pick a more liquid contract",
+                to.symbol.0.trim()
+            );
+        }
+        let net = to_mid - roll.mid;
+        let legs = vec![
+            Leg {
+                symbol: roll.symbol,
+                name: roll.name.trim().to_owned(),
+                instrument_type: InstrumentType::EquityOption,
+                action: Action::BuyToClose,
+                ratio: Decimal::ONE,
+            },
+            Leg {
+                symbol: to.symbol.clone(),
+                name: to.symbol.0.clone(),
+                instrument_type: InstrumentType::EquityOption,
+                action: Action::SellToOpen,
+                ratio: Decimal::ONE,
+            },
+        ];
+        let mut ticket = Self::new(
+            roll.account,
+            "ROLL",
+            roll.name,
+            legs,
+            if net < Decimal::ZERO {
+                PriceEffect::Debit
+            } else {
+                PriceEffect::Credit
+            },
+            Some(roll.held),
+            net.abs(),
+        );
+        ticket.notes.push(format!(
+            "Mids: {} to close, {} to open",
+            roll.mid.round_dp(2),
+            to_mid.round_dp(2)
+        ));
+        let option = &roll.option;
+        let in_the_money = underlying_price.map(|price| match option.kind {
+            OptionKind::Call => price > option.strike,
+            OptionKind::Put => price < option.strike,
+        });
+        match in_the_money {
+            Some(true) => ticket.notes.push(format!(
+                "The short {} is in the money (underlying {}); it may be assigned before the roll fills",
+                option.strike.normalize(),
+                underlying_price.unwrap_or_default().round_dp(2)
+            )),
+            Some(false) => {}
+            None => ticket
+                .notes
+                .push("The underlying has no price, so being in the money isn't checked".to_owned()),
+        }
+        Ok(ticket)
+    }
+
+    fn new(
+        account: String,
+        title: &'static str,
+        name: String,
+        legs: Vec<Leg>,
+        price_effect: PriceEffect,
+        held: Option<Decimal>,
+        price: Decimal,
+    ) -> Self {
+        let mut ticket = Self {
+            account,
+            title,
+            name,
+            legs,
+            price_effect,
+            held,
+            quantity: held.unwrap_or(Decimal::ONE),
+            price,
+            field: Field::Price,
+            typed: None,
+            stage: Stage::Editing,
+            notes: vec![],
+            permission: None,
+        };
+        ticket.price = ticket.round_to_tick(price);
+        ticket
+    }
+
+    /// Has the order checked against what the account may trade before
+    /// it is dry run. `shares` are the account's shares of the underlying.
+    pub fn permit(&mut self, permission: Permission, shares: Decimal) {
+        self.permission = Some((permission, shares));
+    }
+
+    /// The legs opening positions, as the permission rules see them.
+    /// Closing legs only take risk away, so they are left out.
+    fn opening_legs(&self, quantity: Decimal) -> Vec<permissions::Leg> {
+        self.legs
+            .iter()
+            .filter_map(|leg| {
+                let signed = match leg.action {
+                    Action::BuyToOpen => quantity * leg.ratio,
+                    Action::SellToOpen => -quantity * leg.ratio,
+                    _ => return None,
+                };
+                match leg.instrument_type {
+                    InstrumentType::Equity => Some(permissions::Leg::Shares(signed)),
+                    InstrumentType::EquityOption => {
+                        Some(match OptionSymbol::parse(&leg.symbol.0)?.kind {
+                            OptionKind::Call => permissions::Leg::Call(signed),
+                            OptionKind::Put => permissions::Leg::Put(signed),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Single options trade in nickels from $3 up; shares and spreads
+    /// trade in pennies.
+    fn tick(&self) -> Decimal {
+        let single_option = match self.legs.as_slice() {
+            [leg] => matches!(leg.instrument_type, InstrumentType::EquityOption),
+            _ => false,
+        };
+        if single_option && self.price >= Decimal::from(3) {
+            Decimal::new(5, 2)
+        } else {
+            Decimal::new(1, 2)
+        }
+    }
+
+    fn round_to_tick(&self, price: Decimal) -> Decimal {
+        let tick = self.tick();
+        ((price / tick).round() * tick).max(tick)
+    }
+
+    pub fn price_effect(&self) -> PriceEffect {
+        self.price_effect.clone()
+    }
+
+    /// The legs joined up, like `BuyToClose 1 SPY 470 Put, SellToOpen 1
+    /// SPY   240216P00470000`.
+    fn describe(&self, quantity: Decimal) -> String {
+        let legs: Vec<String> = self
+            .legs
+            .iter()
+            .map(|leg| {
+                format!(
+                    "{:?} {} {}",
+                    leg.action,
+                    (quantity * leg.ratio).normalize(),
+                    leg.name
+                )
+            })
+            .collect();
+        legs.join(", ")
+    }
+
+    /// Handles a key while the ticket is open. Every key goes to the
+    /// ticket; Esc while editing is the only way out without sending.
+    /// Returns whether the ticket stays open, and the request to make.
+    pub fn key(&mut self, code: KeyCode) -> Result<(bool, Option<Request>)> {
+        match self.stage.clone() {
+            Stage::Editing => self.edit_key(code),
+            Stage::Confirming {
+                quantity,
+                price,
+                confirmation,
+            } => match confirmation.key(code) {
+                Answer::Send => {
+                    let order = self.order(quantity, price)?;
+                    // From here the ticket only closes; it never goes back
+                    // to editing an order that may have been placed.
+                    self.stage = Stage::Sending;
+                    let details = OrderDetails {
+                        action: OrderAction::Submit,
+                        account: self.account.clone(),
+                        symbol: Some(
+                            self.legs
+                                .iter()
+                                .map(|leg| leg.symbol.0.as_str())
+                                .collect::<Vec<_>>()
+                                .join(" / "),
+                        ),
+                        quantity: Some(quantity),
+                        price: Some(price),
+                        buying_power_effect: confirmation.buying_power_effect(),
+                        order_id: None,
+                    };
+                    Ok((true, Some(Request::Send(order, details))))
+                }
+                Answer::Back => {
+                    self.stage = Stage::Editing;
+                    Ok((true, None))
+                }
+                Answer::Wait => Ok((true, None)),
+            },
+            // An order being sent is left to finish.
+            Stage::Sending => Ok((true, None)),
+        }
+    }
+
+    fn edit_key(&mut self, code: KeyCode) -> Result<(bool, Option<Request>)> {
+        match code {
+            KeyCode::Esc => return Ok((false, None)),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.apply_typed()?;
+                self.field = match self.field {
+                    Field::Quantity => Field::Price,
+                    Field::Price => Field::Quantity,
+                };
+            }
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => {
+                self.apply_typed()?;
+                let up = matches!(code, KeyCode::Up | KeyCode::Right);
+                match self.field {
+                    Field::Price => {
+                        let tick = self.tick();
+                        let price = if up {
+                            self.price + tick
+                        } else {
+                            self.price - tick
+                        };
+                        self.price = self.round_to_tick(price);
+                    }
+                    Field::Quantity => {
+                        let quantity = if up {
+                            self.quantity + Decimal::ONE
+                        } else {
+                            self.quantity - Decimal::ONE
+                        };
+                        self.quantity = match self.held {
+                            Some(held) => quantity.clamp(Decimal::ONE.min(held), held),
+                            None => quantity.max(Decimal::ONE),
+                        };
+                    }
+                }
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
+                self.typed.get_or_insert_with(String::new).push(c);
+            }
+            KeyCode::Backspace => {
+                if let Some(typed) = &mut self.typed {
+                    typed.pop();
+                }
+            }
+            KeyCode::Enter => {
+                self.apply_typed()?;
+                match self.held {
+                    Some(held) if self.quantity <= Decimal::ZERO || self.quantity > held => {
+                        bail!("Quantity must be between 1 and {}", held.normalize())
+                    }
+                    None if self.quantity <= Decimal::ZERO => bail!("Quantity must be above zero"),
+                    _ => {}
+                }
+                if self.price <= Decimal::ZERO {
+                    bail!("Price must be above zero");
+                }
+                if let Some((permission, shares)) = self.permission {
+                    permissions::check(permission, &self.opening_legs(self.quantity), shares)
+                        .map_err(anyhow::Error::msg)?;
+                }
+                self.stage = Stage::Confirming {
+                    quantity: self.quantity,
+                    price: self.price,
+                    confirmation: Confirmation::new(format!(
+                        "{} @ {} {:?}",
+                        self.describe(self.quantity),
+                        self.price,
+                        self.price_effect()
+                    )),
+                };
+                return Ok((
+                    true,
+                    Some(Request::DryRun(self.order(self.quantity, self.price)?)),
+                ));
+            }
+            _ => {}
+        }
+        Ok((true, None))
+    }
+
+    /// Puts what was typed into the selected field.
+    fn apply_typed(&mut self) -> Result<()> {
+        let typed = match self.typed.take() {
+            Some(typed) if !typed.is_empty() => typed,
+            _ => return Ok(()),
+        };
+        let value =
+            Decimal::from_str(&typed).with_context(|| format!("Not a number: {}", typed))?;
+        match self.field {
+            Field::Quantity => self.quantity = value,
+            Field::Price => self.price = value,
+        }
+        Ok(())
+    }
+
+    /// Takes in the dry run of the order being confirmed, unless the
+    /// ticket has moved on since it was asked for.
+    pub fn checked(&mut self, dry_run: DryRun) {
+        if let Stage::Confirming { confirmation, .. } = &mut self.stage {
+            if let DryRun::Pending = confirmation.dry_run {
+                confirmation.dry_run = dry_run;
+            }
+        }
+    }
+
+    fn order(&self, quantity: Decimal, price: Decimal) -> Result<Order> {
+        let legs = self
+            .legs
+            .iter()
+            .map(|leg| {
+                OrderLegBuilder::default()
+                    .instrument_type(leg.instrument_type.clone())
+                    .symbol(leg.symbol.clone())
+                    .quantity(quantity * leg.ratio)
+                    .action(leg.action.clone())
+                    .build()
+                    .context("Building the order leg")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        OrderBuilder::default()
+            .time_in_force(TimeInForce::Day)
+            .order_type(OrderType::Limit)
+            .price(price)
+            .price_effect(self.price_effect())
+            .legs(legs)
+            .build()
+            .context("Building the order")
+    }
+}
+
+/// Asks tastytrade what `order` would do without placing it. tastytrade
+/// refusing the order is a `DryRun::Rejected`; an error means the dry run
+/// couldn't be made at all.
+pub async fn dry_run(
+    guard: readonly::Guard,
+    account: &Account<'_>,
+    order: Order,
+) -> Result<DryRun> {
+    guard.check("checking orders")?;
+    let result = match account.dry_run(&order).await {
+        Ok(result) => result,
+        Err(TastyError::Api(error)) => return Ok(DryRun::Rejected(format!("{:?}", error))),
+        Err(e) => return Err(e).context("Checking the order"),
+    };
+    let signed = |amount: Decimal, effect: &PriceEffect| match effect {
+        PriceEffect::Debit => -amount,
+        _ => amount,
+    };
+    let effect = &result.buying_power_effect;
+    let fees = &result.fee_calculation;
+    Ok(DryRun::Passed {
+        buying_power_effect: signed(
+            effect.change_in_buying_power,
+            &effect.change_in_buying_power_effect,
+        ),
+        fees: Some(signed(fees.total_fees, &fees.total_fees_effect)),
+        warnings: result
+            .warnings
+            .iter()
+            .map(|warning| format!("{:?}", warning))
+            .collect(),
+    })
+}
+
+/// Places `order` through the trade log, returning the new order's id and
+/// a warning if the placed order couldn't be logged.
+pub async fn send(
+    guard: readonly::Guard,
+    account: &Account<'_>,
+    log: TradeLog,
+    order: Order,
+    details: OrderDetails,
+) -> Result<(String, Option<String>)> {
+    guard.check("placing orders")?;
+    let call = async {
+        account
+            .place_order(&order)
+            .await
+            .context("Placing the order")
+    };
+    let (placed, warning) = log
+        .record(details, call, |placed| Some(orders::id(&placed.order)))
+        .await?;
+    Ok((orders::id(&placed.order), warning))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(symbol: &str, short: bool, ratio: i64) -> Leg {
+        Leg::closing(
+            Symbol(symbol.to_owned()),
+            symbol,
+            InstrumentType::EquityOption,
+            short,
+            Decimal::from(ratio),
+        )
+    }
+
+    #[test]
+    fn strategy_closes_every_leg_in_its_ratio() {
+        // Two units of a put ratio spread: long 2, short 4.
+        let ticket = Ticket::closing_strategy(
+            "5WT00001".to_owned(),
+            "SPY strategy".to_owned(),
+            vec![
+                leg("SPY   240119P00470000", false, 1),
+                leg("SPY   240119P00460000", true, 2),
+            ],
+            Decimal::TWO,
+            Decimal::new(-135, 2),
+        )
+        .unwrap();
+        assert_eq!(ticket.quantity, Decimal::TWO);
+        assert_eq!(ticket.price, Decimal::new(135, 2));
+        assert!(matches!(ticket.price_effect(), PriceEffect::Debit));
+        assert_eq!(
+            ticket.describe(ticket.quantity),
+            "SellToClose 2 SPY   240119P00470000, BuyToClose 4 SPY   240119P00460000"
+        );
+        assert!(ticket.opening_legs(ticket.quantity).is_empty());
+
+        let condor = Ticket::closing_strategy(
+            "5WT00001".to_owned(),
+            "SPY strategy".to_owned(),
+            vec![
+                leg("SPY   240119P00450000", false, 1),
+                leg("SPY   240119P00460000", true, 1),
+                leg("SPY   240119C00480000", true, 1),
+                leg("SPY   240119C00490000", false, 1),
+            ],
+            Decimal::ONE,
+            Decimal::new(80, 2),
+        )
+        .unwrap();
+        assert!(matches!(condor.price_effect(), PriceEffect::Credit));
+
+        let five = (0..5)
+            .map(|strike| leg(&format!("SPY   240119C0048{}000", strike), false, 1))
+            .collect();
+        assert!(Ticket::closing_strategy(
+            "5WT00001".to_owned(),
+            "SPY strategy".to_owned(),
+            five,
+            Decimal::ONE,
+            Decimal::ONE,
+        )
+        .is_err());
+    }
+}
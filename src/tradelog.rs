@@ -0,0 +1,141 @@
+use std::{
+    fs::{self, OpenOptions},
+    future::Future,
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::{config::Config, state};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderAction {
+    Submit,
+    Cancel,
+}
+
+/// Where in its life an order action was when the line was written.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Requested,
+    Succeeded,
+    Failed,
+}
+
+/// What is known about an order action before it is sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderDetails {
+    pub action: OrderAction,
+    pub account: String,
+    pub symbol: Option<String>,
+    pub quantity: Option<Decimal>,
+    pub price: Option<Decimal>,
+    /// Change in buying power from the dry run, if one was made.
+    pub buying_power_effect: Option<Decimal>,
+    /// Set for replacements and cancellations, which act on an existing
+    /// order.
+    pub order_id: Option<String>,
+}
+
+/// One line of the trade log.
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    timestamp: DateTime<Utc>,
+    stage: Stage,
+    #[serde(flatten)]
+    details: &'a OrderDetails,
+    /// The error for failed actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+}
+
+/// An append-only JSONL record of every order action taken from the TUI,
+/// kept apart from tastytrade's own history.
+#[derive(Clone)]
+pub struct TradeLog {
+    path: PathBuf,
+}
+
+impl TradeLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The log at the configured path, or in the profile's data directory
+    /// by default.
+    pub fn from_config(config: &Config, profile: Option<&str>) -> Self {
+        let path = config
+            .trade_log
+            .clone()
+            .or_else(|| state::data_dir(profile).map(|dir| dir.join("trade-log.jsonl")))
+            .unwrap_or_else(|| PathBuf::from("trade-log.jsonl"));
+        Self::new(path)
+    }
+
+    fn append(&self, line: &Line) -> Result<()> {
+        let context = || format!("Writing trade log {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(context)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(context)?;
+        let mut text = serde_json::to_string(line)?;
+        text.push('\n');
+        file.write_all(text.as_bytes()).with_context(context)?;
+        file.sync_data().with_context(context)
+    }
+
+    /// Runs an order action, recording it before it is sent and again with
+    /// its outcome. If the first line can't be written the action is not
+    /// taken at all. `order_id` picks the id of the order out of a
+    /// successful response. The action's own outcome is returned whether
+    /// or not the second line is written; failing to write it comes back
+    /// as a warning alongside a success, and is added to a failure.
+    pub async fn record<T>(
+        &self,
+        mut details: OrderDetails,
+        call: impl Future<Output = Result<T>>,
+        order_id: impl FnOnce(&T) -> Option<String>,
+    ) -> Result<(T, Option<String>)> {
+        self.append(&Line {
+            timestamp: Utc::now(),
+            stage: Stage::Requested,
+            details: &details,
+            result: None,
+        })
+        .context("Not sending the order because it couldn't be logged")?;
+
+        let outcome = call.await;
+        let (stage, result) = match &outcome {
+            Ok(response) => {
+                if let Some(id) = order_id(response) {
+                    details.order_id = Some(id);
+                }
+                (Stage::Succeeded, None)
+            }
+            Err(e) => (Stage::Failed, Some(format!("{:#}", e))),
+        };
+        let logged = self
+            .append(&Line {
+                timestamp: Utc::now(),
+                stage,
+                details: &details,
+                result,
+            })
+            .map_err(|e| format!("The outcome couldn't be logged: {:#}", e));
+        match (outcome, logged) {
+            (Ok(response), logged) => Ok((response, logged.err())),
+            (Err(e), Ok(())) => Err(e),
+            (Err(e), Err(warning)) => Err(e.context(warning)),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::{prelude::Zero, Decimal};
+use serde::{Deserialize, Serialize};
+use tastytrade_rs::TastyTrade;
+
+const PAGE_SIZE: usize = 250;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Transaction {
+    #[serde(default)]
+    pub instrument_type: Option<String>,
+    #[serde(default)]
+    pub underlying_symbol: Option<String>,
+    /// "Buy to Open", "Sell to Close" and so on, for trades.
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub price: Option<Decimal>,
+    pub executed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub net_value: Decimal,
+    /// "Credit", "Debit" or "None".
+    #[serde(default)]
+    pub net_value_effect: String,
+}
+
+impl Transaction {
+    pub fn is_option(&self) -> bool {
+        matches!(
+            self.instrument_type.as_deref(),
+            Some("Equity Option" | "Future Option")
+        )
+    }
+
+    pub fn is_opening(&self) -> bool {
+        self.action
+            .as_deref()
+            .map_or(false, |action| action.ends_with("to Open"))
+    }
+
+    pub fn signed_value(&self) -> Decimal {
+        match self.net_value_effect.as_str() {
+            "Credit" => self.net_value,
+            "Debit" => -self.net_value,
+            _ => Decimal::zero(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    items: Vec<Transaction>,
+}
+
+/// Downloads the transactions of `accounts` executed since `since`.
+pub async fn fetch(
+    tasty: &TastyTrade,
+    accounts: Vec<String>,
+    since: NaiveDate,
+) -> Result<Vec<Transaction>> {
+    let mut transactions = vec![];
+    for account in accounts {
+        for offset in 0.. {
+            let url = format!(
+                "/accounts/{}/transactions?start-date={}&per-page={}&page-offset={}",
+                account, since, PAGE_SIZE, offset
+            );
+            let page: Page = tasty
+                .get(url)
+                .await
+                .with_context(|| format!("Downloading transactions of {}", account))?;
+            let done = page.items.len() < PAGE_SIZE;
+            transactions.extend(page.items);
+            if done {
+                break;
+            }
+        }
+    }
+    Ok(transactions)
+}
+
+/// The underlying's price when a position on it was first opened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenReference {
+    pub price: Decimal,
+    pub date: NaiveDate,
+    /// Whether the price is the day's close, standing in for underlyings
+    /// only traded through options, rather than a trade in the shares.
+    #[serde(default)]
+    pub from_close: bool,
+}
+
+/// For each underlying, the price of the earliest opening trade in its
+/// shares. Only share trades say what the underlying traded at, so
+/// underlyings only ever traded through options are missing.
+pub fn opening_prices(transactions: &[Transaction]) -> BTreeMap<String, OpenReference> {
+    let mut opened: BTreeMap<String, (DateTime<Utc>, Decimal)> = BTreeMap::new();
+    let share_opens = transactions
+        .iter()
+        .filter(|t| t.is_opening() && t.instrument_type.as_deref() == Some("Equity"));
+    for transaction in share_opens {
+        let (underlying, price) = match (&transaction.underlying_symbol, transaction.price) {
+            (Some(underlying), Some(price)) => (underlying, price),
+            _ => continue,
+        };
+        let earliest = opened
+            .entry(underlying.clone())
+            .or_insert((transaction.executed_at, price));
+        if transaction.executed_at < earliest.0 {
+            *earliest = (transaction.executed_at, price);
+        }
+    }
+    opened
+        .into_iter()
+        .map(|(underlying, (executed_at, price))| {
+            let date = executed_at.date_naive();
+            let reference = OpenReference {
+                price,
+                date,
+                from_close: false,
+            };
+            (underlying, reference)
+        })
+        .collect()
+}
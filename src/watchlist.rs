@@ -0,0 +1,143 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use tastytrade_rs::{
+    api::{
+        order::{InstrumentType, Symbol},
+        quote_streaming::DxFeedSymbol,
+    },
+    TastyTrade,
+};
+
+use crate::option_symbol::{self, OptionSymbol};
+
+/// A line of a watch file.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub symbol: Symbol,
+    pub label: Option<String>,
+}
+
+/// Reads a watch file: one symbol per line, optionally followed by a label.
+/// Blank lines and anything after a `#` are ignored. Option symbols are
+/// written without the spaces of OCC format.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+    Ok(contents.lines().filter_map(entry).collect())
+}
+
+/// Reads a line of a watch file, or a symbol given with `--watch`.
+pub fn entry(line: &str) -> Option<Entry> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    let (symbol, label) = match line.split_once(char::is_whitespace) {
+        Some((symbol, label)) => (symbol, Some(label.trim().to_owned())),
+        None => (line, None),
+    };
+    let symbol = symbol.to_uppercase();
+    (!symbol.is_empty()).then(|| Entry {
+        symbol: Symbol(option_symbol::occ(&symbol).unwrap_or(symbol)),
+        label,
+    })
+}
+
+/// A watch file with the streamer symbol of each entry.
+pub struct Resolved {
+    pub entries: Vec<(Entry, DxFeedSymbol)>,
+    /// Symbols that couldn't be resolved and were left out.
+    pub warnings: Vec<String>,
+}
+
+/// Reads the watch file at `path`, if any, after the entries `listed` on
+/// the command line, and looks up the streamer symbols of the entries not
+/// already in `known`. A symbol listed twice is kept where it first
+/// appears.
+pub async fn load(
+    tasty: &TastyTrade,
+    listed: &[Entry],
+    path: Option<&Path>,
+    known: BTreeMap<Symbol, DxFeedSymbol>,
+) -> Result<Resolved> {
+    let mut entries = listed.to_vec();
+    if let Some(path) = path {
+        entries.extend(read(path)?);
+    }
+    let mut seen = BTreeSet::new();
+    entries.retain(|entry| seen.insert(entry.symbol.clone()));
+    let lookups = entries
+        .iter()
+        .filter(|entry| !known.contains_key(&entry.symbol))
+        .map(|entry| async {
+            let stream_symbol = tasty
+                .get_streamer_symbol(&instrument_type(&entry.symbol), &entry.symbol)
+                .await;
+            (entry.symbol.clone(), stream_symbol)
+        });
+    let mut resolved = known;
+    let mut warnings = vec![];
+    for (symbol, stream_symbol) in futures::future::join_all(lookups).await {
+        match stream_symbol {
+            Ok(stream_symbol) => {
+                resolved.insert(symbol, stream_symbol);
+            }
+            Err(e) => warnings.push(format!("Watching {}: {}", symbol.0, e)),
+        }
+    }
+    let entries = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let stream_symbol = resolved.get(&entry.symbol)?.clone();
+            Some((entry, stream_symbol))
+        })
+        .collect();
+    Ok(Resolved { entries, warnings })
+}
+
+/// Futures start with a slash; options are in OCC format.
+fn instrument_type(symbol: &Symbol) -> InstrumentType {
+    if symbol.0.starts_with('/') {
+        InstrumentType::Future
+    } else if OptionSymbol::parse(&symbol.0).is_some() {
+        InstrumentType::EquityOption
+    } else {
+        InstrumentType::Equity
+    }
+}
+
+/// Checks that a symbol typed in to watch could be one, before it is
+/// looked up.
+pub fn check(entry: &Entry) -> Result<()> {
+    let symbol = &entry.symbol.0;
+    let valid = symbol.len() <= 32
+        && symbol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | ' '));
+    if !valid {
+        bail!("{} isn't a symbol", symbol.trim());
+    }
+    Ok(())
+}
+
+/// Looks up the streamer symbol of one symbol to watch.
+pub async fn resolve(tasty: &TastyTrade, symbol: &Symbol) -> Result<DxFeedSymbol> {
+    tasty
+        .get_streamer_symbol(&instrument_type(symbol), symbol)
+        .await
+        .with_context(|| format!("{} wasn't found", symbol.0.trim()))
+}
+
+/// Adds `symbol` to the end of the watch file at `path`.
+pub fn append(path: &Path, symbol: &Symbol) -> Result<()> {
+    let context = || format!("Adding {} to {}", symbol.0.trim(), path.display());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(context)?;
+    writeln!(file, "{}", symbol.0.replace(' ', "")).with_context(context)
+}